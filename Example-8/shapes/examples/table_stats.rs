@@ -0,0 +1,78 @@
+//! Measure the memory and time of the area and perimeter kernels for shapes
+//! held in a `Vec<KnownShape>` or a `ShapeTable`.
+//!
+//! Usage: `cargo run --release --example table_stats -- (vec|table) FILE`
+//!
+//! Run each mode in its own process, since the peak memory (`VmHWM`) is
+//! that of the whole process.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::time::Instant;
+
+use shapes::factory::ShapeReader;
+use shapes::known_shape::KnownShape;
+use shapes::shape::Shape;
+use shapes::table::ShapeTable;
+
+/// Peak resident memory of this process, as reported by Linux
+fn peak_memory() -> String {
+    std::fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|status| {
+            status.lines()
+                .find(|line| line.starts_with("VmHWM:"))
+                .map(|line| line["VmHWM:".len()..].trim().to_string())
+        })
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.len() != 3 || !["vec", "table"].contains(&args[1].as_str()) {
+        eprintln!("Usage: {} (vec|table) FILE", args[0]);
+        std::process::exit(1);
+    }
+
+    let file = File::open(&args[2]).unwrap_or_else(|err| {
+        eprintln!("{}: {}", args[2], err);
+        std::process::exit(1);
+    });
+    let reader = ShapeReader::new(BufReader::new(file));
+
+    let start = Instant::now();
+    let (count, read_time, kernel_time, area, perimeter) = match args[1].as_str() {
+        "vec" => {
+            let shapes: Vec<KnownShape> = reader.collect();
+            let read_time = start.elapsed();
+
+            let start = Instant::now();
+            let areas: Vec<f64> = shapes.iter().map(|s| s.area()).collect();
+            let perimeters: Vec<f64> = shapes.iter().map(|s| s.perimeter()).collect();
+
+            (shapes.len(), read_time, start.elapsed(),
+             areas.iter().sum::<f64>(), perimeters.iter().sum::<f64>())
+        }
+        _ => {
+            let mut table: ShapeTable = reader.collect();
+            table.shrink_to_fit();
+            let read_time = start.elapsed();
+
+            let start = Instant::now();
+            let areas = table.areas();
+            let perimeters = table.perimeters();
+
+            (table.len(), read_time, start.elapsed(),
+             areas.iter().sum::<f64>(), perimeters.iter().sum::<f64>())
+        }
+    };
+
+    println!("{:<16}{:>12}", "Mode", args[1]);
+    println!("{:<16}{:>12}", "Shapes", count);
+    println!("{:<16}{:>12.4}", "Area sum", area);
+    println!("{:<16}{:>12.4}", "Perimeter sum", perimeter);
+    println!("{:<16}{:>12.3?}", "Read time", read_time);
+    println!("{:<16}{:>12.3?}", "Kernel time", kernel_time);
+    println!("{:<16}{:>12}", "Max memory", peak_memory());
+}
@@ -0,0 +1,253 @@
+//! Derive macro for the `KnownShape` enum in the `shapes` crate.
+//!
+//! Every variant of the enum wraps exactly one shape type and is annotated
+//! with a `#[shape(...)]` attribute:
+//!
+//! ```ignore
+//! #[derive(ShapeEnum)]
+//! pub enum KnownShape {
+//!     #[shape(name = "Circle", params(radius))]
+//!     Circle(Circle),
+//! }
+//! ```
+//!
+//!   * `name` - the name used by the factory (defaults to the variant name)
+//!   * `params` - the struct fields that are filled, in order, from a
+//!     `&[f64]` of dimensions
+//!
+//! The derive generates:
+//!
+//!   * `Shape` and `Display` for the enum, delegating to each variant
+//!   * `From<T> for KnownShape` for each wrapped type
+//!   * `From<&[f64]> for T` for each wrapped type (which, like indexing,
+//!     panics if there are too few dimensions)
+//!   * `KNOWN_NAMES`, `from_name`, `from_name_with` and `from_name_params`,
+//!     which the factory uses to register and create shapes
+//!   * `PARAM_NAMES`, `PARAM_COUNTS`, `kind_index` and `params`, the inverse
+//!     of `from_name_with`, used by serializers
+//!
+//! Each wrapped type must implement `Default` and `FromNamedParams`. The
+//! generated code refers to `crate::shape::Shape` and
+//! `crate::named_params`, so the derive is only usable inside the `shapes`
+//! crate.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident, LitStr, Type};
+
+/// One enum variant and the information from its `#[shape]` attribute
+struct ShapeVariant {
+    variant: Ident,
+    ty: Type,
+    name: String,
+    params: Vec<Ident>,
+}
+
+#[proc_macro_derive(ShapeEnum, attributes(shape))]
+pub fn derive_shape_enum(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Enum(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "ShapeEnum can only be derived for enums",
+        ));
+    };
+
+    let variants = data
+        .variants
+        .iter()
+        .map(parse_variant)
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let enum_name = &input.ident;
+    let idents: Vec<_> = variants.iter().map(|v| &v.variant).collect();
+    let types: Vec<_> = variants.iter().map(|v| &v.ty).collect();
+    let names: Vec<_> = variants.iter().map(|v| &v.name).collect();
+
+    let param_counts: Vec<_> = variants.iter().map(|v| v.params.len()).collect();
+    let param_names = variants.iter().map(|v| {
+        let names = v.params.iter().map(|p| p.to_string());
+        quote! { &[#(#names),*] }
+    });
+    let indices = 0..variants.len();
+    let params: Vec<_> = variants.iter().map(|v| &v.params).collect();
+    let param_lists = params.iter().map(|fields| quote! { #(s.#fields),* });
+
+    let from_dims = variants.iter().map(|v| {
+        let ty = &v.ty;
+        let fields = &v.params;
+        let indices = 0..fields.len();
+
+        quote! {
+            impl ::std::convert::From<&[f64]> for #ty {
+                fn from(dims: &[f64]) -> Self {
+                    #ty { #(#fields: dims[#indices]),* }
+                }
+            }
+        }
+    });
+
+    Ok(quote! {
+        impl ::std::fmt::Display for #enum_name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                match self {
+                    #(#enum_name::#idents(s) => write!(f, "{}", s),)*
+                }
+            }
+        }
+
+        impl crate::shape::Shape for #enum_name {
+            fn name(&self) -> &'static str {
+                match self {
+                    #(#enum_name::#idents(s) => crate::shape::Shape::name(s),)*
+                }
+            }
+
+            fn area(&self) -> f64 {
+                match self {
+                    #(#enum_name::#idents(s) => crate::shape::Shape::area(s),)*
+                }
+            }
+
+            fn perimeter(&self) -> f64 {
+                match self {
+                    #(#enum_name::#idents(s) => crate::shape::Shape::perimeter(s),)*
+                }
+            }
+
+            fn fields(&self) -> ::std::vec::Vec<(&'static str, f64)> {
+                match self {
+                    #(#enum_name::#idents(s) => crate::shape::Shape::fields(s),)*
+                }
+            }
+        }
+
+        #(
+            impl ::std::convert::From<#types> for #enum_name {
+                fn from(item: #types) -> Self {
+                    #enum_name::#idents(item)
+                }
+            }
+        )*
+
+        #(#from_dims)*
+
+        impl #enum_name {
+            /// Names of all variants, in declaration order
+            pub const KNOWN_NAMES: &'static [&'static str] = &[#(#names),*];
+
+            /// Field names of the `params` of each variant, in declaration
+            /// order
+            pub const PARAM_NAMES: &'static [&'static [&'static str]] =
+                &[#(#param_names),*];
+
+            /// Number of `params` of each variant, in declaration order
+            pub const PARAM_COUNTS: &'static [usize] = &[#(#param_counts),*];
+
+            /// Position of this shape's variant in `KNOWN_NAMES`
+            pub fn kind_index(&self) -> usize {
+                match self {
+                    #(#enum_name::#idents(_) => #indices,)*
+                }
+            }
+
+            /// The dimensions that `from_name_with` would need to recreate
+            /// this shape
+            pub fn params(&self) -> ::std::vec::Vec<f64> {
+                match self {
+                    #(#enum_name::#idents(s) => ::std::vec![#param_lists],)*
+                }
+            }
+
+            /// Create a default shape by name
+            pub fn from_name(name: &str) -> ::std::option::Option<Self> {
+                match name {
+                    #(#names => ::std::option::Option::Some(
+                        <#types as ::std::default::Default>::default().into()
+                    ),)*
+                    _ => ::std::option::Option::None,
+                }
+            }
+
+            /// Create a shape by name from a list of dimensions, or `None`
+            /// if the name is unknown or `dims` does not hold exactly one
+            /// value per parameter
+            pub fn from_name_with(name: &str, dims: &[f64])
+                -> ::std::option::Option<Self> {
+                match name {
+                    #(#names if dims.len() == #param_counts => {
+                        ::std::option::Option::Some(<#types>::from(dims).into())
+                    })*
+                    _ => ::std::option::Option::None,
+                }
+            }
+
+            /// Create a shape by name from named parameters
+            pub fn from_name_params(
+                name: &str,
+                params: &crate::named_params::NamedParams,
+            ) -> ::std::option::Option<Self> {
+                match name {
+                    #(#names => <#types as crate::named_params::FromNamedParams>
+                        ::from_named(params)
+                        .map(::std::convert::Into::into),)*
+                    _ => ::std::option::Option::None,
+                }
+            }
+        }
+    })
+}
+
+fn parse_variant(variant: &syn::Variant) -> syn::Result<ShapeVariant> {
+    let ty = match &variant.fields {
+        Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+            fields.unnamed[0].ty.clone()
+        }
+        _ => {
+            return Err(syn::Error::new_spanned(
+                variant,
+                "ShapeEnum variants must wrap exactly one shape",
+            ))
+        }
+    };
+
+    let mut name = variant.ident.to_string();
+    let mut params = Vec::new();
+
+    for attr in variant.attrs.iter().filter(|a| a.path().is_ident("shape")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                name = meta.value()?.parse::<LitStr>()?.value();
+                Ok(())
+            }
+            else if meta.path.is_ident("params") {
+                meta.parse_nested_meta(|param| {
+                    let field = param.path.get_ident().ok_or_else(|| {
+                        param.error("expected a field name")
+                    })?;
+                    params.push(field.clone());
+                    Ok(())
+                })
+            }
+            else {
+                Err(meta.error("expected `name` or `params`"))
+            }
+        })?;
+    }
+
+    Ok(ShapeVariant {
+        variant: variant.ident.clone(),
+        ty,
+        name,
+        params,
+    })
+}
@@ -0,0 +1,410 @@
+//! An indexed binary container for shapes.
+//!
+//! Parsing the text format is the slowest part of handling large inputs. This
+//! format stores each shape's dimensions as raw `f64`s (so conversion is
+//! lossless) and ends with an index of record offsets, so a memory-mapped
+//! file supports random access without decoding anything else.
+//!
+//! All integers and floats are little-endian.
+//!
+//! ```text
+//! Header
+//!     0   magic     [u8; 8]       "SHAPEBIN"
+//!     8   version   u16           1
+//!    10   header    u16           size of the header in bytes
+//!    12   kinds     u32           number of per-kind counts
+//!    16   count     u64           number of shapes
+//!    24   index     u64           byte offset of the index
+//!    32   counts    [u64; kinds]  number of shapes of each kind
+//!
+//! Records (one per shape, starting right after the header)
+//!         tag       u8            position of the kind in KNOWN_NAMES
+//!         dims      [f64; n]      the kind's parameters (PARAM_COUNTS[tag])
+//!
+//! Index
+//!         offsets   [u64; count]  byte offset of each record
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::known_shape::KnownShape;
+
+/// First bytes of every binary shape file
+pub const MAGIC: &[u8; 8] = b"SHAPEBIN";
+
+/// Version written by `BinaryWriter` (and the only one `ShapeArchive` reads)
+pub const VERSION: u16 = 1;
+
+/// Size of the fixed portion of the header
+const FIXED_HEADER_SIZE: usize = 32;
+
+fn header_size(kinds: usize) -> usize {
+    FIXED_HEADER_SIZE + 8 * kinds
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Write shapes to a binary shape file.
+///
+/// The header can only be completed once every shape has been written, so
+/// the output must be seekable.
+///
+/// # Example
+///
+/// ```
+/// use std::io::Cursor;
+///
+/// use shapes::binary::{BinaryWriter, ShapeArchive};
+/// use shapes::square::Square;
+///
+/// let mut writer = BinaryWriter::new(Cursor::new(Vec::new())).unwrap();
+/// writer.write(&Square::with_side(2.0).into()).unwrap();
+/// let bytes = writer.finish().unwrap().into_inner();
+///
+/// let archive = ShapeArchive::from_bytes(bytes).unwrap();
+/// assert_eq!(archive.len(), 1);
+/// ```
+pub struct BinaryWriter<W: Write + Seek> {
+    out: W,
+
+    /// Where the header starts in `out`
+    start: u64,
+
+    /// Position of the next record, relative to `start`
+    position: u64,
+
+    offsets: Vec<u64>,
+    counts: Vec<u64>,
+}
+
+impl<W: Write + Seek> BinaryWriter<W> {
+    /// Start a new file at the current position of `out`
+    pub fn new(mut out: W) -> io::Result<Self> {
+        let kinds = KnownShape::KNOWN_NAMES.len();
+        let start = out.stream_position()?;
+
+        // Reserve space for the header; it is filled in by `finish`
+        out.write_all(&vec![0; header_size(kinds)])?;
+
+        Ok(BinaryWriter {
+            out,
+            start,
+            position: header_size(kinds) as u64,
+            offsets: Vec::new(),
+            counts: vec![0; kinds],
+        })
+    }
+
+    /// Append one shape
+    pub fn write(&mut self, shape: &KnownShape) -> io::Result<()> {
+        let kind = shape.kind_index();
+        let params = shape.params();
+
+        self.out.write_all(&[kind as u8])?;
+        for dim in params.iter() {
+            self.out.write_all(&dim.to_le_bytes())?;
+        }
+
+        self.offsets.push(self.position);
+        self.counts[kind] += 1;
+        self.position += 1 + 8 * params.len() as u64;
+
+        Ok(())
+    }
+
+    /// Number of shapes written so far
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Check whether no shapes have been written
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Write the index and header, and return the underlying output
+    pub fn finish(mut self) -> io::Result<W> {
+        for offset in self.offsets.iter() {
+            self.out.write_all(&offset.to_le_bytes())?;
+        }
+        let end = self.out.stream_position()?;
+
+        let mut header = Vec::with_capacity(header_size(self.counts.len()));
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&VERSION.to_le_bytes());
+        header.extend_from_slice(&(header_size(self.counts.len()) as u16).to_le_bytes());
+        header.extend_from_slice(&(self.counts.len() as u32).to_le_bytes());
+        header.extend_from_slice(&(self.offsets.len() as u64).to_le_bytes());
+        header.extend_from_slice(&self.position.to_le_bytes());
+        for count in self.counts.iter() {
+            header.extend_from_slice(&count.to_le_bytes());
+        }
+
+        self.out.seek(SeekFrom::Start(self.start))?;
+        self.out.write_all(&header)?;
+        self.out.seek(SeekFrom::Start(end))?;
+        self.out.flush()?;
+
+        Ok(self.out)
+    }
+}
+
+/// Write every shape in `shapes` to a binary shape file.
+///
+/// # Arguments
+///
+///  * `out` - output destination
+///  * `shapes` - shapes to write
+///
+pub fn write_binary<'a, W, I>(out: W, shapes: I) -> io::Result<W>
+    where W: Write + Seek,
+          I: IntoIterator<Item = &'a KnownShape> {
+
+    let mut writer = BinaryWriter::new(out)?;
+
+    for shape in shapes {
+        writer.write(shape)?;
+    }
+
+    writer.finish()
+}
+
+/// Read-only, random access view of a binary shape file.
+///
+/// Only the header is examined up front; records are decoded on demand.
+#[derive(Debug)]
+pub struct ShapeArchive<D> {
+    data: D,
+    count: usize,
+    index_offset: usize,
+    counts: Vec<usize>,
+}
+
+impl ShapeArchive<Mmap> {
+    /// Memory-map the binary shape file at `path`.
+    ///
+    /// The file must not be modified (by this or any other process) while
+    /// the archive is open.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+
+        // SAFETY: the mapping is read-only and, as documented above, the
+        // caller guarantees the file is not modified while it is mapped.
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        ShapeArchive::from_bytes(mmap)
+    }
+}
+
+impl<D: AsRef<[u8]>> ShapeArchive<D> {
+    /// Use an in-memory binary shape file. Return an `InvalidData` error if
+    /// the header or index is malformed or the version is not supported.
+    pub fn from_bytes(data: D) -> io::Result<Self> {
+        let bytes = data.as_ref();
+
+        if bytes.len() < FIXED_HEADER_SIZE || &bytes[0..8] != MAGIC {
+            return Err(invalid("not a binary shape file"));
+        }
+
+        let version = read_u16(bytes, 8);
+        if version != VERSION {
+            return Err(invalid(&format!("unsupported version {}", version)));
+        }
+
+        let header = read_u16(bytes, 10) as usize;
+        let kinds = read_u32(bytes, 12) as usize;
+        let count = read_u64(bytes, 16) as usize;
+        let index_offset = read_u64(bytes, 24) as usize;
+
+        if kinds > KnownShape::KNOWN_NAMES.len()
+            || header != header_size(kinds)
+            || header > bytes.len() {
+            return Err(invalid("malformed header"));
+        }
+
+        let index_fits = count.checked_mul(8)
+            .and_then(|size| size.checked_add(index_offset))
+            .is_some_and(|end| end <= bytes.len());
+
+        if index_offset < header || !index_fits {
+            return Err(invalid("truncated index"));
+        }
+
+        let counts = (0..kinds)
+            .map(|k| read_u64(bytes, FIXED_HEADER_SIZE + 8 * k) as usize)
+            .collect();
+
+        Ok(ShapeArchive { data, count, index_offset, counts })
+    }
+
+    /// Number of shapes
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Check whether the archive holds no shapes
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Decode the `i`-th shape. Return `None` if `i` is out of range or the
+    /// record is corrupt.
+    pub fn get(&self, i: usize) -> Option<KnownShape> {
+        if i >= self.count {
+            return None;
+        }
+
+        let bytes = self.data.as_ref();
+        let offset = read_u64(bytes, self.index_offset + 8 * i) as usize;
+
+        let kind = *bytes.get(offset)? as usize;
+        let name = KnownShape::KNOWN_NAMES.get(kind)?;
+        let n = KnownShape::PARAM_COUNTS[kind];
+
+        let record = bytes.get(offset + 1..offset + 1 + 8 * n)?;
+        let dims: Vec<f64> = record.chunks_exact(8)
+            .map(|chunk| f64::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+
+        KnownShape::from_name_with(name, &dims)
+    }
+
+    /// Decode the shapes in `range` (clamped to the archive), skipping any
+    /// corrupt records
+    pub fn range(&self, range: Range<usize>) -> impl Iterator<Item = KnownShape> + '_ {
+        let end = range.end.min(self.count);
+        let start = range.start.min(end);
+
+        (start..end).filter_map(move |i| self.get(i))
+    }
+
+    /// Decode every shape, in the order written
+    pub fn iter(&self) -> impl Iterator<Item = KnownShape> + '_ {
+        self.range(0..self.count)
+    }
+
+    /// Count the shapes of each kind, keyed (and sorted) by name. Only the
+    /// header is read.
+    pub fn count_by_name(&self) -> BTreeMap<&'static str, usize> {
+        KnownShape::KNOWN_NAMES.iter()
+            .zip(self.counts.iter())
+            .filter(|(_, &count)| count > 0)
+            .map(|(&name, &count)| (name, count))
+            .collect()
+    }
+}
+
+fn read_u16(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes(bytes[at..at + 2].try_into().unwrap())
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hamcrest2::prelude::*;
+
+    use std::io::Cursor;
+
+    use crate::circle::Circle;
+    use crate::shape::Shape;
+    use crate::square::Square;
+    use crate::testing::some_shapes;
+
+    fn to_bytes(shapes: &[KnownShape]) -> Vec<u8> {
+        write_binary(Cursor::new(Vec::new()), shapes).unwrap().into_inner()
+    }
+
+    fn bits(shape: &KnownShape) -> Vec<u64> {
+        shape.params().iter().map(|p| p.to_bits()).collect()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        // Including dimensions that are not exact in decimal, or are tiny
+        let mut shapes = some_shapes();
+        shapes.push(Circle::with_radius(0.1).into());
+        shapes.push(Square::with_side(1e-300).into());
+
+        let archive = ShapeArchive::from_bytes(to_bytes(&shapes)).unwrap();
+
+        assert_that!(archive.len(), is(equal_to(shapes.len())));
+
+        for (original, decoded) in shapes.iter().zip(archive.iter()) {
+            assert_that!(decoded.name(), equal_to(original.name()));
+            assert_that!(bits(&decoded), equal_to(bits(original)));
+        }
+    }
+
+    #[test]
+    fn test_random_access() {
+        let shapes = some_shapes();
+        let archive = ShapeArchive::from_bytes(to_bytes(&shapes)).unwrap();
+
+        assert_that!(archive.get(3).unwrap().to_string(),
+                     equal_to(shapes[3].to_string()));
+        assert!(archive.get(7).is_none());
+
+        let names: Vec<&str> = archive.range(1..3).map(|s| s.name()).collect();
+        assert_that!(names, equal_to(vec!["Circle", "Right Triangle"]));
+        assert_that!(archive.range(4..100).count(), is(equal_to(3)));
+        assert_that!(archive.range(100..200).count(), is(equal_to(0)));
+
+        let counts = archive.count_by_name();
+        assert_that!(counts.len(), is(equal_to(5)));
+        assert_that!(counts["Square"], is(equal_to(2)));
+    }
+
+    #[test]
+    fn test_empty() {
+        let archive = ShapeArchive::from_bytes(to_bytes(&[])).unwrap();
+
+        assert!(archive.is_empty());
+        assert!(archive.get(0).is_none());
+        assert!(archive.count_by_name().is_empty());
+    }
+
+    #[test]
+    fn test_invalid() {
+        let bytes = to_bytes(&some_shapes());
+
+        assert!(ShapeArchive::from_bytes(&b"Square; 9\n"[..]).is_err());
+        assert!(ShapeArchive::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+        let mut future = bytes.clone();
+        future[8] = 2;
+        let err = ShapeArchive::from_bytes(future).unwrap_err();
+        assert_that!(err.kind(), equal_to(io::ErrorKind::InvalidData));
+    }
+
+    #[test]
+    fn test_open() {
+        let path = std::env::temp_dir()
+            .join(format!("shapes-binary-{}.bin", std::process::id()));
+
+        let file = File::create(&path).unwrap();
+        write_binary(file, &some_shapes()).unwrap();
+
+        let archive = ShapeArchive::open(&path).unwrap();
+        assert_that!(archive.len(), is(equal_to(7)));
+        assert_that!(archive.get(6).unwrap().area(), close_to(16.0, 1e-9));
+
+        drop(archive);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,4 +1,6 @@
 use crate::shape::Shape;
+use crate::formatter::ShapeFormatter;
+use crate::named_params::{FromNamedParams, NamedParams};
 
 use std::fmt;
 
@@ -44,6 +46,31 @@ impl Shape for Circle {
     fn perimeter(&self) -> f64 {
         2.0 * std::f64::consts::PI * self.radius
     }
+
+    fn fields(&self) -> Vec<(&'static str, f64)> {
+        vec![
+            ("Radius", self.radius),
+            ("Diameter", self.diameter()),
+        ]
+    }
+}
+
+impl FromNamedParams for Circle {
+    /// Accept `radius`, `diameter`, `perimeter` (or `circumference`) or
+    /// `area`
+    fn from_named(params: &NamedParams) -> Option<Self> {
+        let tau = 2.0 * std::f64::consts::PI;
+
+        params.exactly(["radius"]).map(|[r]| r)
+            .or_else(|| params.exactly(["diameter"]).map(|[d]| d / 2.0))
+            .or_else(|| params.exactly(["perimeter"]).map(|[p]| p / tau))
+            .or_else(|| params.exactly(["circumference"]).map(|[p]| p / tau))
+            .or_else(|| {
+                params.exactly(["area"])
+                    .map(|[a]| (a / std::f64::consts::PI).sqrt())
+            })
+            .map(Circle::with_radius)
+    }
 }
 
 impl Default for Circle {
@@ -54,11 +81,7 @@ impl Default for Circle {
 
 impl fmt::Display for Circle {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(f, "{:12}:{:>24}", "Name", self.name())?;
-        writeln!(f, "{:12}:{:>24.4}", "Radius", self.radius)?;
-        writeln!(f, "{:12}:{:>24.4}", "Diameter", self.diameter())?;
-        writeln!(f, "{:12}:{:>24.4}", "Perimeter", self.perimeter())?;
-        writeln!(f, "{:12}:{:>24.4}", "Area", self.area())
+        ShapeFormatter::default().write(f, self)
     }
 }
 
@@ -91,16 +114,16 @@ mod tests {
     #[test]
     fn test_diameter() {
         let generic = Circle::new();
-        let fancy = Circle::with_radius(2 as f64);
+        let fancy = Circle::with_radius(2_f64);
 
-        assert_that!(generic.diameter(), close_to(2 as f64, 1e-6));
-        assert_that!(fancy.diameter(), close_to(4 as f64, 1e-6));
+        assert_that!(generic.diameter(), close_to(2_f64, 1e-6));
+        assert_that!(fancy.diameter(), close_to(4_f64, 1e-6));
     }
 
     #[test]
     fn test_area() {
         let generic = Circle::new();
-        let fancy = Circle::with_radius(2 as f64);
+        let fancy = Circle::with_radius(2_f64);
 
         assert_that!(generic.area(),
                      close_to(f64::consts::PI * generic.radius.powi(2), 0.05));
@@ -158,4 +181,18 @@ mod tests {
 
         assert!(fancy_str.ends_with("\n"));
     }
+
+    #[test]
+    fn test_from_named() {
+        let params = NamedParams::parse("diameter=10").unwrap();
+        let fancy = Circle::from_named(&params).unwrap();
+        assert_that!(fancy.radius, close_to(5.0, 1e-8));
+
+        let params = NamedParams::parse("area=3.14159265358979").unwrap();
+        let fancy = Circle::from_named(&params).unwrap();
+        assert_that!(fancy.radius, close_to(1.0, 1e-8));
+
+        let params = NamedParams::parse("side=10").unwrap();
+        assert!(Circle::from_named(&params).is_none());
+    }
 }
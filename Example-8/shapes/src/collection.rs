@@ -0,0 +1,461 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use ordered_float::OrderedFloat;
+
+use crate::dedup;
+use crate::group::{self, ShapeGroups};
+use crate::known_shape::KnownShape;
+use crate::query::Query;
+use crate::sort::{self, SortSpec};
+
+/// Sums are taken over chunks of this many values, then the chunk sums are
+/// added in order. `parallel::summary` sums the same chunks on separate
+/// threads, so both give the same result bit for bit.
+pub(crate) const SUM_CHUNK_SIZE: usize = 4096;
+
+/// Descriptive statistics for one metric (e.g., area) over a set of shapes
+#[derive(Clone, Debug, PartialEq)]
+pub struct Summary {
+    pub count: usize,
+    pub sum: f64,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub median: f64,
+
+    /// Population variance
+    pub variance: f64,
+}
+
+impl Summary {
+    /// Summarize a list of values. Return `None` if `values` is empty.
+    pub fn of(values: &[f64]) -> Option<Summary> {
+        if values.is_empty() {
+            return None;
+        }
+
+        let mut sorted = values.to_vec();
+        sorted.sort_by_key(|v| OrderedFloat(*v));
+
+        let count = sorted.len();
+        let sum = sorted.chunks(SUM_CHUNK_SIZE)
+            .map(|chunk| chunk.iter().sum::<f64>())
+            .sum::<f64>();
+        let mean = sum / count as f64;
+        let variance = sorted.chunks(SUM_CHUNK_SIZE)
+            .map(|chunk| chunk.iter().map(|v| (v - mean).powi(2)).sum::<f64>())
+            .sum::<f64>() / count as f64;
+
+        Some(Summary {
+            count,
+            sum,
+            min: sorted[0],
+            max: sorted[count - 1],
+            mean,
+            median: percentile_of_sorted(&sorted, 50.0),
+            variance,
+        })
+    }
+
+    /// Population standard deviation
+    pub fn std_dev(&self) -> f64 {
+        self.variance.sqrt()
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:12}:{:>24}", "Count", self.count)?;
+        writeln!(f, "{:12}:{:>24.4}", "Sum", self.sum)?;
+        writeln!(f, "{:12}:{:>24.4}", "Min", self.min)?;
+        writeln!(f, "{:12}:{:>24.4}", "Max", self.max)?;
+        writeln!(f, "{:12}:{:>24.4}", "Mean", self.mean)?;
+        writeln!(f, "{:12}:{:>24.4}", "Median", self.median)?;
+        writeln!(f, "{:12}:{:>24.4}", "Variance", self.variance)?;
+        writeln!(f, "{:12}:{:>24.4}", "Std Dev", self.std_dev())
+    }
+}
+
+/// A list of shapes with statistics over any metric.
+///
+/// A metric is any function from a shape to a number, e.g.,
+/// `Shape::area`, `Shape::perimeter` or a closure. Every statistic returns
+/// `None` (instead of panicking) when the collection is empty.
+///
+/// # Example
+///
+/// ```
+/// use shapes::collection::ShapeCollection;
+/// use shapes::shape::Shape;
+/// use shapes::square::Square;
+///
+/// let shapes: ShapeCollection = vec![Square::with_side(1.0).into(),
+///                                    Square::with_side(3.0).into()]
+///     .into();
+///
+/// assert_eq!(shapes.mean(Shape::area), Some(5.0));
+/// assert_eq!(ShapeCollection::new().mean(Shape::area), None);
+/// ```
+#[derive(Debug, Default)]
+pub struct ShapeCollection {
+    shapes: Vec<KnownShape>,
+}
+
+impl ShapeCollection {
+    /// Create an empty collection
+    pub fn new() -> Self {
+        ShapeCollection { shapes: Vec::new() }
+    }
+
+    /// Add a shape
+    pub fn push(&mut self, shape: KnownShape) {
+        self.shapes.push(shape);
+    }
+
+    pub fn len(&self) -> usize {
+        self.shapes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shapes.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, KnownShape> {
+        self.shapes.iter()
+    }
+
+    /// View the shapes as a slice
+    pub fn as_slice(&self) -> &[KnownShape] {
+        &self.shapes
+    }
+
+    /// Take back the underlying `Vec`
+    pub fn into_vec(self) -> Vec<KnownShape> {
+        self.shapes
+    }
+
+    /// Keep only the shapes that satisfy `query`
+    pub fn filter(self, query: &Query) -> ShapeCollection {
+        self.shapes.into_iter().filter(|s| query.matches(s)).collect()
+    }
+
+    /// Sort the shapes in place (stable)
+    pub fn sort_by_spec(&mut self, spec: &SortSpec) {
+        sort::sort(&mut self.shapes, spec);
+    }
+
+    /// Select the first `k` shapes in `spec` order
+    pub fn top_k(&self, k: usize, spec: &SortSpec) -> Vec<&KnownShape> {
+        sort::top_k(self.shapes.iter(), k, spec)
+    }
+
+    /// Select the last `k` shapes in `spec` order
+    pub fn bottom_k(&self, k: usize, spec: &SortSpec) -> Vec<&KnownShape> {
+        sort::bottom_k(self.shapes.iter(), k, spec)
+    }
+
+    /// Rank every shape (see [`sort::rank`])
+    pub fn rank(&self, spec: &SortSpec) -> Vec<usize> {
+        sort::rank(&self.shapes, spec)
+    }
+
+    /// Find the shape with the largest value of `metric`
+    pub fn max_by<F>(&self, metric: F) -> Option<&KnownShape>
+        where F: Fn(&KnownShape) -> f64 {
+
+        self.shapes.iter().max_by_key(|s| OrderedFloat(metric(s)))
+    }
+
+    /// Find the shape with the smallest value of `metric`
+    pub fn min_by<F>(&self, metric: F) -> Option<&KnownShape>
+        where F: Fn(&KnownShape) -> f64 {
+
+        self.shapes.iter().min_by_key(|s| OrderedFloat(metric(s)))
+    }
+
+    /// Sum `metric` over all shapes (zero if the collection is empty)
+    pub fn sum<F>(&self, metric: F) -> f64
+        where F: Fn(&KnownShape) -> f64 {
+
+        self.shapes.iter().map(metric).sum()
+    }
+
+    pub fn mean<F>(&self, metric: F) -> Option<f64>
+        where F: Fn(&KnownShape) -> f64 {
+
+        self.summary(metric).map(|s| s.mean)
+    }
+
+    pub fn median<F>(&self, metric: F) -> Option<f64>
+        where F: Fn(&KnownShape) -> f64 {
+
+        self.summary(metric).map(|s| s.median)
+    }
+
+    /// Population variance of `metric`
+    pub fn variance<F>(&self, metric: F) -> Option<f64>
+        where F: Fn(&KnownShape) -> f64 {
+
+        self.summary(metric).map(|s| s.variance)
+    }
+
+    /// Compute the `p`-th percentile (0 to 100) of `metric`, interpolating
+    /// linearly between the closest ranks.
+    pub fn percentile<F>(&self, metric: F, p: f64) -> Option<f64>
+        where F: Fn(&KnownShape) -> f64 {
+
+        if self.shapes.is_empty() || !(0.0..=100.0).contains(&p) {
+            return None;
+        }
+
+        let mut values = self.values(metric);
+        values.sort_by_key(|v| OrderedFloat(*v));
+
+        Some(percentile_of_sorted(&values, p))
+    }
+
+    /// Compute all descriptive statistics for `metric` at once
+    pub fn summary<F>(&self, metric: F) -> Option<Summary>
+        where F: Fn(&KnownShape) -> f64 {
+
+        Summary::of(&self.values(metric))
+    }
+
+    /// Group shapes by `key`, e.g., [`group::by_name`], a size bucket
+    /// ([`group::by_size`]) or any closure returning an ordered value.
+    pub fn group_by<K, F>(&self, key: F) -> ShapeGroups<'_, K>
+        where K: Ord,
+              F: Fn(&KnownShape) -> K {
+
+        ShapeGroups::new(self.shapes.iter(), key)
+    }
+
+    /// Count the shapes of each kind, keyed (and sorted) by name
+    pub fn count_by_name(&self) -> BTreeMap<&'static str, usize> {
+        self.group_by(group::by_name)
+            .count()
+            .into_iter()
+            .map(|(name, count)| (*name, count))
+            .collect()
+    }
+
+    /// Summarize `metric` separately for each kind of shape
+    pub fn summary_by_name<F>(&self, metric: F) -> BTreeMap<&'static str, Summary>
+        where F: Fn(&KnownShape) -> f64 {
+
+        self.group_by(group::by_name)
+            .summary(metric)
+            .into_iter()
+            .map(|(name, summary)| (*name, summary))
+            .collect()
+    }
+
+    /// List every group of two or more congruent shapes (see
+    /// [`dedup::congruence_groups`])
+    pub fn duplicates(&self, tolerance: f64) -> Vec<Vec<&KnownShape>> {
+        dedup::congruence_groups(&self.shapes, tolerance)
+            .into_iter()
+            .filter(|group| group.len() > 1)
+            .map(|group| group.into_iter().map(|i| &self.shapes[i]).collect())
+            .collect()
+    }
+
+    /// Keep only the first shape of each group of congruent shapes
+    pub fn dedup(self, tolerance: f64) -> ShapeCollection {
+        let mut keep = vec![false; self.shapes.len()];
+
+        for group in dedup::congruence_groups(&self.shapes, tolerance) {
+            keep[group[0]] = true;
+        }
+
+        self.shapes.into_iter()
+            .zip(keep)
+            .filter_map(|(shape, keep)| keep.then_some(shape))
+            .collect()
+    }
+
+    fn values<F>(&self, metric: F) -> Vec<f64>
+        where F: Fn(&KnownShape) -> f64 {
+
+        self.shapes.iter().map(metric).collect()
+    }
+}
+
+impl From<Vec<KnownShape>> for ShapeCollection {
+    fn from(shapes: Vec<KnownShape>) -> Self {
+        ShapeCollection { shapes }
+    }
+}
+
+impl FromIterator<KnownShape> for ShapeCollection {
+    fn from_iter<I: IntoIterator<Item = KnownShape>>(iter: I) -> Self {
+        ShapeCollection { shapes: iter.into_iter().collect() }
+    }
+}
+
+impl Extend<KnownShape> for ShapeCollection {
+    fn extend<I: IntoIterator<Item = KnownShape>>(&mut self, iter: I) {
+        self.shapes.extend(iter);
+    }
+}
+
+impl IntoIterator for ShapeCollection {
+    type Item = KnownShape;
+    type IntoIter = std::vec::IntoIter<KnownShape>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.shapes.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a ShapeCollection {
+    type Item = &'a KnownShape;
+    type IntoIter = std::slice::Iter<'a, KnownShape>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.shapes.iter()
+    }
+}
+
+/// Linear interpolation between closest ranks. `sorted` must not be empty.
+pub(crate) fn percentile_of_sorted(sorted: &[f64], p: f64) -> f64 {
+    let rank = p / 100.0 * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    let weight = rank - lower as f64;
+
+    sorted[lower] + (sorted[upper] - sorted[lower]) * weight
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hamcrest2::prelude::*;
+
+    use crate::circle::Circle;
+    use crate::shape::Shape;
+    use crate::square::Square;
+
+    fn squares(sides: &[f64]) -> ShapeCollection {
+        sides.iter().map(|&s| Square::with_side(s).into()).collect()
+    }
+
+    #[test]
+    fn test_empty() {
+        let empty = ShapeCollection::new();
+
+        assert!(empty.is_empty());
+        assert!(empty.max_by(Shape::area).is_none());
+        assert!(empty.min_by(Shape::perimeter).is_none());
+        assert!(empty.mean(Shape::area).is_none());
+        assert!(empty.median(Shape::area).is_none());
+        assert!(empty.variance(Shape::area).is_none());
+        assert!(empty.percentile(Shape::area, 50.0).is_none());
+        assert!(empty.summary_by_name(Shape::area).is_empty());
+        assert_that!(empty.sum(Shape::area), close_to(0.0, 1e-8));
+    }
+
+    #[test]
+    fn test_summary() {
+        // Perimeters 4, 8, 12, 16
+        let some_shapes = squares(&[1.0, 2.0, 3.0, 4.0]);
+        let summary = some_shapes.summary(Shape::perimeter).unwrap();
+
+        assert_that!(summary.count, is(equal_to(4)));
+        assert_that!(summary.sum, close_to(40.0, 1e-8));
+        assert_that!(summary.min, close_to(4.0, 1e-8));
+        assert_that!(summary.max, close_to(16.0, 1e-8));
+        assert_that!(summary.mean, close_to(10.0, 1e-8));
+        assert_that!(summary.median, close_to(10.0, 1e-8));
+        assert_that!(summary.variance, close_to(20.0, 1e-8));
+        assert_that!(summary.std_dev(), close_to(20_f64.sqrt(), 1e-8));
+    }
+
+    #[test]
+    fn test_percentile() {
+        let some_shapes = squares(&[4.0, 1.0, 3.0, 2.0, 5.0]);
+
+        let side = |s: &KnownShape| s.perimeter() / 4.0;
+        assert_that!(some_shapes.percentile(side, 0.0), equal_to(Some(1.0)));
+        assert_that!(some_shapes.percentile(side, 25.0), equal_to(Some(2.0)));
+        assert_that!(some_shapes.percentile(side, 90.0).unwrap(),
+                     close_to(4.6, 1e-8));
+        assert_that!(some_shapes.percentile(side, 100.0), equal_to(Some(5.0)));
+        assert!(some_shapes.percentile(side, 101.0).is_none());
+    }
+
+    #[test]
+    fn test_max_min() {
+        let mut some_shapes = squares(&[2.0, 1.0]);
+        some_shapes.push(Circle::with_radius(1.0).into());
+
+        assert_that!(some_shapes.max_by(Shape::area).unwrap().name(),
+                     equal_to("Square"));
+        assert_that!(some_shapes.min_by(Shape::area).unwrap().area(),
+                     close_to(1.0, 1e-8));
+    }
+
+    #[test]
+    fn test_by_name() {
+        let mut some_shapes = squares(&[1.0, 3.0]);
+        some_shapes.push(Circle::with_radius(1.0).into());
+
+        let counts = some_shapes.count_by_name();
+        assert_that!(counts["Square"], is(equal_to(2)));
+        assert_that!(counts["Circle"], is(equal_to(1)));
+
+        let by_name = some_shapes.summary_by_name(Shape::area);
+        assert_that!(by_name["Square"].mean, close_to(5.0, 1e-8));
+        assert_that!(by_name["Circle"].count, is(equal_to(1)));
+    }
+
+    #[test]
+    fn test_filter() {
+        let mut some_shapes = squares(&[1.0, 3.0]);
+        some_shapes.push(Circle::with_radius(1.0).into());
+
+        let query = Query::parse(r#"name = "Square" and area > 2"#).unwrap();
+        let filtered = some_shapes.filter(&query);
+
+        assert_that!(filtered.len(), is(equal_to(1)));
+        assert_that!(filtered.as_slice()[0].area(), close_to(9.0, 1e-8));
+    }
+
+    #[test]
+    fn test_sort_and_select() {
+        let mut some_shapes = squares(&[2.0, 3.0, 1.0]);
+        let spec = SortSpec::parse("area desc").unwrap();
+
+        assert_that!(some_shapes.top_k(1, &spec)[0].area(),
+                     close_to(9.0, 1e-8));
+        assert_that!(some_shapes.bottom_k(1, &spec)[0].area(),
+                     close_to(1.0, 1e-8));
+        assert_that!(some_shapes.rank(&spec), equal_to(vec![2, 1, 3]));
+
+        some_shapes.sort_by_spec(&spec);
+        assert_that!(some_shapes.as_slice()[2].area(), close_to(1.0, 1e-8));
+    }
+
+    #[test]
+    fn test_dedup() {
+        let some_shapes = squares(&[2.0, 3.0, 2.0 + 1e-12, 3.0, 1.0]);
+
+        let duplicates = some_shapes.duplicates(1e-9);
+        assert_that!(duplicates.len(), is(equal_to(2)));
+        assert_that!(duplicates[0].len(), is(equal_to(2)));
+
+        let unique = some_shapes.dedup(1e-9);
+        let sides: Vec<f64> = unique.iter().map(|s| s.perimeter() / 4.0).collect();
+        assert_that!(sides, equal_to(vec![2.0, 3.0, 1.0]));
+    }
+
+    #[test]
+    fn test_summary_display() {
+        let summary = squares(&[2.0]).summary(Shape::area).unwrap();
+        let summary_str = summary.to_string();
+
+        assert!(summary_str.contains(&format!("{:12}:{:>24}", "Count", 1)));
+        assert!(summary_str.contains(&format!("{:12}:{:>24.4}", "Mean", 4.0)));
+    }
+}
@@ -0,0 +1,316 @@
+//! Transparent gzip and zstd (de)compression.
+//!
+//! Compressed input is recognized by its magic bytes, so callers can wrap
+//! any `BufRead` in [`decompress`] and hand the result to the usual parsers
+//! (e.g., `read_shapes_with`). Decoding needs the `gzip` or `zstd` cargo
+//! feature; without it, compressed input is reported as an error instead of
+//! being parsed as garbage.
+//!
+//! [`CompressedWriter`] produces compressed output for any writer.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+/// First bytes of a gzip stream
+pub const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+
+/// First bytes of a zstd frame
+pub const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Supported compression formats
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// Recognize the compression of data that starts with `magic`
+    pub fn detect(magic: &[u8]) -> Compression {
+        if magic.starts_with(GZIP_MAGIC) {
+            Compression::Gzip
+        }
+        else if magic.starts_with(ZSTD_MAGIC) {
+            Compression::Zstd
+        }
+        else {
+            Compression::None
+        }
+    }
+
+    /// Check whether this build can read and write the format
+    pub fn is_supported(&self) -> bool {
+        match self {
+            Compression::None => true,
+            Compression::Gzip => cfg!(feature = "gzip"),
+            Compression::Zstd => cfg!(feature = "zstd"),
+        }
+    }
+
+    fn unsupported(&self) -> io::Error {
+        io::Error::new(io::ErrorKind::Unsupported,
+                       format!("{} compression needs the `{}` feature", self, self))
+    }
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_lowercase().as_str() {
+            "none" => Ok(Compression::None),
+            "gzip" | "gz" => Ok(Compression::Gzip),
+            "zstd" | "zst" => Ok(Compression::Zstd),
+            _ => Err(format!("Unknown compression '{}'", name)),
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Compression::None => "none",
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// Wrap `ins` so that gzip or zstd data is decompressed as it is read;
+/// anything else is passed through unchanged.
+///
+/// The compression is recognized from the bytes that `ins` has buffered
+/// after one `fill_buf`, so `ins` should buffer at least 4 bytes (as
+/// `BufReader` does for files).
+///
+/// # Errors
+///
+/// If `ins` can not be read, or is compressed in a format this build does
+/// not support.
+pub fn decompress<'a, R>(mut ins: R) -> io::Result<Box<dyn BufRead + 'a>>
+    where R: BufRead + 'a {
+
+    let compression = Compression::detect(ins.fill_buf()?);
+
+    match compression {
+        Compression::None => Ok(Box::new(ins)),
+
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => {
+            // Multi-member, so concatenated .gz files are read completely
+            let decoder = flate2::bufread::MultiGzDecoder::new(ins);
+            Ok(Box::new(BufReader::new(decoder)))
+        }
+
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => {
+            let decoder = zstd::stream::read::Decoder::with_buffer(ins)?;
+            Ok(Box::new(BufReader::new(decoder)))
+        }
+
+        #[allow(unreachable_patterns)]
+        _ => Err(compression.unsupported()),
+    }
+}
+
+/// Open a file, decompressing it as it is read if needed
+pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Box<dyn BufRead>> {
+    decompress(BufReader::new(File::open(path)?))
+}
+
+/// Read a whole file, decompressing it if needed
+pub fn read_file<P: AsRef<Path>>(path: P) -> io::Result<Vec<u8>> {
+    let mut content = Vec::new();
+    open(path)?.read_to_end(&mut content)?;
+
+    Ok(content)
+}
+
+/// A writer that compresses everything written to it.
+///
+/// `finish` must be called to write the end of the compressed stream.
+///
+/// # Example
+///
+/// ```
+/// use shapes::compress::{Compression, CompressedWriter};
+/// use std::io::Write;
+///
+/// let mut out = CompressedWriter::new(Vec::new(), Compression::None).unwrap();
+/// out.write_all(b"Square; 2\n").unwrap();
+///
+/// assert_eq!(out.finish().unwrap(), b"Square; 2\n");
+/// ```
+pub enum CompressedWriter<W: Write> {
+    Plain(W),
+
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::write::GzEncoder<W>),
+
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::stream::write::Encoder<'static, W>),
+}
+
+impl<W: Write> CompressedWriter<W> {
+    /// Compress output to `out` with `compression` (at its default level)
+    ///
+    /// # Errors
+    ///
+    /// If this build does not support `compression`.
+    pub fn new(out: W, compression: Compression) -> io::Result<Self> {
+        match compression {
+            Compression::None => Ok(CompressedWriter::Plain(out)),
+
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => {
+                let level = flate2::Compression::default();
+                Ok(CompressedWriter::Gzip(flate2::write::GzEncoder::new(out, level)))
+            }
+
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => {
+                let encoder = zstd::stream::write::Encoder::new(out, 0)?;
+                Ok(CompressedWriter::Zstd(encoder))
+            }
+
+            #[allow(unreachable_patterns)]
+            _ => Err(compression.unsupported()),
+        }
+    }
+
+    /// Write the end of the compressed stream and return the underlying
+    /// writer
+    pub fn finish(self) -> io::Result<W> {
+        match self {
+            CompressedWriter::Plain(mut out) => {
+                out.flush()?;
+                Ok(out)
+            }
+
+            #[cfg(feature = "gzip")]
+            CompressedWriter::Gzip(encoder) => encoder.finish(),
+
+            #[cfg(feature = "zstd")]
+            CompressedWriter::Zstd(encoder) => encoder.finish(),
+        }
+    }
+
+    fn inner(&mut self) -> &mut dyn Write {
+        match self {
+            CompressedWriter::Plain(out) => out,
+
+            #[cfg(feature = "gzip")]
+            CompressedWriter::Gzip(encoder) => encoder,
+
+            #[cfg(feature = "zstd")]
+            CompressedWriter::Zstd(encoder) => encoder,
+        }
+    }
+}
+
+impl<W: Write> Write for CompressedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner().flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hamcrest2::prelude::*;
+
+    use crate::factory;
+    use crate::shape::Shape;
+    use crate::testing::some_shapes;
+
+    /// Write `some_shapes` in the text format with `compression`
+    fn compressed(compression: Compression) -> Vec<u8> {
+        let mut out = CompressedWriter::new(Vec::new(), compression).unwrap();
+        factory::write_shapes_with(&mut out, &some_shapes(), None).unwrap();
+
+        out.finish().unwrap()
+    }
+
+    fn assert_round_trip(compression: Compression) {
+        let bytes = compressed(compression);
+        assert_that!(Compression::detect(&bytes), equal_to(compression));
+
+        let shapes = factory::read_shapes_with(decompress(bytes.as_slice()).unwrap());
+        let names: Vec<&str> = shapes.iter().map(|s| s.name()).collect();
+
+        let expected: Vec<&str> = some_shapes().iter().map(|s| s.name()).collect();
+        assert_that!(names, equal_to(expected));
+        assert_that!(shapes[4].area(), close_to(4.0 * std::f64::consts::PI, 1e-12));
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_that!(Compression::detect(&[0x1f, 0x8b, 0x08]), equal_to(Compression::Gzip));
+        assert_that!(Compression::detect(&[0x28, 0xb5, 0x2f, 0xfd]),
+                     equal_to(Compression::Zstd));
+        assert_that!(Compression::detect(b"Square; 2"), equal_to(Compression::None));
+        assert_that!(Compression::detect(&[0x1f]), equal_to(Compression::None));
+    }
+
+    #[test]
+    fn test_names() {
+        for compression in [Compression::None, Compression::Gzip, Compression::Zstd] {
+            assert_that!(compression.to_string().parse(), equal_to(Ok(compression)));
+        }
+
+        assert_that!("gz".parse(), equal_to(Ok(Compression::Gzip)));
+        assert!("bzip2".parse::<Compression>().is_err());
+    }
+
+    #[test]
+    fn test_plain() {
+        assert_round_trip(Compression::None);
+
+        let mut text = String::new();
+        decompress(&b"Square; 2\n"[..]).unwrap().read_to_string(&mut text).unwrap();
+        assert_that!(text, equal_to("Square; 2\n".to_string()));
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_gzip() {
+        assert_round_trip(Compression::Gzip);
+
+        // Concatenated members are read as one stream
+        let mut bytes = compressed(Compression::Gzip);
+        bytes.extend(compressed(Compression::Gzip));
+
+        let shapes = factory::read_shapes_with(decompress(bytes.as_slice()).unwrap());
+        assert_that!(shapes.len(), is(equal_to(2 * some_shapes().len())));
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd() {
+        assert_round_trip(Compression::Zstd);
+    }
+
+    #[test]
+    fn test_unsupported() {
+        for (compression, magic) in [(Compression::Gzip, GZIP_MAGIC),
+                                     (Compression::Zstd, ZSTD_MAGIC)] {
+            if compression.is_supported() {
+                continue;
+            }
+
+            let err = decompress(magic).err().unwrap();
+            assert_that!(err.kind(), equal_to(io::ErrorKind::Unsupported));
+            assert!(CompressedWriter::new(Vec::new(), compression).is_err());
+        }
+    }
+}
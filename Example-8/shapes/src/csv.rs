@@ -0,0 +1,553 @@
+//! CSV import and export.
+//!
+//! With a header row, each column is mapped to either the shape name or a
+//! named dimension (e.g., `radius`, `side`, `base`, `height`), so any set of
+//! dimensions accepted by [`factory::create_named`] can be used. Without a
+//! header, each row is a shape name followed by its dimensions, in the same
+//! order as the text format.
+//!
+//! Quoted fields may contain delimiters and doubled quotes (`""`), but not
+//! line breaks.
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use crate::factory;
+use crate::formatter;
+use crate::known_shape::KnownShape;
+use crate::locale::Locale;
+use crate::named_params::NamedParams;
+use crate::report::csv_escape;
+use crate::shape::Shape;
+
+/// Column key of the shape name
+pub const SHAPE_COLUMN: &str = "shape";
+
+/// Computed columns that are checked, rather than used, when the other
+/// columns already describe the shape
+const COMPUTED_COLUMNS: [&str; 2] = ["area", "perimeter"];
+
+/// A problem with one row (or the header) of a CSV file
+#[derive(Clone, Debug, PartialEq)]
+pub struct CsvError {
+    /// Line number, starting at 1
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for CsvError {}
+
+/// Read shapes from CSV.
+///
+/// # Example
+///
+/// ```
+/// use shapes::csv::CsvReader;
+/// use shapes::shape::Shape;
+///
+/// let input = "Kind,Radius,Side\nCircle,1,\nSquare,,2\nCircle,x,\n";
+/// let rows = CsvReader::new()
+///     .map_column("kind", "shape")
+///     .read(input.as_bytes());
+///
+/// assert_eq!(rows[1].as_ref().unwrap().area(), 4.0);
+/// assert_eq!(rows[2].as_ref().unwrap_err().line, 4);
+/// ```
+#[derive(Clone, Debug)]
+pub struct CsvReader {
+    delimiter: char,
+    header: Option<bool>,
+    columns: HashMap<String, String>,
+    ignored: HashSet<String>,
+    locale: Locale,
+}
+
+impl Default for CsvReader {
+    fn default() -> Self {
+        CsvReader::new()
+    }
+}
+
+impl CsvReader {
+    /// Read comma separated values, detecting whether there is a header
+    pub fn new() -> Self {
+        let columns = [("name", SHAPE_COLUMN)].iter()
+            .map(|(header, key)| (header.to_string(), key.to_string()))
+            .collect();
+
+        CsvReader {
+            delimiter: ',',
+            header: None,
+            columns,
+            ignored: HashSet::new(),
+            locale: Locale::english(),
+        }
+    }
+
+    pub fn delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Whether the first row is a header. By default, the first row is a
+    /// header unless its first field is a shape name.
+    pub fn header(mut self, header: bool) -> Self {
+        self.header = Some(header);
+        self
+    }
+
+    /// Use the column titled `header` for `key`--either [`SHAPE_COLUMN`] or a
+    /// dimension name. Columns that are not mapped use their own title
+    /// (lowercase, with spaces replaced by `_`) as the key.
+    pub fn map_column(mut self, header: &str, key: &str) -> Self {
+        self.columns.insert(header.trim().to_lowercase(), key.to_lowercase());
+        self
+    }
+
+    /// Skip the column titled `header`
+    pub fn ignore_column(mut self, header: &str) -> Self {
+        self.ignored.insert(header.trim().to_lowercase());
+        self
+    }
+
+    /// Accept shape names in the language of `locale`
+    pub fn locale(mut self, locale: Locale) -> Self {
+        self.locale = locale;
+        self
+    }
+
+    /// Read every row, producing one result per non-blank row after the
+    /// header (or a single error if the header itself is malformed).
+    pub fn read<B: BufRead>(&self, ins: B) -> Vec<Result<KnownShape, CsvError>> {
+        let mut rows = ins.lines()
+            .enumerate()
+            // A line that is not valid UTF-8 is skipped, but any other read
+            // error ends the input
+            .scan(false, |stopped, (i, text)| {
+                if *stopped {
+                    return None;
+                }
+
+                *stopped = matches!(&text,
+                                    Err(err) if err.kind() != io::ErrorKind::InvalidData);
+                Some((i + 1, text))
+            })
+            .filter(|(_, text)| {
+                text.as_ref().map_or(true, |text| !text.trim().is_empty())
+            })
+            .map(|(line, text)| {
+                text.map_err(|err| err.to_string())
+                    .and_then(|text| split_record(&text, self.delimiter))
+                    .map(|fields| (line, fields))
+                    .map_err(|message| CsvError { line, message })
+            })
+            .peekable();
+
+        let has_header = match (self.header, rows.peek()) {
+            (Some(header), _) => header,
+            (None, Some(Ok((_, fields)))) => {
+                self.locale.canonical_name(fields[0].trim()).is_none()
+            }
+            (None, _) => false,
+        };
+
+        if !has_header {
+            return rows.map(|row| self.positional_row(row?)).collect();
+        }
+
+        let columns = match rows.next() {
+            Some(Ok((line, titles))) => match self.header_keys(&titles) {
+                Ok(columns) => columns,
+                Err(message) => return vec![Err(CsvError { line, message })],
+            },
+            Some(Err(err)) => return vec![Err(err)],
+            None => return Vec::new(),
+        };
+
+        rows.map(|row| self.named_row(&columns, row?)).collect()
+    }
+
+    /// Key for each column (`None` for ignored columns)
+    fn header_keys(&self, titles: &[String]) -> Result<Vec<Option<String>>, String> {
+        let keys: Vec<Option<String>> = titles.iter()
+            .map(|title| title.trim().to_lowercase())
+            .map(|title| {
+                if self.ignored.contains(&title) {
+                    None
+                }
+                else {
+                    Some(self.columns.get(&title)
+                        .cloned()
+                        .unwrap_or_else(|| formatter::to_key(&title)))
+                }
+            })
+            .collect();
+
+        let mut seen = HashSet::new();
+        for key in keys.iter().flatten() {
+            if !seen.insert(key) {
+                return Err(format!("more than one column for '{}'", key));
+            }
+        }
+
+        if !seen.contains(&SHAPE_COLUMN.to_string()) {
+            return Err(format!("no '{}' column", SHAPE_COLUMN));
+        }
+
+        Ok(keys)
+    }
+
+    fn shape_name(&self, field: &str) -> Result<&'static str, String> {
+        self.locale.canonical_name(field.trim())
+            .ok_or_else(|| format!("unknown shape '{}'", field.trim()))
+    }
+
+    fn named_row(&self, columns: &[Option<String>], (line, fields): (usize, Vec<String>))
+        -> Result<KnownShape, CsvError> {
+
+        let error = |message: String| CsvError { line, message };
+
+        if fields.len() != columns.len() {
+            return Err(error(format!("expected {} fields, found {}",
+                                     columns.len(), fields.len())));
+        }
+
+        let mut name = None;
+        let mut params = NamedParams::default();
+
+        for (key, field) in columns.iter().zip(fields.iter()) {
+            let Some(key) = key else { continue };
+
+            if key == SHAPE_COLUMN {
+                name = Some(self.shape_name(field).map_err(error)?);
+                continue;
+            }
+
+            let field = field.trim();
+            if field.is_empty() {
+                continue;
+            }
+
+            let value = field.parse()
+                .map_err(|_| error(format!("{} '{}' is not a number", key, field)))?;
+            params.insert(key, value);
+        }
+
+        let name = name.expect("header has a shape column");
+
+        create_checked(name, params).map_err(error)
+    }
+
+    fn positional_row(&self, (line, fields): (usize, Vec<String>))
+        -> Result<KnownShape, CsvError> {
+
+        let error = |message: String| CsvError { line, message };
+
+        let name = self.shape_name(&fields[0]).map_err(error)?;
+        let kind = KnownShape::KNOWN_NAMES.iter()
+            .position(|&known| known == name)
+            .expect("canonical names are known");
+
+        let dims = fields[1..].iter()
+            .map(|field| field.trim())
+            .filter(|field| !field.is_empty())
+            .map(|field| {
+                field.parse()
+                    .map_err(|_| error(format!("'{}' is not a number", field)))
+            })
+            .collect::<Result<Vec<f64>, _>>()?;
+
+        let expected = KnownShape::PARAM_COUNTS[kind];
+        if dims.len() != expected {
+            return Err(error(format!("{} needs {} dimension(s), found {}",
+                                     name, expected, dims.len())));
+        }
+
+        Ok(factory::create_with(name, &dims).expect("name is known"))
+    }
+}
+
+/// Create `name` from `params`. If that fails, try again without any
+/// computed columns, which must then agree with the shape.
+fn create_checked(name: &str, mut params: NamedParams) -> Result<KnownShape, String> {
+    if let Some(shape) = factory::create_named(name, &params) {
+        return Ok(shape);
+    }
+
+    let invalid = format!("{} can not be built from the given dimensions ({})",
+                          name, params.keys().join(", "));
+
+    let computed: Vec<(&str, f64)> = COMPUTED_COLUMNS.iter()
+        .filter_map(|&key| Some((key, params.remove(key)?)))
+        .collect();
+
+    if computed.is_empty() {
+        return Err(invalid);
+    }
+
+    let shape = factory::create_named(name, &params).ok_or(invalid)?;
+
+    for (key, expected) in computed {
+        let actual = if key == "area" { shape.area() } else { shape.perimeter() };
+
+        if (actual - expected).abs() > 1e-6 * expected.abs().max(1.0) {
+            return Err(format!("{} is {}, but the dimensions give {}",
+                               key, expected, actual));
+        }
+    }
+
+    Ok(shape)
+}
+
+/// Split one line into fields, honoring double quotes
+fn split_record(line: &str, delimiter: char) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+            }
+            c if c == delimiter && !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+
+    if quoted {
+        return Err("unterminated quoted field".to_string());
+    }
+
+    fields.push(field);
+
+    Ok(fields)
+}
+
+/// Every column written by [`write_csv`]: the shape name, every dimension
+/// used by any kind of shape, then area and perimeter
+pub fn csv_columns() -> Vec<&'static str> {
+    let mut columns = vec![SHAPE_COLUMN];
+
+    for &name in KnownShape::PARAM_NAMES.iter().flat_map(|names| names.iter()) {
+        if !columns.contains(&name) {
+            columns.push(name);
+        }
+    }
+
+    columns.extend(COMPUTED_COLUMNS);
+    columns
+}
+
+/// Write shapes as CSV, with a header row. Dimensions that do not apply to a
+/// shape are left blank. Values are written with full precision, so reading
+/// the file back with [`CsvReader`] recreates the same shapes.
+///
+/// # Arguments
+///
+///  * `out` - output destination
+///  * `shapes` - shapes to write
+///
+pub fn write_csv<'a, W, I>(out: &mut W, shapes: I) -> io::Result<()>
+    where W: Write,
+          I: IntoIterator<Item = &'a KnownShape> {
+
+    let columns = csv_columns();
+    writeln!(out, "{}", columns.join(","))?;
+
+    for shape in shapes {
+        let kind = shape.kind_index();
+        let names = KnownShape::PARAM_NAMES[kind];
+        let params = shape.params();
+
+        let mut row = vec![csv_escape(shape.name())];
+
+        for column in columns[1..columns.len() - COMPUTED_COLUMNS.len()].iter() {
+            let value = names.iter()
+                .position(|name| name == column)
+                .map(|p| params[p].to_string());
+
+            row.push(value.unwrap_or_default());
+        }
+
+        row.push(shape.area().to_string());
+        row.push(shape.perimeter().to_string());
+
+        writeln!(out, "{}", row.join(","))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hamcrest2::prelude::*;
+
+    use crate::circle::Circle;
+    use crate::equilateral_triangle::EquilateralTriangle;
+    use crate::testing::some_shapes;
+
+    fn errors(rows: &[Result<KnownShape, CsvError>]) -> Vec<(usize, String)> {
+        rows.iter()
+            .filter_map(|row| row.as_ref().err())
+            .map(|err| (err.line, err.message.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_split_record() {
+        assert_that!(split_record("a,b,,c", ','),
+                     equal_to(Ok(vec!["a".into(), "b".into(), "".into(), "c".into()])));
+        assert_that!(split_record("\"Right, Triangle\",\"say \"\"hi\"\"\"", ','),
+                     equal_to(Ok(vec!["Right, Triangle".into(), "say \"hi\"".into()])));
+        assert_that!(split_record("a;b", ';'),
+                     equal_to(Ok(vec!["a".into(), "b".into()])));
+        assert!(split_record("\"open", ',').is_err());
+    }
+
+    #[test]
+    fn test_write() {
+        let mut out = Vec::new();
+        write_csv(&mut out, &some_shapes()[..2]).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_that!(lines[0], equal_to(
+            "shape,side_a,side_b,side_c,base,height,side,radius,area,perimeter"));
+        assert_that!(lines[1], equal_to("Square,,,,,,2,,4,8"));
+        assert!(lines[2].starts_with("Circle,,,,,,,1,3.14159"));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        // Including dimensions that are not exact in decimal
+        let mut shapes = some_shapes();
+        shapes.push(EquilateralTriangle::with_side(0.1).into());
+        shapes.push(Circle::with_radius(1.0 / 3.0).into());
+
+        let mut out = Vec::new();
+        write_csv(&mut out, &shapes).unwrap();
+
+        let rows = CsvReader::new().read(out.as_slice());
+
+        assert_that!(rows.len(), is(equal_to(shapes.len())));
+        for (row, original) in rows.iter().zip(shapes.iter()) {
+            let shape = row.as_ref().unwrap();
+
+            assert_that!(shape.name(), equal_to(original.name()));
+            assert_that!(shape.params(), equal_to(original.params()));
+        }
+    }
+
+    #[test]
+    fn test_column_mapping() {
+        let input = "Kind;R;Notes\n\
+                     Circle;2;big\n\
+                     \n\
+                     Square;2;\n";
+
+        let rows = CsvReader::new()
+            .delimiter(';')
+            .map_column("Kind", "shape")
+            .map_column("r", "radius")
+            .ignore_column("notes")
+            .read(input.as_bytes());
+
+        assert_that!(rows[0].as_ref().unwrap().area(),
+                     close_to(4.0 * std::f64::consts::PI, 1e-9));
+        assert_that!(errors(&rows), equal_to(vec![
+            (4, "Square can not be built from the given dimensions (radius)".to_string()),
+        ]));
+    }
+
+    #[test]
+    fn test_named_alternatives() {
+        let input = "Shape,Base,Area,Diameter\n\
+                     Right Triangle,4,10,\n\
+                     Circle,,,10\n";
+
+        let rows = CsvReader::new().read(input.as_bytes());
+
+        assert_that!(rows[0].as_ref().unwrap().perimeter(),
+                     close_to(9.0 + 41_f64.sqrt(), 1e-9));
+        assert_that!(rows[1].as_ref().unwrap().fields()[0].1, close_to(5.0, 1e-9));
+    }
+
+    #[test]
+    fn test_row_errors() {
+        let input = "shape,radius,area\n\
+                     Circle,1\n\
+                     Circle,1,3,4\n\
+                     Hexagon,1,\n\
+                     Circle,one,\n\
+                     Circle,1,3\n\
+                     Circle,\"1\n";
+
+        let rows = CsvReader::new().read(input.as_bytes());
+
+        assert_that!(errors(&rows), equal_to(vec![
+            (2, "expected 3 fields, found 2".to_string()),
+            (3, "expected 3 fields, found 4".to_string()),
+            (4, "unknown shape 'Hexagon'".to_string()),
+            (5, "radius 'one' is not a number".to_string()),
+            (6, format!("area is 3, but the dimensions give {}", std::f64::consts::PI)),
+            (7, "unterminated quoted field".to_string()),
+        ]));
+    }
+
+    #[test]
+    fn test_read_errors() {
+        let input = b"shape,radius\nCircle,1\n\xff\xfe,2\nCircle,2\n";
+        let rows = CsvReader::new().read(&input[..]);
+
+        assert_that!(rows.len(), is(equal_to(3)));
+        assert!(rows[0].is_ok() && rows[2].is_ok());
+        assert_that!(errors(&rows), equal_to(vec![
+            (3, "stream did not contain valid UTF-8".to_string()),
+        ]));
+    }
+
+    #[test]
+    fn test_header_errors() {
+        let rows = CsvReader::new().read("radius,area\n1,2\n".as_bytes());
+        assert_that!(errors(&rows), equal_to(vec![(1, "no 'shape' column".to_string())]));
+
+        let rows = CsvReader::new().read("shape,side,Side\n".as_bytes());
+        assert_that!(errors(&rows),
+                     equal_to(vec![(1, "more than one column for 'side'".to_string())]));
+
+        assert!(CsvReader::new().read("".as_bytes()).is_empty());
+        assert!(CsvReader::new().header(true).read("shape\n".as_bytes()).is_empty());
+    }
+
+    #[test]
+    fn test_positional() {
+        let input = "Triangle,3,4,5\n\
+                     Circle,2\n\
+                     Square,1,2\n";
+
+        let rows = CsvReader::new().read(input.as_bytes());
+
+        assert_that!(rows[0].as_ref().unwrap().area(), close_to(6.0, 1e-9));
+        assert_that!(rows[1].as_ref().unwrap().name(), equal_to("Circle"));
+        assert_that!(errors(&rows), equal_to(vec![
+            (3, "Square needs 1 dimension(s), found 2".to_string()),
+        ]));
+    }
+}
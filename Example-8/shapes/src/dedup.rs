@@ -0,0 +1,231 @@
+//! Find shapes that are congruent (within a tolerance), even when they were
+//! entered as different kinds--e.g., `Triangle; 2 2 2` and
+//! `Equilateral Triangle; 2`.
+//!
+//! Each shape is reduced to a canonical form (a triangle's sorted sides, a
+//! square's side or a circle's radius). Forms are quantized to a grid of
+//! `tolerance`-sized cells and hashed, so each shape is only compared with
+//! shapes in the same or a neighboring cell.
+
+use std::collections::HashMap;
+
+use crate::known_shape::KnownShape;
+
+/// Tolerance used when none is given
+pub const DEFAULT_TOLERANCE: f64 = 1e-9;
+
+/// The measurements that determine a shape up to congruence
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CanonicalForm {
+    /// Side lengths, smallest first
+    Triangle([f64; 3]),
+    Square(f64),
+    Circle(f64),
+}
+
+impl CanonicalForm {
+    /// Reduce `shape` to its canonical form
+    pub fn of(shape: &KnownShape) -> Self {
+        match shape {
+            KnownShape::Triangle(t) => {
+                CanonicalForm::triangle([t.side_a, t.side_b, t.side_c])
+            }
+            KnownShape::RightTriangle(t) => {
+                let hypotenuse = (t.base.powi(2) + t.height.powi(2)).sqrt();
+                CanonicalForm::triangle([t.base, t.height, hypotenuse])
+            }
+            KnownShape::EquilateralTriangle(t) => {
+                CanonicalForm::Triangle([t.side; 3])
+            }
+            KnownShape::Square(s) => CanonicalForm::Square(s.side),
+            KnownShape::Circle(c) => CanonicalForm::Circle(c.radius),
+        }
+    }
+
+    fn triangle(mut sides: [f64; 3]) -> Self {
+        sides.sort_by(f64::total_cmp);
+        CanonicalForm::Triangle(sides)
+    }
+
+    /// The measurements, in canonical order
+    pub fn values(&self) -> &[f64] {
+        match self {
+            CanonicalForm::Triangle(sides) => sides,
+            CanonicalForm::Square(side) => std::slice::from_ref(side),
+            CanonicalForm::Circle(radius) => std::slice::from_ref(radius),
+        }
+    }
+
+    /// Check whether both forms are the same kind and every measurement
+    /// differs by at most `tolerance`
+    pub fn within(&self, other: &CanonicalForm, tolerance: f64) -> bool {
+        self.kind() == other.kind()
+            && self.values().iter()
+                .zip(other.values())
+                .all(|(a, b)| (a - b).abs() <= tolerance)
+    }
+
+    fn kind(&self) -> u8 {
+        match self {
+            CanonicalForm::Triangle(_) => 0,
+            CanonicalForm::Square(_) => 1,
+            CanonicalForm::Circle(_) => 2,
+        }
+    }
+
+    fn cell(&self, tolerance: f64) -> Cell {
+        let mut coords = [0; 3];
+
+        for (coord, value) in coords.iter_mut().zip(self.values()) {
+            *coord = (value / tolerance).floor() as i64;
+        }
+
+        (self.kind(), coords)
+    }
+}
+
+/// Kind and grid coordinates of a quantized canonical form
+type Cell = (u8, [i64; 3]);
+
+/// The cell itself and every cell adjacent to it (in the dimensions that
+/// `dims` says are used)
+fn neighbors((kind, coords): Cell, dims: usize) -> impl Iterator<Item = Cell> {
+    (0..3_usize.pow(dims as u32)).map(move |n| {
+        let mut neighbor = coords;
+
+        for (d, coord) in neighbor.iter_mut().enumerate().take(dims) {
+            *coord += (n / 3_usize.pow(d as u32) % 3) as i64 - 1;
+        }
+
+        (kind, neighbor)
+    })
+}
+
+/// Partition `shapes` into groups of congruent shapes.
+///
+/// Each group is a list of indices into `shapes`, in ascending order; groups
+/// are ordered by their first member. A shape joins the earliest group whose
+/// first member is within `tolerance` of it, so every member of a group is
+/// close to that first member (but not necessarily to each other).
+///
+/// # Panics
+///
+/// If `tolerance` is not positive.
+pub fn congruence_groups(shapes: &[KnownShape], tolerance: f64) -> Vec<Vec<usize>> {
+    assert!(tolerance > 0.0, "tolerance must be positive");
+
+    let mut groups: Vec<(CanonicalForm, Vec<usize>)> = Vec::new();
+    let mut cells: HashMap<Cell, Vec<usize>> = HashMap::new();
+
+    for (i, shape) in shapes.iter().enumerate() {
+        let form = CanonicalForm::of(shape);
+        let cell = form.cell(tolerance);
+
+        let found = neighbors(cell, form.values().len())
+            .filter_map(|neighbor| cells.get(&neighbor))
+            .flatten()
+            .copied()
+            .filter(|&g| groups[g].0.within(&form, tolerance))
+            .min();
+
+        match found {
+            Some(g) => groups[g].1.push(i),
+            None => {
+                cells.entry(cell).or_default().push(groups.len());
+                groups.push((form, vec![i]));
+            }
+        }
+    }
+
+    groups.into_iter().map(|(_, members)| members).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hamcrest2::prelude::*;
+
+    use crate::circle::Circle;
+    use crate::equilateral_triangle::EquilateralTriangle;
+    use crate::right_triangle::RightTriangle;
+    use crate::square::Square;
+    use crate::triangle::Triangle;
+
+    #[test]
+    fn test_canonical_form() {
+        let equilateral: KnownShape = EquilateralTriangle::with_side(2.0).into();
+        let right: KnownShape = RightTriangle::with_base_height(4.0, 3.0).into();
+
+        assert_that!(CanonicalForm::of(&equilateral),
+                     equal_to(CanonicalForm::Triangle([2.0, 2.0, 2.0])));
+        assert_that!(CanonicalForm::of(&right),
+                     equal_to(CanonicalForm::Triangle([3.0, 4.0, 5.0])));
+        assert_that!(CanonicalForm::of(&Circle::with_radius(2.0).into())
+                         .values(),
+                     equal_to(&[2.0][..]));
+    }
+
+    #[test]
+    fn test_within() {
+        let a = CanonicalForm::Triangle([3.0, 4.0, 5.0]);
+        let b = CanonicalForm::Triangle([3.0, 4.0, 5.05]);
+
+        assert!(a.within(&b, 0.1));
+        assert!(!a.within(&b, 0.01));
+        assert!(!CanonicalForm::Square(1.0).within(&CanonicalForm::Circle(1.0), 1.0));
+    }
+
+    #[test]
+    fn test_groups_across_kinds() {
+        let shapes: Vec<KnownShape> = vec![
+            Triangle::with_sides(2.0, 2.0, 2.0).into(),
+            Square::with_side(2.0).into(),
+            EquilateralTriangle::with_side(2.0).into(),
+            Triangle::with_sides(5.0, 3.0, 4.0).into(),
+            RightTriangle::with_base_height(3.0, 4.0).into(),
+            Circle::with_radius(2.0).into(),
+        ];
+
+        let groups = congruence_groups(&shapes, DEFAULT_TOLERANCE);
+
+        assert_that!(groups, equal_to(vec![
+            vec![0, 2],
+            vec![1],
+            vec![3, 4],
+            vec![5],
+        ]));
+    }
+
+    #[test]
+    fn test_groups_across_cells() {
+        // 0.999 and 1.001 fall in different cells of a 0.005 grid, but are
+        // still within tolerance of each other
+        let shapes: Vec<KnownShape> = vec![
+            Square::with_side(0.999).into(),
+            Square::with_side(1.001).into(),
+            Square::with_side(1.2).into(),
+        ];
+
+        assert_that!(congruence_groups(&shapes, 0.005),
+                     equal_to(vec![vec![0, 1], vec![2]]));
+        assert_that!(congruence_groups(&shapes, 0.001).len(), is(equal_to(3)));
+    }
+
+    #[test]
+    fn test_not_transitive() {
+        let shapes: Vec<KnownShape> = vec![
+            Circle::with_radius(1.0).into(),
+            Circle::with_radius(1.1).into(),
+            Circle::with_radius(1.2).into(),
+        ];
+
+        assert_that!(congruence_groups(&shapes, 0.15),
+                     equal_to(vec![vec![0, 1], vec![2]]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_tolerance() {
+        congruence_groups(&[], 0.0);
+    }
+}
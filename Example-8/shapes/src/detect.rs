@@ -0,0 +1,592 @@
+//! Input format detection.
+//!
+//! Each format is scored from the start of the input (magic numbers, leading
+//! characters and how many lines look like that format) and, when a file
+//! name is known, its extension. The best scoring format is used; its score
+//! is reported as a confidence so that callers can warn about guesses.
+
+use std::fmt;
+use std::io::{self, BufRead, Read};
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::binary::{self, ShapeArchive};
+use crate::compress;
+use crate::csv::CsvReader;
+use crate::factory::{self, ShapeLines};
+use crate::gis;
+use crate::known_shape::KnownShape;
+use crate::locale::Locale;
+use crate::svg;
+
+#[cfg(not(feature = "parallel"))]
+use crate::factory::ShapeReader;
+
+#[cfg(feature = "parallel")]
+use crate::parallel;
+
+/// Bytes examined when scoring content
+const SAMPLE_BYTES: usize = 16 * 1024;
+
+/// Non-blank lines examined when scoring line-based formats
+const SAMPLE_LINES: usize = 100;
+
+/// Share of a score that comes from the file extension
+const EXTENSION_WEIGHT: f64 = 0.25;
+
+/// Lowest confidence that is not reported as a guess
+const MIN_CONFIDENCE: f64 = 0.75;
+
+/// Smallest lead over the runner-up that is not reported as a guess
+const MIN_MARGIN: f64 = 0.25;
+
+/// Supported input formats
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputFormat {
+    /// `name; dims` lines, read by `read_shapes_with`
+    Text,
+
+    /// One shape name per line, read by `read_shapes`
+    Names,
+
+    Csv,
+    GeoJson,
+
+    /// One WKT polygon per line
+    Wkt,
+
+    Svg,
+    Binary,
+}
+
+impl InputFormat {
+    /// Every format, in order of preference when scores tie
+    pub const ALL: [InputFormat; 7] = [
+        InputFormat::Text,
+        InputFormat::Names,
+        InputFormat::Csv,
+        InputFormat::GeoJson,
+        InputFormat::Wkt,
+        InputFormat::Svg,
+        InputFormat::Binary,
+    ];
+
+    /// Formats usually stored with file extension `ext` (case-insensitive)
+    fn for_extension(ext: &str) -> &'static [InputFormat] {
+        match ext.to_lowercase().as_str() {
+            "txt" => &[InputFormat::Text, InputFormat::Names],
+            "csv" => &[InputFormat::Csv],
+            "json" | "geojson" => &[InputFormat::GeoJson],
+            "wkt" => &[InputFormat::Wkt],
+            "svg" => &[InputFormat::Svg],
+            "bin" | "shapebin" => &[InputFormat::Binary],
+            _ => &[],
+        }
+    }
+}
+
+impl FromStr for InputFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        InputFormat::ALL.iter()
+            .copied()
+            .find(|format| format.to_string() == name.to_lowercase())
+            .ok_or(format!("Unknown input format '{}'", name))
+    }
+}
+
+impl fmt::Display for InputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            InputFormat::Text => "text",
+            InputFormat::Names => "names",
+            InputFormat::Csv => "csv",
+            InputFormat::GeoJson => "geojson",
+            InputFormat::Wkt => "wkt",
+            InputFormat::Svg => "svg",
+            InputFormat::Binary => "binary",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// The outcome of [`detect`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Detection {
+    /// Every format with a nonzero score (between 0 and 1), best first
+    pub candidates: Vec<(InputFormat, f64)>,
+}
+
+impl Detection {
+    /// The best scoring format (`Text` if nothing scored)
+    pub fn format(&self) -> InputFormat {
+        self.candidates.first()
+            .map_or(InputFormat::Text, |&(format, _)| format)
+    }
+
+    /// Score of the chosen format
+    pub fn confidence(&self) -> f64 {
+        self.candidates.first().map_or(0.0, |&(_, score)| score)
+    }
+
+    /// Check whether the chosen format is a guess--i.e., it scored poorly or
+    /// barely beat another format
+    pub fn is_ambiguous(&self) -> bool {
+        let runner_up = self.candidates.get(1).map_or(0.0, |&(_, score)| score);
+
+        self.confidence() < MIN_CONFIDENCE || self.confidence() - runner_up < MIN_MARGIN
+    }
+}
+
+impl fmt::Display for Detection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (confidence {:.0}%)", self.format(), 100.0 * self.confidence())?;
+
+        for (format, score) in self.candidates.iter().skip(1) {
+            write!(f, ", {} ({:.0}%)", format, 100.0 * score)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Guess the format of `content`.
+///
+/// # Arguments
+///
+///  * `path` - where `content` came from, if it is a file
+///  * `content` - complete input, or at least its first few kilobytes
+///
+pub fn detect(path: Option<&Path>, content: &[u8]) -> Detection {
+    let extension_formats = path.and_then(inner_extension)
+        .map_or(&[][..], InputFormat::for_extension);
+
+    let content_scores = score_content(content);
+
+    let mut candidates: Vec<(InputFormat, f64)> = InputFormat::ALL.iter()
+        .zip(content_scores)
+        .map(|(&format, content_score)| {
+            let score = if extension_formats.is_empty() {
+                content_score
+            }
+            else {
+                let extension_score = if extension_formats.contains(&format) { 1.0 } else { 0.0 };
+                (1.0 - EXTENSION_WEIGHT) * content_score + EXTENSION_WEIGHT * extension_score
+            };
+
+            (format, score)
+        })
+        .filter(|&(_, score)| score > 0.0)
+        .collect();
+
+    // Stable, so ties keep the order of InputFormat::ALL
+    candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    Detection { candidates }
+}
+
+/// The extension of `path`, ignoring a compression extension--e.g., `txt`
+/// for `shapes.txt.gz`
+fn inner_extension(path: &Path) -> Option<&str> {
+    let ext = path.extension()?.to_str()?;
+
+    match ext.to_lowercase().as_str() {
+        "gz" | "zst" => Path::new(path.file_stem()?).extension()?.to_str(),
+        _ => Some(ext),
+    }
+}
+
+/// Score each of `InputFormat::ALL` (in order) by how much `content` looks
+/// like it
+fn score_content(content: &[u8]) -> [f64; 7] {
+    let [mut text, mut names, mut csv, mut json, mut wkt, mut svg, mut binary] = [0.0; 7];
+
+    if content.starts_with(binary::MAGIC) {
+        binary = 1.0;
+        return [text, names, csv, json, wkt, svg, binary];
+    }
+
+    let sample = &content[..content.len().min(SAMPLE_BYTES)];
+    let sample = match std::str::from_utf8(sample) {
+        Ok(sample) => sample,
+        // A multi-byte character may have been cut at the end of the sample
+        Err(err) if err.error_len().is_none() => {
+            std::str::from_utf8(&sample[..err.valid_up_to()]).unwrap()
+        }
+        Err(_) => return [text, names, csv, json, wkt, svg, binary],
+    };
+
+    let start = sample.trim_start();
+
+    if start.starts_with('{') {
+        json = if sample.contains("\"type\"") { 1.0 } else { 0.8 };
+    }
+    else if start.starts_with('<') {
+        svg = if sample.contains("<svg") { 1.0 } else { 0.5 };
+    }
+    else if start.starts_with("#!shapes") {
+        text = 1.0;
+    }
+    else {
+        // The last line may have been cut short
+        let complete = if sample.len() < content.len() {
+            sample.rsplit_once('\n').map_or("", |(lines, _)| lines)
+        }
+        else {
+            sample
+        };
+
+        let lines: Vec<&str> = complete.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .take(SAMPLE_LINES)
+            .collect();
+
+        let share = |test: &dyn Fn(&str) -> bool| {
+            if lines.is_empty() {
+                return 0.0;
+            }
+            lines.iter().filter(|line| test(line)).count() as f64 / lines.len() as f64
+        };
+
+        text = share(&|line| line.contains(';'));
+        names = share(&factory::is_known);
+        wkt = share(&|line| line.to_uppercase().starts_with("POLYGON"));
+
+        let fields = |line: &str| line.matches(',').count();
+        if let Some(first) = lines.first().filter(|line| fields(line) > 0) {
+            csv = share(&|line| fields(line) == fields(first) && !line.contains('('));
+        }
+    }
+
+    [text, names, csv, json, wkt, svg, binary]
+}
+
+/// Split off the start of `ins`, enough for [`detect`]. Return it with a
+/// reader for all of `ins`, start included.
+pub fn sample<B: BufRead>(mut ins: B) -> io::Result<(Vec<u8>, impl BufRead)> {
+    let mut start = Vec::new();
+    ins.by_ref().take(SAMPLE_BYTES as u64 + 1).read_to_end(&mut start)?;
+
+    Ok((start.clone(), io::Cursor::new(start).chain(ins)))
+}
+
+/// Read shapes from `ins` in `format`, producing one result per entry.
+///
+/// Lines of the text format, CSV rows, GeoJSON geometries, WKT lines and SVG
+/// elements that are not valid shapes are returned as errors that say which
+/// file (if any) and entry failed, and reading goes on with the next entry.
+/// Names that are not valid shapes are skipped, as with `read_shapes_with`.
+///
+/// The text format is parsed as it is read (unless the `parallel` feature
+/// is enabled); other formats are read completely first.
+///
+/// # Arguments
+///
+///  * `format` - format of `ins`
+///  * `ins` - input source
+///  * `path` - where `ins` comes from, if it is a file (used to resolve
+///    `include` directives in the text format)
+///  * `locale` - language of the shape names
+///
+/// # Errors
+///
+/// If `ins` is not in `format` at all, or can not be read (in formats other
+/// than text, where each line that can not be read is an entry error).
+pub fn read_as<B>(format: InputFormat, mut ins: B, path: Option<&Path>,
+                  locale: &Locale) -> Result<Vec<Result<KnownShape, String>>, String>
+    where B: BufRead {
+
+    // Errors in the text format already give the file and line
+    if format == InputFormat::Text {
+        return Ok(read_text(ins, path, locale));
+    }
+
+    let located = |err: String| match path {
+        Some(path) => format!("{}: {}", path.display(), err),
+        None => err,
+    };
+
+    let mut content = Vec::new();
+    ins.read_to_end(&mut content).map_err(|err| located(err.to_string()))?;
+
+    if format == InputFormat::Binary {
+        let archive = ShapeArchive::from_bytes(&content)
+            .map_err(|err| located(err.to_string()))?;
+        return Ok(archive.iter().map(Ok).collect());
+    }
+
+    let text = std::str::from_utf8(&content)
+        .map_err(|_| located("input is not valid UTF-8".to_string()))?;
+
+    let shapes: Vec<Result<KnownShape, String>> = match format {
+        InputFormat::Names => {
+            factory::read_shapes(text.as_bytes()).into_iter().map(Ok).collect()
+        }
+        InputFormat::Csv => {
+            CsvReader::new()
+                .locale(locale.clone())
+                .read(text.as_bytes())
+                .into_iter()
+                .map(|row| row.map_err(|err| err.to_string()))
+                .collect()
+        }
+        InputFormat::GeoJson => {
+            gis::from_geojson(text).map_err(located)?
+                .into_iter()
+                .enumerate()
+                .map(|(i, shape)| shape.map_err(|err| format!("geometry {}: {}", i + 1, err)))
+                .collect()
+        }
+        InputFormat::Wkt => {
+            text.lines()
+                .enumerate()
+                .filter(|(_, line)| !line.trim().is_empty())
+                .map(|(i, line)| {
+                    gis::from_wkt(line).map_err(|err| format!("line {}: {}", i + 1, err))
+                })
+                .collect()
+        }
+        InputFormat::Svg => {
+            svg::read_svg(text).map_err(located)?
+                .into_iter()
+                .map(|shape| shape.map_err(|err| err.to_string()))
+                .collect()
+        }
+        InputFormat::Text | InputFormat::Binary => unreachable!(),
+    };
+
+    Ok(shapes.into_iter().map(|shape| shape.map_err(located)).collect())
+}
+
+/// Detect the format of a (possibly compressed) file, then read it.
+///
+/// # Arguments
+///
+///  * `path` - file to read
+///  * `locale` - language of the shape names
+///
+pub fn read_file<P>(path: P, locale: &Locale)
+    -> Result<(Detection, Vec<Result<KnownShape, String>>), String>
+    where P: AsRef<Path> {
+
+    let path = path.as_ref();
+    let (start, ins) = compress::open(path)
+        .and_then(sample)
+        .map_err(|err| format!("{}: {}", path.display(), err))?;
+
+    let detection = detect(Some(path), &start);
+    let shapes = read_as(detection.format(), ins, Some(path), locale)?;
+
+    Ok((detection, shapes))
+}
+
+/// The lines of `ins`, following `include` directives relative to `path`
+fn text_lines<B: BufRead>(ins: B, path: Option<&Path>) -> ShapeLines<B> {
+    let lines = ShapeLines::new(ins);
+
+    match path {
+        Some(path) => lines.path(path),
+        None => lines,
+    }
+}
+
+#[cfg(not(feature = "parallel"))]
+fn read_text<B: BufRead>(ins: B, path: Option<&Path>, locale: &Locale)
+    -> Vec<Result<KnownShape, String>> {
+
+    let mut reader = ShapeReader::from_lines(text_lines(ins, path), locale.clone())
+        .strict(true);
+
+    std::iter::from_fn(|| reader.try_next()).collect()
+}
+
+// Large inputs are parsed on all available cores
+#[cfg(feature = "parallel")]
+fn read_text<B: BufRead>(ins: B, path: Option<&Path>, locale: &Locale)
+    -> Vec<Result<KnownShape, String>> {
+
+    parallel::parse_lines(text_lines(ins, path), locale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hamcrest2::prelude::*;
+
+    use crate::shape::Shape;
+    use crate::square::Square;
+
+    fn detect_str(path: Option<&str>, content: &str) -> Detection {
+        detect(path.map(Path::new), content.as_bytes())
+    }
+
+    #[test]
+    fn test_detect_content() {
+        let cases = [
+            ("Square; 2\nCircle; 1\n1337 Haxor; 1 lol", InputFormat::Text),
+            ("#!shapes v2\n", InputFormat::Text),
+            ("Square\nCircle\nTriangle\n", InputFormat::Names),
+            ("shape,side,radius\nSquare,2,\nCircle,,1\n", InputFormat::Csv),
+            (r#"{"type": "FeatureCollection", "features": []}"#, InputFormat::GeoJson),
+            ("POLYGON ((0 0, 1 0, 1 1, 0 1, 0 0))\npolygon ((0 0, 1 0, 0 1, 0 0))", InputFormat::Wkt),
+            ("<?xml version=\"1.0\"?>\n<svg></svg>", InputFormat::Svg),
+        ];
+
+        for (content, expected) in cases {
+            let detection = detect_str(None, content);
+
+            assert_that!(detection.format(), equal_to(expected));
+            assert!(!detection.is_ambiguous(), "{}: {}", content, detection);
+        }
+    }
+
+    #[test]
+    fn test_detect_binary() {
+        let bytes = binary::write_binary(std::io::Cursor::new(Vec::new()),
+                                         &[Square::with_side(2.0).into()])
+            .unwrap()
+            .into_inner();
+
+        let detection = detect(Some(Path::new("shapes.txt")), &bytes);
+
+        assert_that!(detection.format(), equal_to(InputFormat::Binary));
+        assert_that!(detection.confidence(), close_to(0.75, 1e-9));
+        assert!(!detection.is_ambiguous());
+
+        let shapes = read_as(detection.format(), &bytes[..], None, &Locale::english())
+            .unwrap();
+        assert_that!(shapes[0].as_ref().unwrap().area(), close_to(4.0, 1e-9));
+    }
+
+    #[test]
+    fn test_extension() {
+        // Content outweighs the extension
+        let detection = detect_str(Some("shapes.csv"), "Square\n");
+        assert_that!(detection.format(), equal_to(InputFormat::Names));
+        assert_that!(detection.confidence(), close_to(0.75, 1e-9));
+        assert!(!detection.is_ambiguous());
+
+        // ... but decides between equally likely formats
+        let content = "Square; 2\nCircle\n";
+        assert!(detect_str(None, content).is_ambiguous());
+        assert_that!(detect_str(Some("shapes.txt"), content).format(),
+                     equal_to(InputFormat::Text));
+
+        let detection = detect_str(Some("shapes.json"), "");
+        assert_that!(detection.format(), equal_to(InputFormat::GeoJson));
+        assert_that!(detection.confidence(), close_to(0.25, 1e-9));
+        assert!(detection.is_ambiguous());
+
+        let detection = detect_str(Some("shapes.CSV.gz"), "");
+        assert_that!(detection.format(), equal_to(InputFormat::Csv));
+
+        let detection = detect_str(None, "");
+        assert_that!(detection.format(), equal_to(InputFormat::Text));
+        assert_that!(detection.confidence(), equal_to(0.0));
+    }
+
+    #[test]
+    fn test_display() {
+        let detection = detect_str(None, "Square; 2\nCircle\n");
+
+        assert_that!(detection.to_string(),
+                     equal_to("text (confidence 50%), names (50%)".to_string()));
+    }
+
+    #[test]
+    fn test_input_format_names() {
+        for format in InputFormat::ALL {
+            assert_that!(format.to_string().parse::<InputFormat>(), equal_to(Ok(format)));
+        }
+
+        assert!("GeoJSON".parse::<InputFormat>().is_ok());
+        assert!("yaml".parse::<InputFormat>().is_err());
+    }
+
+    #[test]
+    fn test_read_as() {
+        let locale = Locale::english();
+        let read = |format, content: &str| -> Vec<KnownShape> {
+            read_as(format, content.as_bytes(), None, &locale).unwrap()
+                .into_iter()
+                .map(Result::unwrap)
+                .collect()
+        };
+
+        assert_that!(read(InputFormat::Text, "Square; 2\n# comment\n\n").len(),
+                     is(equal_to(1)));
+        assert_that!(read(InputFormat::Names, "Square\nCircle\n").len(), is(equal_to(2)));
+        assert_that!(read(InputFormat::Csv, "shape,side\nSquare,3\n")[0].area(),
+                     close_to(9.0, 1e-9));
+        assert_that!(read(InputFormat::Wkt, "POLYGON ((0 0, 2 0, 2 2, 0 2, 0 0))\n")[0].area(),
+                     close_to(4.0, 1e-9));
+        assert_that!(read(InputFormat::Svg, "<svg><circle r=\"1\"/></svg>").len(),
+                     is(equal_to(1)));
+
+        assert!(read_as(InputFormat::GeoJson, &b"{"[..], None, &locale).is_err());
+
+        // Text is read line by line, so a line that can not be read is an
+        // entry error
+        let text = read_as(InputFormat::Text, &[0xff, 0xfe][..], None, &locale).unwrap();
+        assert_that!(text.len(), is(equal_to(1)));
+        assert!(text[0].is_err());
+    }
+
+    #[test]
+    fn test_read_as_entry_errors() {
+        let locale = Locale::english();
+        let errors = |format, content: &str| -> Vec<String> {
+            read_as(format, content.as_bytes(), None, &locale).unwrap()
+                .into_iter()
+                .filter_map(Result::err)
+                .collect()
+        };
+
+        assert_that!(errors(InputFormat::Text, "Square; 2\nTriangle; 3 4\nbad\nCircle; 1\n"),
+                     equal_to(vec![
+                         "line 2: Triangle takes 3 dimension(s), found 2".to_string(),
+                         "line 3: expected 'name; dimensions', found 'bad'".to_string(),
+                     ]));
+        assert_that!(errors(InputFormat::Csv, "shape,side\nSquare,3\nHexagon,1\n"),
+                     equal_to(vec!["line 3: unknown shape 'Hexagon'".to_string()]));
+        assert_that!(errors(InputFormat::Svg, "<svg><circle id=\"c\" r=\"x\"/></svg>"),
+                     equal_to(vec!["<circle id=\"c\">: invalid r 'x'".to_string()]));
+
+        let wkt = errors(InputFormat::Wkt, "POLYGON ((0 0, 2 0, 2 2, 0 2, 0 0))\n\nPOINT (1 1)\n");
+        assert_that!(wkt.len(), is(equal_to(1)));
+        assert!(wkt[0].starts_with("line 3: "), "{}", wkt[0]);
+
+        let json = r#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "geometry": {"type": "Point", "coordinates": [0, 0]}}
+        ]}"#;
+        assert_that!(errors(InputFormat::GeoJson, json),
+                     equal_to(vec!["geometry 1: expected a Polygon, found Point".to_string()]));
+    }
+
+    #[test]
+    fn test_read_as_text_uses_content() {
+        // The content is parsed, not the file at `path` (which does not exist)
+        let path = Path::new("no-such-directory/shapes.txt");
+        let shapes = read_as(InputFormat::Text, &b"Square; 2\n"[..], Some(path),
+                             &Locale::english()).unwrap();
+
+        assert_that!(shapes.len(), is(equal_to(1)));
+
+        // ... but includes are still relative to it
+        let shapes = read_as(InputFormat::Text, &b"include \"part.txt\"\n"[..], Some(path),
+                             &Locale::english()).unwrap();
+        let err = shapes[0].clone().unwrap_err();
+        assert!(err.contains("no-such-directory/part.txt"), "{}", err);
+
+        // ... and errors in the other formats name the file
+        let path = Path::new("shapes.csv");
+        let shapes = read_as(InputFormat::Csv, &b"shape,side\nHexagon,1\n"[..], Some(path),
+                             &Locale::english()).unwrap();
+
+        assert_that!(shapes[0].clone().unwrap_err(),
+                     equal_to("shapes.csv: line 2: unknown shape 'Hexagon'".to_string()));
+    }
+}
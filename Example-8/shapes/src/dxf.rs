@@ -0,0 +1,649 @@
+//! ASCII DXF export for CAD software and laser cutters.
+//!
+//! Circles are written as `CIRCLE` entities; every other shape is a closed
+//! `LWPOLYLINE` using the canonical coordinates from [`geometry::polygon`].
+//! Each kind of shape has its own layer (e.g., `RIGHT_TRIANGLE`).
+//!
+//! The drawing is an AutoCAD 2000 (`AC1015`) file: every table, block and
+//! entity has a handle and an owner, and the symbol tables, the model and
+//! paper space blocks and the root dictionary that version requires are
+//! written along with the shapes.
+//!
+//! Shapes are laid out left to right, bottom aligned, with `spacing` between
+//! their bounding boxes. A new row is started above the tallest shape of the
+//! current row once a row would grow wider than `row_width`.
+
+use std::io::{self, Write};
+
+use crate::geometry::{self, Point};
+use crate::known_shape::KnownShape;
+use crate::shape::Shape;
+
+/// The layer that holds shapes named `name`, e.g., `Right Triangle` is on
+/// `RIGHT_TRIANGLE`
+pub fn layer_name(name: &str) -> String {
+    name.to_uppercase().replace(' ', "_")
+}
+
+/// Writes shapes as an ASCII DXF drawing
+#[derive(Clone, Debug)]
+pub struct DxfWriter {
+    spacing: f64,
+    row_width: f64,
+}
+
+impl Default for DxfWriter {
+    fn default() -> Self {
+        DxfWriter::new()
+    }
+}
+
+impl DxfWriter {
+    /// A writer with 1 unit of spacing and rows of unlimited width
+    pub fn new() -> Self {
+        DxfWriter {
+            spacing: 1.0,
+            row_width: f64::INFINITY,
+        }
+    }
+
+    /// Gap between neighboring shapes
+    pub fn spacing(mut self, spacing: f64) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    /// Widest a row may grow before a new row is started (a shape wider
+    /// than this gets a row of its own)
+    pub fn row_width(mut self, row_width: f64) -> Self {
+        self.row_width = row_width;
+        self
+    }
+
+    /// Write every shape to `out` and return the number of entities written.
+    ///
+    /// Shapes that can not be drawn (e.g., a triangle with impossible sides)
+    /// are skipped.
+    pub fn write<W: Write>(&self, out: &mut W, shapes: &[KnownShape]) -> io::Result<usize> {
+        let mut layers: Vec<(String, usize)> = Vec::new();
+        for shape in shapes {
+            let layer = layer_name(shape.name());
+
+            if !layers.iter().any(|(name, _)| *name == layer) {
+                layers.push((layer, shape.kind_index() + 1));
+            }
+        }
+
+        // The header holds the next free handle, so the rest is written first
+        let mut body = Vec::new();
+        let mut handles = Handles::default();
+
+        let spaces = write_tables(&mut body, &mut handles, &layers)?;
+        write_blocks(&mut body, &mut handles, &spaces)?;
+        let model_space = &spaces[0].1;
+
+        group(&mut body, 0, "SECTION")?;
+        group(&mut body, 2, "ENTITIES")?;
+
+        let mut cursor = (0.0, 0.0);
+        let mut row_height: f64 = 0.0;
+        let mut count = 0;
+
+        for shape in shapes {
+            let points = match geometry::polygon(shape, f64::INFINITY) {
+                Some(points) => points,
+                None => continue,
+            };
+
+            let (min, max) = geometry::bounds(&points);
+            let (width, height) = (max.0 - min.0, max.1 - min.1);
+
+            if cursor.0 > 0.0 && cursor.0 + width > self.row_width {
+                cursor = (0.0, cursor.1 + row_height + self.spacing);
+                row_height = 0.0;
+            }
+
+            let offset = (cursor.0 - min.0, cursor.1 - min.1);
+            let layer = layer_name(shape.name());
+
+            let owner = (handles.next(), model_space.as_str());
+
+            match shape {
+                KnownShape::Circle(c) => {
+                    write_circle(&mut body, owner, &layer, offset, c.radius)?;
+                }
+                _ => {
+                    write_polyline(&mut body, owner, &layer, offset, &points)?;
+                }
+            }
+
+            cursor.0 += width + self.spacing;
+            row_height = row_height.max(height);
+            count += 1;
+        }
+
+        group(&mut body, 0, "ENDSEC")?;
+        write_objects(&mut body, &mut handles)?;
+        group(&mut body, 0, "EOF")?;
+
+        write_header(out, &handles)?;
+        out.write_all(&body)?;
+
+        Ok(count)
+    }
+}
+
+/// Write `shapes` with the default layout
+pub fn write_dxf<W: Write>(out: &mut W, shapes: &[KnownShape]) -> io::Result<usize> {
+    DxfWriter::new().write(out, shapes)
+}
+
+/// Write one group code and its value
+fn group<W, V>(out: &mut W, code: u16, value: V) -> io::Result<()>
+    where W: Write, V: std::fmt::Display {
+
+    writeln!(out, "{:>3}", code)?;
+    writeln!(out, "{}", value)
+}
+
+fn write_header<W: Write>(out: &mut W, handles: &Handles) -> io::Result<()> {
+    group(out, 0, "SECTION")?;
+    group(out, 2, "HEADER")?;
+    group(out, 9, "$ACADVER")?;
+    // LWPOLYLINE needs AutoCAD 2000 or later
+    group(out, 1, "AC1015")?;
+    group(out, 9, "$HANDSEED")?;
+    group(out, 5, handles.seed())?;
+    group(out, 9, "$INSUNITS")?;
+    group(out, 70, 0)?;
+    group(out, 0, "ENDSEC")
+}
+
+/// Hands out entity handles (hexadecimal, starting at 1)
+#[derive(Debug, Default)]
+struct Handles {
+    used: u32,
+}
+
+impl Handles {
+    fn next(&mut self) -> String {
+        self.used += 1;
+        format!("{:X}", self.used)
+    }
+
+    /// The first handle not yet handed out
+    fn seed(&self) -> String {
+        format!("{:X}", self.used + 1)
+    }
+}
+
+/// Start a symbol table with `count` records and return its handle
+fn begin_table<W: Write>(out: &mut W, handles: &mut Handles, name: &str,
+                         count: usize) -> io::Result<String> {
+    let handle = handles.next();
+
+    group(out, 0, "TABLE")?;
+    group(out, 2, name)?;
+    group(out, 5, &handle)?;
+    group(out, 330, 0)?;
+    group(out, 100, "AcDbSymbolTable")?;
+    group(out, 70, count)?;
+
+    Ok(handle)
+}
+
+/// Start a record of the table `owner` and return its handle. Only the
+/// name and the fields specific to the kind of record are left to write.
+fn begin_record<W: Write>(out: &mut W, handles: &mut Handles, owner: &str,
+                          kind: &str, subclass: &str) -> io::Result<String> {
+    let handle = handles.next();
+
+    group(out, 0, kind)?;
+    // Dimension styles keep their handle in 105 rather than 5
+    group(out, if kind == "DIMSTYLE" { 105 } else { 5 }, &handle)?;
+    group(out, 330, owner)?;
+    group(out, 100, "AcDbSymbolTableRecord")?;
+    group(out, 100, subclass)?;
+
+    Ok(handle)
+}
+
+/// Write every symbol table and return the name and handle of the model and
+/// paper space block records. The model space owns the entities.
+fn write_tables<W: Write>(out: &mut W, handles: &mut Handles,
+                          layers: &[(String, usize)]) -> io::Result<Vec<(&'static str, String)>> {
+    group(out, 0, "SECTION")?;
+    group(out, 2, "TABLES")?;
+
+    let table = begin_table(out, handles, "VPORT", 1)?;
+    begin_record(out, handles, &table, "VPORT", "AcDbViewportTableRecord")?;
+    group(out, 2, "*Active")?;
+    group(out, 70, 0)?;
+    group(out, 10, 0.0)?;
+    group(out, 20, 0.0)?;
+    group(out, 11, 1.0)?;
+    group(out, 21, 1.0)?;
+    group(out, 40, 10.0)?;
+    group(out, 41, 1.0)?;
+    group(out, 0, "ENDTAB")?;
+
+    let table = begin_table(out, handles, "LTYPE", 3)?;
+    for name in &["ByBlock", "ByLayer", "Continuous"] {
+        begin_record(out, handles, &table, "LTYPE", "AcDbLinetypeTableRecord")?;
+        group(out, 2, name)?;
+        group(out, 70, 0)?;
+        group(out, 3, if *name == "Continuous" { "Solid line" } else { "" })?;
+        group(out, 72, 65)?;
+        group(out, 73, 0)?;
+        group(out, 40, 0.0)?;
+    }
+    group(out, 0, "ENDTAB")?;
+
+    // Layer 0 always exists
+    let table = begin_table(out, handles, "LAYER", layers.len() + 1)?;
+    let default_layer = ("0".to_string(), 7);
+    for (name, color) in std::iter::once(&default_layer).chain(layers) {
+        begin_record(out, handles, &table, "LAYER", "AcDbLayerTableRecord")?;
+        group(out, 2, name)?;
+        group(out, 70, 0)?;
+        group(out, 62, color)?;
+        group(out, 6, "Continuous")?;
+    }
+    group(out, 0, "ENDTAB")?;
+
+    let table = begin_table(out, handles, "STYLE", 1)?;
+    begin_record(out, handles, &table, "STYLE", "AcDbTextStyleTableRecord")?;
+    group(out, 2, "Standard")?;
+    group(out, 70, 0)?;
+    group(out, 40, 0.0)?;
+    group(out, 41, 1.0)?;
+    group(out, 50, 0.0)?;
+    group(out, 71, 0)?;
+    group(out, 42, 2.5)?;
+    group(out, 3, "txt")?;
+    group(out, 4, "")?;
+    group(out, 0, "ENDTAB")?;
+
+    for name in &["VIEW", "UCS"] {
+        begin_table(out, handles, name, 0)?;
+        group(out, 0, "ENDTAB")?;
+    }
+
+    let table = begin_table(out, handles, "APPID", 1)?;
+    begin_record(out, handles, &table, "APPID", "AcDbRegAppTableRecord")?;
+    group(out, 2, "ACAD")?;
+    group(out, 70, 0)?;
+    group(out, 0, "ENDTAB")?;
+
+    let table = begin_table(out, handles, "DIMSTYLE", 1)?;
+    group(out, 100, "AcDbDimStyleTable")?;
+    begin_record(out, handles, &table, "DIMSTYLE", "AcDbDimStyleTableRecord")?;
+    group(out, 2, "Standard")?;
+    group(out, 70, 0)?;
+    group(out, 0, "ENDTAB")?;
+
+    let table = begin_table(out, handles, "BLOCK_RECORD", 2)?;
+    let mut spaces = Vec::new();
+    for name in &["*Model_Space", "*Paper_Space"] {
+        let handle =
+            begin_record(out, handles, &table, "BLOCK_RECORD", "AcDbBlockTableRecord")?;
+        group(out, 2, name)?;
+
+        spaces.push((*name, handle));
+    }
+    group(out, 0, "ENDTAB")?;
+
+    group(out, 0, "ENDSEC")?;
+
+    Ok(spaces)
+}
+
+/// Write the (empty) model and paper space blocks, each owned by its block
+/// record
+fn write_blocks<W: Write>(out: &mut W, handles: &mut Handles,
+                          spaces: &[(&str, String)]) -> io::Result<()> {
+    group(out, 0, "SECTION")?;
+    group(out, 2, "BLOCKS")?;
+
+    for (name, owner) in spaces {
+        group(out, 0, "BLOCK")?;
+        group(out, 5, handles.next())?;
+        group(out, 330, owner)?;
+        group(out, 100, "AcDbEntity")?;
+        group(out, 8, "0")?;
+        group(out, 100, "AcDbBlockBegin")?;
+        group(out, 2, name)?;
+        group(out, 70, 0)?;
+        group(out, 10, 0.0)?;
+        group(out, 20, 0.0)?;
+        group(out, 30, 0.0)?;
+        group(out, 3, name)?;
+        group(out, 1, "")?;
+
+        group(out, 0, "ENDBLK")?;
+        group(out, 5, handles.next())?;
+        group(out, 330, owner)?;
+        group(out, 100, "AcDbEntity")?;
+        group(out, 8, "0")?;
+        group(out, 100, "AcDbBlockEnd")?;
+    }
+
+    group(out, 0, "ENDSEC")
+}
+
+/// Write the root dictionary and the (empty) group dictionary it must hold
+fn write_objects<W: Write>(out: &mut W, handles: &mut Handles) -> io::Result<()> {
+    let root = handles.next();
+    let groups = handles.next();
+
+    group(out, 0, "SECTION")?;
+    group(out, 2, "OBJECTS")?;
+
+    group(out, 0, "DICTIONARY")?;
+    group(out, 5, &root)?;
+    group(out, 330, 0)?;
+    group(out, 100, "AcDbDictionary")?;
+    group(out, 281, 1)?;
+    group(out, 3, "ACAD_GROUP")?;
+    group(out, 350, &groups)?;
+
+    group(out, 0, "DICTIONARY")?;
+    group(out, 5, &groups)?;
+    group(out, 330, &root)?;
+    group(out, 100, "AcDbDictionary")?;
+    group(out, 281, 1)?;
+
+    group(out, 0, "ENDSEC")
+}
+
+/// Write the handle, owner and layer every entity starts with
+fn begin_entity<W: Write>(out: &mut W, kind: &str, (handle, owner): (String, &str),
+                          layer: &str) -> io::Result<()> {
+    group(out, 0, kind)?;
+    group(out, 5, handle)?;
+    group(out, 330, owner)?;
+    group(out, 100, "AcDbEntity")?;
+    group(out, 8, layer)
+}
+
+fn write_circle<W: Write>(out: &mut W, owner: (String, &str), layer: &str,
+                          center: Point, radius: f64) -> io::Result<()> {
+    begin_entity(out, "CIRCLE", owner, layer)?;
+    group(out, 100, "AcDbCircle")?;
+    group(out, 10, center.0)?;
+    group(out, 20, center.1)?;
+    group(out, 30, 0.0)?;
+    group(out, 40, radius)
+}
+
+fn write_polyline<W: Write>(out: &mut W, owner: (String, &str), layer: &str,
+                            offset: Point, points: &[Point]) -> io::Result<()> {
+    begin_entity(out, "LWPOLYLINE", owner, layer)?;
+    group(out, 100, "AcDbPolyline")?;
+    group(out, 90, points.len())?;
+    // Closed
+    group(out, 70, 1)?;
+
+    for (x, y) in points {
+        group(out, 10, x + offset.0)?;
+        group(out, 20, y + offset.1)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hamcrest2::prelude::*;
+
+    use crate::circle::Circle;
+    use crate::equilateral_triangle::EquilateralTriangle;
+    use crate::right_triangle::RightTriangle;
+    use crate::square::Square;
+    use crate::triangle::Triangle;
+
+    /// Split a drawing into (group code, value) pairs
+    fn pairs(text: &str) -> Vec<(u16, String)> {
+        let lines: Vec<&str> = text.lines().collect();
+        assert_that!(lines.len() % 2, is(equal_to(0)));
+
+        lines.chunks(2)
+            .map(|pair| (pair[0].trim().parse().unwrap(), pair[1].to_string()))
+            .collect()
+    }
+
+    /// The entities of a drawing, each as its (group code, value) pairs
+    fn entities(pairs: &[(u16, String)]) -> Vec<Vec<(u16, String)>> {
+        let start = pairs.iter()
+            .position(|p| *p == (2, "ENTITIES".to_string()))
+            .unwrap() + 1;
+
+        let mut entities: Vec<Vec<(u16, String)>> = Vec::new();
+        for pair in &pairs[start..] {
+            if pair.0 == 0 {
+                if pair.1 == "ENDSEC" {
+                    break;
+                }
+                entities.push(Vec::new());
+            }
+            entities.last_mut().unwrap().push(pair.clone());
+        }
+
+        entities
+    }
+
+    fn values(entity: &[(u16, String)], code: u16) -> Vec<f64> {
+        entity.iter()
+            .filter(|(c, _)| *c == code)
+            .map(|(_, v)| v.parse().unwrap())
+            .collect()
+    }
+
+    /// The names (group 2) of every `kind` record, table entry or block
+    fn names<'a>(pairs: &'a [(u16, String)], kind: &str) -> Vec<&'a str> {
+        pairs.iter()
+            .enumerate()
+            .filter(|(_, p)| p.0 == 0 && p.1 == kind)
+            .filter_map(|(i, _)| {
+                pairs[i + 1..].iter()
+                    .take_while(|p| p.0 != 0)
+                    .find(|p| p.0 == 2)
+                    .map(|p| p.1.as_str())
+            })
+            .collect()
+    }
+
+    fn layer(entity: &[(u16, String)]) -> &str {
+        &entity.iter().find(|(c, _)| *c == 8).unwrap().1
+    }
+
+    fn write(writer: &DxfWriter, shapes: &[KnownShape]) -> (usize, String) {
+        let mut out = Vec::new();
+        let count = writer.write(&mut out, shapes).unwrap();
+
+        (count, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_structure() {
+        let shapes: Vec<KnownShape> = vec![
+            Square::with_side(2.0).into(),
+            Circle::with_radius(1.5).into(),
+            RightTriangle::with_base_height(3.0, 4.0).into(),
+            Square::with_side(1.0).into(),
+        ];
+
+        let (count, text) = write(&DxfWriter::new(), &shapes);
+        let pairs = pairs(&text);
+
+        assert_that!(count, is(equal_to(4)));
+        assert_that!(pairs.first().cloned(), equal_to(Some((0, "SECTION".to_string()))));
+        assert_that!(pairs.last().cloned(), equal_to(Some((0, "EOF".to_string()))));
+
+        let sections: Vec<&str> = pairs.windows(2)
+            .filter(|w| w[0] == (0, "SECTION".to_string()))
+            .map(|w| w[1].1.as_str())
+            .collect();
+        assert_that!(sections, equal_to(vec!["HEADER", "TABLES", "BLOCKS", "ENTITIES", "OBJECTS"]));
+
+        let tables: Vec<&str> = pairs.windows(2)
+            .filter(|w| w[0] == (0, "TABLE".to_string()))
+            .map(|w| w[1].1.as_str())
+            .collect();
+        assert_that!(tables, equal_to(vec!["VPORT", "LTYPE", "LAYER", "STYLE", "VIEW", "UCS",
+                                           "APPID", "DIMSTYLE", "BLOCK_RECORD"]));
+
+        assert_that!(names(&pairs, "LAYER"), equal_to(vec!["0", "SQUARE", "CIRCLE", "RIGHT_TRIANGLE"]));
+        assert_that!(names(&pairs, "BLOCK_RECORD"), equal_to(vec!["*Model_Space", "*Paper_Space"]));
+        assert_that!(names(&pairs, "BLOCK"), equal_to(vec!["*Model_Space", "*Paper_Space"]));
+
+        let entities = entities(&pairs);
+        let kinds: Vec<&str> = entities.iter().map(|e| e[0].1.as_str()).collect();
+        assert_that!(kinds, equal_to(vec!["LWPOLYLINE", "CIRCLE", "LWPOLYLINE", "LWPOLYLINE"]));
+        assert_that!(layer(&entities[2]), equal_to("RIGHT_TRIANGLE"));
+
+        let subclasses: Vec<Vec<&str>> = entities.iter()
+            .map(|e| e.iter().filter(|(c, _)| *c == 100).map(|(_, v)| v.as_str()).collect())
+            .collect();
+        assert_that!(subclasses[0].clone(), equal_to(vec!["AcDbEntity", "AcDbPolyline"]));
+        assert_that!(subclasses[1].clone(), equal_to(vec!["AcDbEntity", "AcDbCircle"]));
+
+        // Square: 4 closed vertices, starting at the origin
+        assert_that!(values(&entities[0], 90), equal_to(vec![4.0]));
+        assert_that!(values(&entities[0], 70), equal_to(vec![1.0]));
+        assert_that!(values(&entities[0], 10), equal_to(vec![0.0, 2.0, 2.0, 0.0]));
+        assert_that!(values(&entities[0], 20), equal_to(vec![0.0, 0.0, 2.0, 2.0]));
+
+        // Circle: 1 unit after the square, resting on the x-axis
+        assert_that!(values(&entities[1], 10), equal_to(vec![4.5]));
+        assert_that!(values(&entities[1], 20), equal_to(vec![1.5]));
+        assert_that!(values(&entities[1], 40), equal_to(vec![1.5]));
+
+        assert_that!(values(&entities[2], 10), equal_to(vec![7.0, 10.0, 7.0]));
+    }
+
+    #[test]
+    fn test_handles() {
+        let shapes: Vec<KnownShape> = vec![
+            Circle::with_radius(1.0).into(),
+            Square::with_side(2.0).into(),
+        ];
+
+        let (_, text) = write(&DxfWriter::new(), &shapes);
+        let pairs = pairs(&text);
+
+        let handle = |value: &str| u32::from_str_radix(value, 16).unwrap();
+        let field = |record: &[(u16, String)], code| {
+            record.iter().find(|(c, _)| *c == code).map(|(_, v)| v.clone())
+        };
+
+        // Every table, record, block, entity and dictionary has a unique handle
+        let records: Vec<&[(u16, String)]> = pairs.split(|p| p.0 == 0 && p.1 == "SECTION")
+            .flat_map(|section| {
+                let starts: Vec<usize> = (0..section.len())
+                    .filter(|&i| section[i].0 == 0)
+                    .collect();
+                (0..starts.len())
+                    .map(|j| &section[starts[j]..*starts.get(j + 1).unwrap_or(&section.len())])
+                    .filter(|r| !["ENDSEC", "ENDTAB", "EOF"].contains(&r[0].1.as_str()))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        let handles: Vec<u32> = records.iter()
+            .map(|r| {
+                let code = if r[0].1 == "DIMSTYLE" { 105 } else { 5 };
+                handle(&field(r, code).unwrap_or_else(|| panic!("{:?}", r)))
+            })
+            .collect();
+
+        let mut unique = handles.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        assert_that!(unique.len(), is(equal_to(handles.len())));
+
+        // ... below the seed in the header
+        let seed = pairs.windows(2)
+            .find(|w| w[0] == (9, "$HANDSEED".to_string()))
+            .map(|w| handle(&w[1].1))
+            .unwrap();
+        assert!(handles.iter().all(|&h| h < seed));
+
+        // ... and every owner is one of them
+        for (_, owner) in pairs.iter().filter(|(c, _)| *c == 330 || *c == 350) {
+            assert!(owner == "0" || handles.contains(&handle(owner)), "{}", owner);
+        }
+
+        // The entities belong to the model space
+        let model_space = records.iter()
+            .find(|r| r[0].1 == "BLOCK_RECORD" && field(r, 2).unwrap() == "*Model_Space")
+            .and_then(|r| field(r, 5));
+        for entity in entities(&pairs) {
+            assert_that!(field(&entity, 330), equal_to(model_space.clone()));
+        }
+    }
+
+    #[test]
+    fn test_no_overlap() {
+        let shapes: Vec<KnownShape> = vec![
+            EquilateralTriangle::with_side(2.0).into(),
+            Triangle::with_sides(3.0, 5.0, 7.0).into(),
+            Circle::with_radius(2.0).into(),
+            Square::with_side(3.0).into(),
+            RightTriangle::with_base_height(2.0, 5.0).into(),
+            Triangle::with_sides(1.0, 1.0, 3.0).into(),
+        ];
+
+        let writer = DxfWriter::new().spacing(0.5).row_width(10.0);
+        let (count, text) = write(&writer, &shapes);
+        let entities = entities(&pairs(&text));
+
+        // The impossible triangle is skipped
+        assert_that!(count, is(equal_to(5)));
+        assert_that!(entities.len(), is(equal_to(5)));
+
+        let boxes: Vec<(Point, Point)> = entities.iter()
+            .map(|e| {
+                if e[0].1 == "CIRCLE" {
+                    let (x, y, r) = (values(e, 10)[0], values(e, 20)[0], values(e, 40)[0]);
+                    ((x - r, y - r), (x + r, y + r))
+                }
+                else {
+                    let points: Vec<Point> = values(e, 10).into_iter()
+                        .zip(values(e, 20))
+                        .collect();
+                    geometry::bounds(&points)
+                }
+            })
+            .collect();
+
+        for (i, a) in boxes.iter().enumerate() {
+            assert!(a.0.0 >= -1e-9 && a.0.1 >= -1e-9);
+
+            for b in &boxes[i + 1..] {
+                let apart = a.1.0 + 0.5 <= b.0.0 + 1e-9 || b.1.0 + 0.5 <= a.0.0 + 1e-9
+                    || a.1.1 + 0.5 <= b.0.1 + 1e-9 || b.1.1 + 0.5 <= a.0.1 + 1e-9;
+                assert!(apart, "{:?} overlaps {:?}", a, b);
+            }
+        }
+
+        // Rows wrap at 10 units
+        assert!(boxes.iter().all(|b| b.1.0 <= 10.0 + 1e-9));
+        assert!(boxes.iter().any(|b| b.0.1 > 0.0));
+    }
+
+    #[test]
+    fn test_layer_name() {
+        assert_that!(layer_name("Equilateral Triangle"),
+                     equal_to("EQUILATERAL_TRIANGLE".to_string()));
+    }
+
+    #[test]
+    fn test_empty() {
+        let (count, text) = write(&DxfWriter::new(), &[]);
+
+        assert_that!(count, is(equal_to(0)));
+        assert!(entities(&pairs(&text)).is_empty());
+    }
+}
@@ -1,4 +1,6 @@
 use crate::shape::Shape;
+use crate::formatter::ShapeFormatter;
+use crate::named_params::{FromNamedParams, NamedParams};
 
 use std::fmt;
 
@@ -43,6 +45,28 @@ impl Shape for EquilateralTriangle {
     fn area(&self) -> f64 {
         3_f64.sqrt() / 4_f64 * self.side.powi(2)
     }
+
+    fn fields(&self) -> Vec<(&'static str, f64)> {
+        vec![
+            ("Side", self.side),
+            ("Height", self.height()),
+        ]
+    }
+}
+
+impl FromNamedParams for EquilateralTriangle {
+    /// Accept `side`, `perimeter`, `height` or `area`
+    fn from_named(params: &NamedParams) -> Option<Self> {
+        let sqrt_3 = 3_f64.sqrt();
+
+        params.exactly(["side"]).map(|[s]| s)
+            .or_else(|| params.exactly(["perimeter"]).map(|[p]| p / 3.0))
+            .or_else(|| params.exactly(["height"]).map(|[h]| 2.0 * h / sqrt_3))
+            .or_else(|| {
+                params.exactly(["area"]).map(|[a]| (4.0 * a / sqrt_3).sqrt())
+            })
+            .map(EquilateralTriangle::with_side)
+    }
 }
 
 impl Default for EquilateralTriangle {
@@ -53,11 +77,7 @@ impl Default for EquilateralTriangle {
 
 impl fmt::Display for EquilateralTriangle {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(f, "{:12}:{:>24}", "Name", self.name())?;
-        writeln!(f, "{:12}:{:>24.4}", "Side", self.side)?;
-        writeln!(f, "{:12}:{:>24.4}", "Height", self.height())?;
-        writeln!(f, "{:12}:{:>24.4}", "Perimeter", self.perimeter())?;
-        writeln!(f, "{:12}:{:>24.4}", "Area", self.area())
+        ShapeFormatter::default().write(f, self)
     }
 }
 
@@ -65,7 +85,6 @@ impl fmt::Display for EquilateralTriangle {
 mod tests {
     use super::*;
     use std::f64;
-    use std::ptr;
     use hamcrest2::prelude::*;
 
     #[test]
@@ -142,4 +161,17 @@ mod tests {
 
         assert!(fancy_str.ends_with("\n"));
     }
+
+    #[test]
+    fn test_from_named() {
+        let params = NamedParams::parse("perimeter=9").unwrap();
+        let fancy = EquilateralTriangle::from_named(&params).unwrap();
+        assert_that!(fancy.side, close_to(3.0, 1e-8));
+
+        let generic = EquilateralTriangle::new();
+        let params = NamedParams::parse(&format!("area={}", generic.area()))
+            .unwrap();
+        let fancy = EquilateralTriangle::from_named(&params).unwrap();
+        assert_that!(fancy.side, close_to(1.0, 1e-8));
+    }
 }
@@ -0,0 +1,483 @@
+//! Exact (rational) arithmetic for shape measurements.
+//!
+//! Everything in the rest of the crate is computed with `f64`. This module
+//! converts shape dimensions to rationals (every finite `f64` is exactly
+//! representable as one) and computes area and perimeter without rounding.
+//!
+//! Results that involve a square root or $\pi$ can not be rational. Those are
+//! returned in symbolic form (e.g., $\frac{9}{4}\sqrt{3}$ or $25\pi$). Any
+//! result can be turned into a tight interval with [`ExactValue::bounds`].
+
+use std::fmt;
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+use crate::circle::Circle;
+use crate::equilateral_triangle::EquilateralTriangle;
+use crate::known_shape::KnownShape;
+use crate::right_triangle::RightTriangle;
+use crate::square::Square;
+use crate::triangle::Triangle;
+
+/// Largest trial divisor used when pulling square factors out of a radicand
+const MAX_TRIAL_DIVISOR: u32 = 1000;
+
+/// An exact area or perimeter
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExactValue {
+    /// A plain rational number
+    Rational(BigRational),
+
+    /// $rational + coeff \sqrt{radicand}$, where `radicand` is an integer
+    /// that is not a perfect square
+    Surd {
+        rational: BigRational,
+        coeff: BigRational,
+        radicand: BigInt,
+    },
+
+    /// $coeff \pi$
+    Pi(BigRational),
+}
+
+impl ExactValue {
+    /// Return the rational value, if this value is rational
+    pub fn as_rational(&self) -> Option<&BigRational> {
+        match self {
+            ExactValue::Rational(r) => Some(r),
+            _ => None,
+        }
+    }
+
+    /// Check whether the value is a plain rational number
+    pub fn is_rational(&self) -> bool {
+        self.as_rational().is_some()
+    }
+
+    /// Compute the nearest `f64`
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            ExactValue::Rational(r) => ratio_to_f64(r),
+            ExactValue::Surd {
+                rational,
+                coeff,
+                radicand,
+            } => {
+                let root = radicand.to_f64().unwrap_or(f64::NAN).sqrt();
+                ratio_to_f64(rational) + ratio_to_f64(coeff) * root
+            }
+            ExactValue::Pi(coeff) => ratio_to_f64(coeff) * std::f64::consts::PI,
+        }
+    }
+
+    /// Compute an interval `(lo, hi)` that contains the exact value.
+    ///
+    /// Rational values that fit in an `f64` produce a zero-width interval.
+    /// Everything else is widened by a few ulps to absorb rounding in
+    /// [`ExactValue::to_f64`].
+    pub fn bounds(&self) -> (f64, f64) {
+        let approx = self.to_f64();
+
+        if let ExactValue::Rational(r) = self {
+            if BigRational::from_float(approx).as_ref() == Some(r) {
+                return (approx, approx);
+            }
+        }
+
+        let slack = approx.abs() * 4.0 * f64::EPSILON;
+        (approx - slack, approx + slack)
+    }
+
+    /// Add a rational number to this value. Return `None` if the sum can
+    /// not be represented (i.e., a rational plus a multiple of $\pi$).
+    fn plus_rational(self, rhs: &BigRational) -> Option<ExactValue> {
+        match self {
+            ExactValue::Rational(r) => Some(ExactValue::Rational(r + rhs)),
+            ExactValue::Surd {
+                rational,
+                coeff,
+                radicand,
+            } => Some(ExactValue::Surd {
+                rational: rational + rhs,
+                coeff,
+                radicand,
+            }),
+            ExactValue::Pi(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for ExactValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExactValue::Rational(r) => write!(f, "{}", r),
+            ExactValue::Surd {
+                rational,
+                coeff,
+                radicand,
+            } => {
+                if !rational.is_zero() {
+                    write!(f, "{} + ", rational)?;
+                }
+
+                write_coeff(f, coeff)?;
+                write!(f, "√{}", radicand)
+            }
+            ExactValue::Pi(coeff) => {
+                write_coeff(f, coeff)?;
+                write!(f, "π")
+            }
+        }
+    }
+}
+
+/// Write the coefficient of `√` or `π`, if it is not 1. A fraction is
+/// parenthesized, e.g., `(9/4)√3`, so that it is not read as `9/(4√3)`.
+fn write_coeff(f: &mut fmt::Formatter, coeff: &BigRational) -> fmt::Result {
+    if coeff.is_one() {
+        Ok(())
+    }
+    else if coeff.is_integer() {
+        write!(f, "{}", coeff)
+    }
+    else {
+        write!(f, "({})", coeff)
+    }
+}
+
+/// Exact counterparts of [`Shape::area`](crate::shape::Shape::area) and
+/// [`Shape::perimeter`](crate::shape::Shape::perimeter).
+///
+/// Both return `None` if a dimension is not finite or the result is not a
+/// real number (e.g., a triangle that violates the triangle inequality).
+pub trait ExactShape {
+    /// Compute the area exactly
+    fn exact_area(&self) -> Option<ExactValue>;
+
+    /// Compute the perimeter exactly
+    fn exact_perimeter(&self) -> Option<ExactValue>;
+}
+
+impl ExactShape for Square {
+    /// $s^2$
+    fn exact_area(&self) -> Option<ExactValue> {
+        let s = to_ratio(self.side)?;
+        Some(ExactValue::Rational(&s * &s))
+    }
+
+    /// $4s$
+    fn exact_perimeter(&self) -> Option<ExactValue> {
+        let s = to_ratio(self.side)?;
+        Some(ExactValue::Rational(s * int(4)))
+    }
+}
+
+impl ExactShape for RightTriangle {
+    /// $\frac{1}{2} base \cdot height$
+    fn exact_area(&self) -> Option<ExactValue> {
+        let b = to_ratio(self.base)?;
+        let h = to_ratio(self.height)?;
+        Some(ExactValue::Rational(b * h / int(2)))
+    }
+
+    /// $base + height + \sqrt{base^2 + height^2}$
+    fn exact_perimeter(&self) -> Option<ExactValue> {
+        let b = to_ratio(self.base)?;
+        let h = to_ratio(self.height)?;
+        let hypotenuse = sqrt_ratio(&(&b * &b + &h * &h))?;
+        hypotenuse.plus_rational(&(b + h))
+    }
+}
+
+impl ExactShape for Triangle {
+    /// Heron's Formula, $\sqrt{ s(s-a)(s-b)(s-c) }$
+    fn exact_area(&self) -> Option<ExactValue> {
+        let a = to_ratio(self.side_a)?;
+        let b = to_ratio(self.side_b)?;
+        let c = to_ratio(self.side_c)?;
+        let s = (&a + &b + &c) / int(2);
+
+        sqrt_ratio(&(&s * (&s - a) * (&s - b) * (&s - c)))
+    }
+
+    /// $a + b + c$
+    fn exact_perimeter(&self) -> Option<ExactValue> {
+        let a = to_ratio(self.side_a)?;
+        let b = to_ratio(self.side_b)?;
+        let c = to_ratio(self.side_c)?;
+        Some(ExactValue::Rational(a + b + c))
+    }
+}
+
+impl ExactShape for EquilateralTriangle {
+    /// $\frac{\sqrt{3}}{4} s^2$
+    fn exact_area(&self) -> Option<ExactValue> {
+        let s = to_ratio(self.side)?;
+        let coeff = &s * &s / int(4);
+
+        if coeff.is_zero() {
+            return Some(ExactValue::Rational(coeff));
+        }
+
+        Some(ExactValue::Surd {
+            rational: BigRational::zero(),
+            coeff,
+            radicand: BigInt::from(3),
+        })
+    }
+
+    /// $3s$
+    fn exact_perimeter(&self) -> Option<ExactValue> {
+        let s = to_ratio(self.side)?;
+        Some(ExactValue::Rational(s * int(3)))
+    }
+}
+
+impl ExactShape for Circle {
+    /// $\pi r^2$
+    fn exact_area(&self) -> Option<ExactValue> {
+        let r = to_ratio(self.radius)?;
+        Some(pi_times(&r * &r))
+    }
+
+    /// $2\pi r$
+    fn exact_perimeter(&self) -> Option<ExactValue> {
+        let r = to_ratio(self.radius)?;
+        Some(pi_times(r * int(2)))
+    }
+}
+
+impl ExactShape for KnownShape {
+    fn exact_area(&self) -> Option<ExactValue> {
+        match self {
+            KnownShape::Triangle(s) => s.exact_area(),
+            KnownShape::RightTriangle(s) => s.exact_area(),
+            KnownShape::EquilateralTriangle(s) => s.exact_area(),
+            KnownShape::Square(s) => s.exact_area(),
+            KnownShape::Circle(s) => s.exact_area(),
+        }
+    }
+
+    fn exact_perimeter(&self) -> Option<ExactValue> {
+        match self {
+            KnownShape::Triangle(s) => s.exact_perimeter(),
+            KnownShape::RightTriangle(s) => s.exact_perimeter(),
+            KnownShape::EquilateralTriangle(s) => s.exact_perimeter(),
+            KnownShape::Square(s) => s.exact_perimeter(),
+            KnownShape::Circle(s) => s.exact_perimeter(),
+        }
+    }
+}
+
+/// Compute the area of a simple polygon with integer vertex coordinates
+/// using the Shoelace Formula.
+///
+/// # Arguments
+///
+///  * `vertices` - polygon vertices in order (either orientation)
+///
+pub fn polygon_area(vertices: &[(i64, i64)]) -> BigRational {
+    let n = vertices.len();
+
+    let twice_area: BigInt = (0..n)
+        .map(|i| {
+            let (x1, y1) = vertices[i];
+            let (x2, y2) = vertices[(i + 1) % n];
+            BigInt::from(x1) * y2 - BigInt::from(x2) * y1
+        })
+        .sum();
+
+    BigRational::new(twice_area.abs(), BigInt::from(2))
+}
+
+fn int(n: i64) -> BigRational {
+    BigRational::from_integer(BigInt::from(n))
+}
+
+fn to_ratio(x: f64) -> Option<BigRational> {
+    BigRational::from_float(x)
+}
+
+fn ratio_to_f64(r: &BigRational) -> f64 {
+    r.to_f64().unwrap_or(f64::NAN)
+}
+
+fn pi_times(coeff: BigRational) -> ExactValue {
+    if coeff.is_zero() {
+        ExactValue::Rational(coeff)
+    }
+    else {
+        ExactValue::Pi(coeff)
+    }
+}
+
+/// Compute $\sqrt{r}$, returning a rational whenever possible.
+///
+/// $\sqrt{p/q} = \sqrt{pq}/q$, so the radicand is always an integer. Small
+/// square factors are moved out of the radicand into the coefficient.
+fn sqrt_ratio(r: &BigRational) -> Option<ExactValue> {
+    if r.is_negative() {
+        return None;
+    }
+
+    let mut radicand = r.numer() * r.denom();
+    let mut coeff = BigRational::new(BigInt::one(), r.denom().clone());
+
+    let root = radicand.sqrt();
+    if &root * &root == radicand {
+        return Some(ExactValue::Rational(coeff * root));
+    }
+
+    for k in 2..=MAX_TRIAL_DIVISOR {
+        let square = BigInt::from(k * k);
+
+        if square > radicand {
+            break;
+        }
+
+        while (&radicand % &square).is_zero() {
+            radicand /= &square;
+            coeff *= int(k.into());
+        }
+    }
+
+    Some(ExactValue::Surd {
+        rational: BigRational::zero(),
+        coeff,
+        radicand,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hamcrest2::prelude::*;
+
+    fn ratio(n: i64, d: i64) -> BigRational {
+        BigRational::new(BigInt::from(n), BigInt::from(d))
+    }
+
+    #[test]
+    fn test_square() {
+        let fancy = Square::with_side(2.5);
+
+        assert_that!(fancy.exact_area(),
+                     equal_to(Some(ExactValue::Rational(ratio(25, 4)))));
+        assert_that!(fancy.exact_perimeter(),
+                     equal_to(Some(ExactValue::Rational(int(10)))));
+    }
+
+    #[test]
+    fn test_right_triangle() {
+        let fancy = RightTriangle::with_base_height(3.0, 4.0);
+
+        assert_that!(fancy.exact_area(),
+                     equal_to(Some(ExactValue::Rational(int(6)))));
+        assert_that!(fancy.exact_perimeter(),
+                     equal_to(Some(ExactValue::Rational(int(12)))));
+
+        let generic = RightTriangle::new();
+        let perimeter = generic.exact_perimeter().unwrap();
+
+        assert_that!(perimeter.to_string(), equal_to("2 + √2"));
+        assert_that!(perimeter.is_rational(), is(false));
+    }
+
+    #[test]
+    fn test_plus_rational() {
+        assert_that!(ExactValue::Rational(int(1)).plus_rational(&ratio(1, 2)),
+                     equal_to(Some(ExactValue::Rational(ratio(3, 2)))));
+        assert_that!(ExactValue::Pi(int(1)).plus_rational(&int(1)), equal_to(None));
+    }
+
+    #[test]
+    fn test_triangle() {
+        let fancy = Triangle::with_sides(3.0, 4.0, 5.0);
+
+        assert_that!(fancy.exact_area(),
+                     equal_to(Some(ExactValue::Rational(int(6)))));
+        assert_that!(fancy.exact_perimeter(),
+                     equal_to(Some(ExactValue::Rational(int(12)))));
+
+        // s = 6, A^2 = 6 * 4 * 1 * 1 = 24 -> 2 sqrt(6)
+        let odd = Triangle::with_sides(2.0, 5.0, 5.0);
+        assert_that!(odd.exact_area().unwrap().to_string(), equal_to("2√6"));
+
+        let impossible = Triangle::with_sides(1.0, 2.0, 5.0);
+        assert!(impossible.exact_area().is_none());
+    }
+
+    #[test]
+    fn test_equilateral_triangle() {
+        let fancy = EquilateralTriangle::with_side(3.0);
+        let area = fancy.exact_area().unwrap();
+
+        assert_that!(area.to_string(), equal_to("(9/4)√3"));
+
+        let (lo, hi) = area.bounds();
+        let approx = 3_f64.sqrt() / 4.0 * 9.0;
+        assert!(lo <= approx && approx <= hi);
+    }
+
+    #[test]
+    fn test_circle() {
+        let fancy = Circle::with_radius(5.0);
+
+        assert_that!(fancy.exact_area(),
+                     equal_to(Some(ExactValue::Pi(int(25)))));
+        assert_that!(fancy.exact_perimeter().unwrap().to_string(),
+                     equal_to("10π"));
+        assert_that!(Circle::new().exact_area().unwrap().to_string(),
+                     equal_to("π"));
+    }
+
+    #[test]
+    fn test_known_shape() {
+        let a_shape = KnownShape::Square(Square::with_side(3.0));
+
+        assert_that!(a_shape.exact_area(),
+                     equal_to(Some(ExactValue::Rational(int(9)))));
+
+        let broken = KnownShape::Circle(Circle::with_radius(f64::NAN));
+        assert!(broken.exact_area().is_none());
+    }
+
+    #[test]
+    fn test_bounds() {
+        let exact = ExactValue::Rational(ratio(1, 3));
+        let (lo, hi) = exact.bounds();
+
+        assert!(lo < hi);
+        assert!(lo <= 1.0 / 3.0 && 1.0 / 3.0 <= hi);
+
+        let exact = ExactValue::Rational(int(6));
+        assert_that!(exact.bounds(), equal_to((6.0, 6.0)));
+    }
+
+    #[test]
+    fn test_polygon_area() {
+        let unit_square = [(0, 0), (1, 0), (1, 1), (0, 1)];
+        assert_that!(polygon_area(&unit_square), equal_to(int(1)));
+
+        let tri = [(0, 0), (0, 1), (1, 0)];
+        assert_that!(polygon_area(&tri), equal_to(ratio(1, 2)));
+    }
+
+    #[test]
+    fn test_display() {
+        let surd = |rational, coeff| ExactValue::Surd {
+            rational,
+            coeff,
+            radicand: BigInt::from(3),
+        };
+
+        assert_that!(ExactValue::Rational(ratio(9, 4)).to_string(), equal_to("9/4"));
+        assert_that!(surd(int(0), ratio(9, 4)).to_string(), equal_to("(9/4)√3"));
+        assert_that!(surd(ratio(1, 2), int(2)).to_string(), equal_to("1/2 + 2√3"));
+        assert_that!(surd(int(1), int(1)).to_string(), equal_to("1 + √3"));
+        assert_that!(ExactValue::Pi(ratio(1, 2)).to_string(), equal_to("(1/2)π"));
+        assert_that!(ExactValue::Pi(int(2)).to_string(), equal_to("2π"));
+    }
+}
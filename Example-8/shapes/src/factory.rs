@@ -1,30 +1,20 @@
 use std::collections::HashSet;
 use std::fmt;
-use std::io::BufRead;
-use std::convert::From;
+use std::io::{self, BufRead, Lines, Write};
+use std::path::{Path, PathBuf};
 
 use itertools::Itertools;
 
-use crate::shape::Shape;
-use crate::square::Square;
-use crate::circle::Circle;
-use crate::triangle::Triangle;
-use crate::equilateral_triangle::EquilateralTriangle;
-use crate::right_triangle::RightTriangle;
+use crate::compress;
 use crate::known_shape::KnownShape;
+use crate::locale::Locale;
+use crate::named_params::NamedParams;
+use crate::shape::Shape;
 
 
 lazy_static! {
     static ref KNOWN_SHAPES: HashSet<&'static str> = {
-        let mut known_shapes = HashSet::new();
-
-        known_shapes.insert("Triangle");
-        known_shapes.insert("Right Triangle");
-        known_shapes.insert("Equilateral Triangle");
-        known_shapes.insert("Square");
-        known_shapes.insert("Circle");
-
-        known_shapes
+        KnownShape::KNOWN_NAMES.iter().copied().collect()
     };
 
     static ref NUMBER_KNOWN: usize = KNOWN_SHAPES.len();
@@ -37,14 +27,7 @@ lazy_static! {
 ///   * `name` shape to be created
 ///
 pub fn create(name: &str) -> Option<KnownShape> {
-    match name  {
-        "Triangle" => Some(Triangle::new().into()),
-        "Right Triangle" => Some(RightTriangle::new().into()),
-        "Equilateral Triangle" => Some(EquilateralTriangle::new().into()),
-        "Square" => Some(Square::new().into()),
-        "Circle" => Some(Circle::new().into()),
-        _ =>  None
-    }
+    KnownShape::from_name(name)
 }
 
 /// Create a Shape with specified dimensions.
@@ -55,24 +38,7 @@ pub fn create(name: &str) -> Option<KnownShape> {
 ///   * `dims` input dimensions
 ///
 pub fn create_with(name: &str, dims: &[f64]) -> Option<KnownShape> {
-    match name  {
-        "Triangle" => {
-            Some(Triangle::from(dims).into())
-        },
-        "Right Triangle" => {
-            Some(RightTriangle::from(dims).into())
-        },
-        "Equilateral Triangle" => {
-            Some(EquilateralTriangle::from(dims).into())
-        },
-        "Square" => {
-            Some(Square::from(dims).into())
-        },
-        "Circle" => {
-            Some(Circle::from(dims).into())
-        },
-        _ =>  None
-    }
+    KnownShape::from_name_with(name, dims)
 }
 
 /// Determine whether a given shape is known
@@ -125,8 +91,42 @@ pub fn read_shapes<B: BufRead>(ins: B)-> Vec<KnownShape> {
         // .collect()
 }
 
+/// Create a Shape from named dimensions (e.g., `diameter=10`). Any
+/// equivalent set of parameters is accepted.
+///
+/// # Arguments
+///
+///   * `name` shape to be created
+///   * `params` named dimensions
+///
+pub fn create_named(name: &str, params: &NamedParams) -> Option<KnownShape> {
+    KnownShape::from_name_params(name, params)
+}
+
+/// Create a Shape with specified dimensions from a name in any language
+/// supported by `locale` (English names are always accepted).
+///
+/// # Arguments
+///
+///   * `name` shape to be created
+///   * `dims` input dimensions
+///   * `locale` language of `name`
+///
+pub fn create_localized(name: &str, dims: &[f64], locale: &Locale)
+    -> Option<KnownShape> {
+    create_with(locale.canonical_name(name)?, dims)
+}
+
 /// Create shapes based on names *and dimension data* from an input buffer.
 ///
+/// Dimensions may be positional (`Right Triangle; 4 5`) or named
+/// (`Right Triangle; base=4 height=5`).
+///
+/// Lines that are not valid shapes are skipped. So are lines that can not
+/// be followed, i.e., `include` directives (use `read_shapes_file` to follow
+/// them) and unsupported version headers. Reading stops at a line that can
+/// not be read.
+///
 /// # Arguments
 ///
 ///  * `ins` - input source
@@ -134,41 +134,494 @@ pub fn read_shapes<B: BufRead>(ins: B)-> Vec<KnownShape> {
 pub fn read_shapes_with<B>(ins: B)-> Vec<KnownShape>
     where B: BufRead  {
 
-    let mut shapes: Vec<KnownShape> = Vec::new();
+    read_shapes_with_locale(ins, &Locale::english())
+}
 
-    for line in ins.lines() {
-        let raw_line = line.unwrap();
+/// Create shapes based on (possibly localized) names *and dimension data*
+/// from an input buffer.
+///
+/// # Arguments
+///
+///  * `ins` - input source
+///  * `locale` - language of the shape names
+///
+pub fn read_shapes_with_locale<B>(ins: B, locale: &Locale)-> Vec<KnownShape>
+    where B: BufRead  {
+
+    ShapeReader::with_locale(ins, locale.clone()).collect()
+}
+
+/// Write shapes as `name; dims` lines, the format read by
+/// `read_shapes_with`.
+///
+/// With no `precision`, every dimension is written with the fewest digits
+/// that parse back to the same value, so reading the output recreates
+/// identical shapes.
+///
+/// # Arguments
+///
+///  * `out` - output destination
+///  * `shapes` - shapes to write
+///  * `precision` - digits after the decimal point, if rounding is wanted
+///
+pub fn write_shapes_with<'a, W, I>(out: &mut W, shapes: I,
+                                   precision: Option<usize>) -> io::Result<()>
+    where W: Write,
+          I: IntoIterator<Item = &'a KnownShape> {
+
+    for shape in shapes {
+        let dims = shape.params()
+            .iter()
+            .map(|dim| match precision {
+                Some(digits) => format!("{:.*}", digits, dim),
+                None => dim.to_string(),
+            })
+            .join(" ");
+
+        writeln!(out, "{}; {}", shape.name(), dims)?;
+    }
+
+    Ok(())
+}
+
+/// Newest version of the text format, declared by an optional first line
+/// such as `#!shapes v2`.
+///
+///   * v1 - `name; dims` lines
+///   * v2 - adds `#` comments, quoted names (`"Right Triangle"; 3 4`) and
+///     `include "other.txt"` directives
+///
+/// Every v1 file is also a valid v2 file.
+pub const FORMAT_VERSION: u32 = 2;
+
+/// Create one shape from a `name; dims` line. Return `Ok(None)` for blank
+/// and comment lines.
+///
+/// # Arguments
+///
+///  * `raw_line` - one line of input
+///  * `locale` - language of the shape name
+///
+/// # Errors
+///
+/// If the line is not a valid shape (e.g., it has an unknown name or the
+/// wrong number of dimensions) or is an `include` directive, which can only
+/// be followed by [`ShapeLines`].
+pub fn parse_line(raw_line: &str, locale: &Locale)
+    -> Result<Option<KnownShape>, String> {
+
+    parse_line_with(raw_line, locale, true)
+}
+
+/// `parse_line` that, unless `strict`, returns `Ok(None)` rather than an
+/// error for a line that is not a valid shape. Directives are always errors.
+pub(crate) fn parse_line_with(raw_line: &str, locale: &Locale, strict: bool)
+    -> Result<Option<KnownShape>, String> {
+
+    match include_path(raw_line) {
+        Some(Ok(_)) => Err(INCLUDE_OUTSIDE_FILE.to_string()),
+        Some(Err(err)) => Err(err),
+        None if strict => parse_shape(raw_line, locale),
+        None => Ok(parse_shape(raw_line, locale).unwrap_or(None)),
+    }
+}
+
+/// `parse_line` for a line that is known not to be a directive
+fn parse_shape(raw_line: &str, locale: &Locale)
+    -> Result<Option<KnownShape>, String> {
+
+    let line = strip_comment(raw_line).trim();
+
+    if line.is_empty() {
+        return Ok(None);
+    }
+
+    let (n, dims_str) = split_name(line)
+        .ok_or_else(|| format!("expected 'name; dimensions', found '{}'", line))?;
+    let name = locale.canonical_name(n)
+        .ok_or_else(|| format!("unknown shape '{}'", n))?;
+
+    // Named parameters (e.g., "base=4 height=5")
+    if dims_str.contains('=') {
+        let shape = NamedParams::parse(dims_str)
+            .and_then(|params| create_named(name, &params));
+
+        return shape.map(Some).ok_or_else(|| {
+            format!("{} can not be built from '{}'", name, dims_str.trim())
+        });
+    }
+
+    // Mistake -> s.len() > 0 != s.is_empty() -> I forgot the leading '!'
+    let dims: Vec<f64> = dims_str.split(' ')
+        .filter(|s| !s.is_empty())
+        .map(|dim| dim.trim().parse().unwrap_or(0.0))
+        .collect();
 
-        if raw_line.is_empty() {
-            continue;
+    create_with(name, &dims).map(Some).ok_or_else(|| {
+        format!("{} takes {} dimension(s), found {}",
+                name, KnownShape::PARAM_COUNTS[kind_of(name)], dims.len())
+    })
+}
+
+/// Position of the shape `name` in `KnownShape::KNOWN_NAMES`
+fn kind_of(name: &str) -> usize {
+    KnownShape::KNOWN_NAMES.iter()
+        .position(|known| *known == name)
+        .expect("canonical names are known")
+}
+
+/// Remove everything from the first `#` that is not inside quotes
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..i],
+            _ => {}
         }
+    }
 
-        let split_line: Vec<String> = raw_line.trim().split(';')
-            .map(|s| s.to_string())
-            .collect();
+    line
+}
 
-        // There is no line data (i.e., no ';')
-        if split_line.len() != 2 {
-            continue;
+/// Split a line into its (possibly quoted) name and its dimensions. Return
+/// `None` unless there is exactly one `;` outside the name.
+fn split_name(line: &str) -> Option<(&str, &str)> {
+    let (name, dims) = match line.strip_prefix('"') {
+        Some(quoted) => {
+            let (name, rest) = quoted.split_once('"')?;
+            (name, rest.trim_start().strip_prefix(';')?)
         }
+        None => line.split_once(';')?,
+    };
+
+    if dims.contains(';') {
+        return None;
+    }
+
+    Some((name, dims))
+}
 
-        let n = &split_line[0];
-        let dims_str = &split_line[1];
+/// The file named by an `include "file"` directive, or `None` if `line` is
+/// not a directive
+fn include_path(line: &str) -> Option<Result<&str, String>> {
+    let rest = strip_comment(line).trim().strip_prefix("include")?;
+
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    let path = rest.trim()
+        .strip_prefix('"')
+        .and_then(|r| r.strip_suffix('"'))
+        .filter(|path| !path.is_empty());
+
+    Some(path.ok_or("expected a quoted file name after 'include'".to_string()))
+}
+
+/// The version declared by a `#!shapes vN` header, or `None` if `line` is
+/// not a header
+pub(crate) fn header_version(line: &str) -> Option<Result<u32, String>> {
+    let rest = line.trim().strip_prefix("#!shapes")?;
+
+    let version = rest.trim()
+        .strip_prefix('v')
+        .and_then(|v| v.parse::<u32>().ok())
+        .filter(|&v| v > 0);
+
+    let result = match version {
+        Some(v) if v <= FORMAT_VERSION => Ok(v),
+        Some(v) => Err(format!("unsupported format version {} (newest is {})",
+                               v, FORMAT_VERSION)),
+        None => Err(format!("invalid header '{}'", line.trim())),
+    };
+
+    Some(result)
+}
 
-        // Mistake -> s.len() > 0 != s.is_empty() -> I forgot the leading '!'
-        let dims: Vec<f64> = dims_str.split(' ')
-            .filter(|s| !s.is_empty())
-            .map(|dim| {
-                match dim.trim().parse() {
-                    Ok(d) => d,
-                    Err(_e) => 0.0,
+/// Prefix `err` with `path:line_number` (or `line line_number` for input that
+/// is not a file)
+pub(crate) fn located_at(path: Option<&Path>, line_number: usize,
+                         err: impl fmt::Display) -> String {
+    match path {
+        Some(path) => format!("{}:{}: {}", path.display(), line_number, err),
+        None => format!("line {}: {}", line_number, err),
+    }
+}
+
+/// Error for an `include` directive in input that is not a file
+const INCLUDE_OUTSIDE_FILE: &str = "'include' can only be used in files";
+
+/// Read a shape file, replacing each `include "file"` directive with the
+/// lines of that file (see [`ShapeLines`]).
+///
+/// # Arguments
+///
+///  * `path` - file to read
+///
+pub fn expand_includes<P: AsRef<Path>>(path: P) -> Result<String, String> {
+    ShapeLines::open(path.as_ref())?.into_string()
+}
+
+/// Create shapes from a file, following its `include` directives. The file
+/// is read one line at a time.
+///
+/// # Arguments
+///
+///  * `path` - file to read
+///  * `locale` - language of the shape names
+///
+pub fn read_shapes_file<P>(path: P, locale: &Locale) -> Result<Vec<KnownShape>, String>
+    where P: AsRef<Path> {
+
+    ShapeReader::from_lines(ShapeLines::open(path.as_ref())?, locale.clone())
+        .try_collect()
+}
+
+/// The lines of a shape file, with each `include "file"` directive replaced
+/// by the lines of that file. Included files are opened only when their
+/// directive is reached; their paths are relative to the file that includes
+/// them. Compressed files are decompressed (see `compress`).
+///
+/// A line that can not be followed is returned as an error and reading goes
+/// on with the next line. That is an unsupported or malformed version
+/// header, a malformed directive, an included file that can not be opened or
+/// that (directly or indirectly) includes itself, or an `include` in input
+/// that is not a file. Iteration ends after a line that can not be read.
+pub struct ShapeLines<B> {
+    lines: Lines<B>,
+
+    /// Number of lines read so far
+    line_number: usize,
+
+    /// The file being read, if any
+    path: Option<PathBuf>,
+
+    /// Canonical paths of the files that include this one, then this one
+    ancestors: Vec<PathBuf>,
+
+    /// The file named by the current `include` directive
+    included: Option<Box<ShapeLines<Box<dyn BufRead>>>>,
+
+    /// Whether a line could not be read
+    failed: bool,
+}
+
+impl ShapeLines<Box<dyn BufRead>> {
+    /// Read the file `path`
+    pub fn open(path: &Path) -> Result<Self, String> {
+        ShapeLines::open_included(path, Vec::new())
+    }
+
+    fn open_included(path: &Path, mut ancestors: Vec<PathBuf>) -> Result<Self, String> {
+        let located = |err: io::Error| format!("{}: {}", path.display(), err);
+
+        let canonical = path.canonicalize().map_err(located)?;
+
+        if let Some(start) = ancestors.iter().position(|p| *p == canonical) {
+            let cycle = ancestors[start..].iter()
+                .chain([&canonical])
+                .map(|p| p.display())
+                .join(" -> ");
+
+            return Err(format!("include cycle: {}", cycle));
+        }
+
+        let ins = compress::open(path).map_err(located)?;
+
+        ancestors.push(canonical);
+
+        Ok(ShapeLines {
+            path: Some(path.to_path_buf()),
+            ancestors,
+            ..ShapeLines::new(ins)
+        })
+    }
+}
+
+impl<B: BufRead> ShapeLines<B> {
+    /// Read lines that are not from a file, so `include` directives are
+    /// errors
+    pub fn new(ins: B) -> Self {
+        ShapeLines {
+            lines: ins.lines(),
+            line_number: 0,
+            path: None,
+            ancestors: Vec::new(),
+            included: None,
+            failed: false,
+        }
+    }
+
+    /// Treat the lines as the contents of the file `path`, which may
+    /// include other files
+    pub fn path(mut self, path: &Path) -> Self {
+        self.ancestors = vec![path.canonicalize().unwrap_or_else(|_| path.to_path_buf())];
+        self.path = Some(path.to_path_buf());
+        self
+    }
+
+    /// Collect every line into one string, stopping at the first error
+    pub fn into_string(self) -> Result<String, String> {
+        let mut text = String::new();
+
+        for line in self {
+            text.push_str(&line?);
+            text.push('\n');
+        }
+
+        Ok(text)
+    }
+
+    /// The file (if any) and number of the line read last, which may be in
+    /// an included file
+    pub fn location(&self) -> (Option<&Path>, usize) {
+        match &self.included {
+            Some(included) => included.location(),
+            None => (self.path.as_deref(), self.line_number),
+        }
+    }
+
+    /// Prefix `err` with the current file and line
+    fn located(&self, err: impl fmt::Display) -> String {
+        let (path, line_number) = self.location();
+
+        located_at(path, line_number, err)
+    }
+
+    fn next_line(&mut self) -> Option<Result<String, String>> {
+        loop {
+            if let Some(included) = self.included.as_mut() {
+                match included.next() {
+                    Some(line) => return Some(line),
+                    None => self.included = None,
+                }
+            }
+
+            let line = self.lines.next()?;
+            self.line_number += 1;
+
+            let line = match line {
+                Ok(line) => line,
+                Err(err) => {
+                    self.failed = true;
+                    return Some(Err(self.located(err)));
+                }
+            };
+
+            if self.line_number == 1 {
+                if let Some(Err(err)) = header_version(&line) {
+                    return Some(Err(self.located(err)));
                 }
-            }).collect();
+            }
 
-        if let Some(s) = create_with(n, &dims) {
-            shapes.push(s);
+            let file = match include_path(&line) {
+                None => return Some(Ok(line)),
+                Some(Ok(file)) => file,
+                Some(Err(err)) => return Some(Err(self.located(err))),
+            };
+
+            let dir = match &self.path {
+                Some(path) => path.parent().unwrap_or_else(|| Path::new("")),
+                None => return Some(Err(self.located(INCLUDE_OUTSIDE_FILE))),
+            };
+
+            match ShapeLines::open_included(&dir.join(file), self.ancestors.clone()) {
+                Ok(included) => self.included = Some(Box::new(included)),
+                Err(err) => return Some(Err(err)),
+            }
         }
     }
+}
 
-    shapes
+impl<B: BufRead> Iterator for ShapeLines<B> {
+    type Item = Result<String, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        self.next_line()
+    }
+}
+
+/// Lazily create shapes, one line at a time, from an input buffer.
+///
+/// This is the streaming counterpart of `read_shapes_with`--as an
+/// `Iterator` it yields the same shapes in the same order without holding
+/// all of them in memory. Use [`ShapeReader::try_next`] to also see the
+/// lines that can not be followed.
+pub struct ShapeReader<B> {
+    lines: ShapeLines<B>,
+    locale: Locale,
+    strict: bool,
+}
+
+impl<B: BufRead> ShapeReader<B> {
+    /// Read English shape names
+    pub fn new(ins: B) -> Self {
+        ShapeReader::with_locale(ins, Locale::english())
+    }
+
+    /// Read shape names in the language of `locale`
+    pub fn with_locale(ins: B, locale: Locale) -> Self {
+        ShapeReader::from_lines(ShapeLines::new(ins), locale)
+    }
+
+    /// Read shapes from `lines`, e.g., a file opened with
+    /// [`ShapeLines::open`]
+    pub fn from_lines(lines: ShapeLines<B>, locale: Locale) -> Self {
+        ShapeReader { lines, locale, strict: false }
+    }
+
+    /// Treat the input as the contents of the file `path`, which may include
+    /// other files
+    pub fn path(mut self, path: &Path) -> Self {
+        self.lines = self.lines.path(path);
+        self
+    }
+
+    /// Have `try_next` also return an error for each line that is not a
+    /// valid shape (e.g., an unknown name or the wrong number of dimensions)
+    /// rather than skip it
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Create the next shape, skipping lines that are not valid shapes, or
+    /// return the error for the next line that can not be followed (see
+    /// [`ShapeLines`]). Return `None` at the end of the input.
+    pub fn try_next(&mut self) -> Option<Result<KnownShape, String>> {
+        while let Some(line) = self.lines.next() {
+            let line = match line {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err)),
+            };
+
+            match parse_shape(&line, &self.locale) {
+                Ok(Some(shape)) => return Some(Ok(shape)),
+                Ok(None) => {}
+                Err(err) if self.strict => return Some(Err(self.lines.located(err))),
+                Err(_) => {}
+            }
+        }
+
+        None
+    }
+
+    /// Create every remaining shape, stopping at the first error
+    pub fn try_collect(mut self) -> Result<Vec<KnownShape>, String> {
+        std::iter::from_fn(|| self.try_next()).collect()
+    }
+}
+
+impl<B: BufRead> Iterator for ShapeReader<B> {
+    type Item = KnownShape;
+
+    fn next(&mut self) -> Option<KnownShape> {
+        std::iter::from_fn(|| self.try_next()).find_map(Result::ok)
+    }
 }
@@ -0,0 +1,388 @@
+use std::fmt;
+
+use crate::shape::Shape;
+use crate::locale::Locale;
+
+/// How numeric values are written
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Notation {
+    /// A fixed number of digits after the decimal point
+    Fixed(usize),
+
+    /// A fixed number of significant figures
+    Significant(usize),
+
+    /// Scientific notation with a fixed number of digits after the decimal
+    /// point (e.g., `3.1416e1`)
+    Scientific(usize),
+}
+
+/// Placement of a value within its column
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Alignment {
+    Left,
+    Right,
+    Center,
+}
+
+/// Overall layout of a formatted shape
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Style {
+    /// One `Label: value` line per field (the `Display` output)
+    Table,
+
+    /// A single line, e.g., `Circle(Radius: 5.0000, ..., Area: 78.5398)`
+    Compact,
+
+    /// A single line of `key=value` pairs, e.g.,
+    /// `name="Circle" radius=5.0000 ... area=78.5398`. This style is meant
+    /// for machines and is never localized.
+    KeyValue,
+}
+
+/// Configurable formatting for shapes.
+///
+/// The default formatter reproduces each shape's `Display` output: a table
+/// with 12 character labels, 24 character right-aligned values and four
+/// decimal places.
+///
+/// # Example
+///
+/// ```
+/// use shapes::circle::Circle;
+/// use shapes::formatter::{ShapeFormatter, Style};
+///
+/// let circle = Circle::with_radius(5.0);
+/// let line = ShapeFormatter::new()
+///     .style(Style::Compact)
+///     .precision(2)
+///     .format(&circle);
+///
+/// assert_eq!(line,
+///            "Circle(Radius: 5.00, Diameter: 10.00, Perimeter: 31.42, Area: 78.54)");
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShapeFormatter {
+    style: Style,
+    label_width: usize,
+    value_width: usize,
+    notation: Notation,
+    alignment: Alignment,
+    locale: Option<Locale>,
+}
+
+impl ShapeFormatter {
+    /// Create a formatter that matches the `Display` output
+    pub fn new() -> Self {
+        ShapeFormatter {
+            style: Style::Table,
+            label_width: 12,
+            value_width: 24,
+            notation: Notation::Fixed(4),
+            alignment: Alignment::Right,
+            locale: None,
+        }
+    }
+
+    /// Set the layout
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Set the minimum width of the label column (table style only)
+    pub fn label_width(mut self, width: usize) -> Self {
+        self.label_width = width;
+        self
+    }
+
+    /// Set the minimum width of the value column (table style only)
+    pub fn value_width(mut self, width: usize) -> Self {
+        self.value_width = width;
+        self
+    }
+
+    /// Use fixed notation with `digits` after the decimal point
+    pub fn precision(self, digits: usize) -> Self {
+        self.notation(Notation::Fixed(digits))
+    }
+
+    /// Set the numeric notation
+    pub fn notation(mut self, notation: Notation) -> Self {
+        self.notation = notation;
+        self
+    }
+
+    /// Set the alignment of values within the value column
+    pub fn alignment(mut self, alignment: Alignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+
+    /// Translate names and labels, and use the locale's number separators
+    pub fn locale(mut self, locale: Locale) -> Self {
+        self.locale = Some(locale);
+        self
+    }
+
+    /// Format a shape as a `String`
+    pub fn format<S: Shape + ?Sized>(&self, shape: &S) -> String {
+        let mut out = String::new();
+
+        // Writing to a String can not fail
+        self.write(&mut out, shape).unwrap();
+
+        out
+    }
+
+    /// Format a shape into any `fmt::Write` sink (e.g., a `fmt::Formatter`)
+    pub fn write<W, S>(&self, out: &mut W, shape: &S) -> fmt::Result
+        where W: fmt::Write + ?Sized, S: Shape + ?Sized {
+
+        let mut fields = shape.fields();
+        fields.push(("Perimeter", shape.perimeter()));
+        fields.push(("Area", shape.area()));
+
+        match self.style {
+            Style::Table => {
+                self.write_row(out, self.translate("Name"),
+                               self.translate(shape.name()))?;
+
+                for (label, value) in fields {
+                    self.write_row(out, self.translate(label),
+                                   &self.localized_value(value))?;
+                }
+
+                Ok(())
+            }
+            Style::Compact => {
+                let values = fields.iter()
+                    .map(|(label, value)| {
+                        format!("{}: {}", self.translate(label),
+                                self.localized_value(*value))
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                write!(out, "{}({})", self.translate(shape.name()), values)
+            }
+            Style::KeyValue => {
+                write!(out, "name={:?}", shape.name())?;
+
+                for (label, value) in fields {
+                    write!(out, " {}={}", to_key(label),
+                           self.format_value(value))?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Format a single number using the configured notation
+    pub fn format_value(&self, value: f64) -> String {
+        match self.notation {
+            Notation::Fixed(digits) => format!("{:.*}", digits, value),
+            Notation::Scientific(digits) => format!("{:.*e}", digits, value),
+            Notation::Significant(figures) => {
+                format_significant(value, figures)
+            }
+        }
+    }
+
+    fn localized_value(&self, value: f64) -> String {
+        let formatted = self.format_value(value);
+
+        match &self.locale {
+            Some(locale) => locale.localize_number(&formatted),
+            None => formatted,
+        }
+    }
+
+    fn translate<'a>(&'a self, key: &'a str) -> &'a str {
+        match &self.locale {
+            Some(locale) => locale.translate(key),
+            None => key,
+        }
+    }
+
+    fn write_row<W>(&self, out: &mut W, label: &str, value: &str)
+        -> fmt::Result where W: fmt::Write + ?Sized {
+
+        let width = self.value_width;
+
+        match self.alignment {
+            Alignment::Left => {
+                writeln!(out, "{:<lw$}:{:<w$}", label, value,
+                         lw = self.label_width, w = width)
+            }
+            Alignment::Right => {
+                writeln!(out, "{:<lw$}:{:>w$}", label, value,
+                         lw = self.label_width, w = width)
+            }
+            Alignment::Center => {
+                writeln!(out, "{:<lw$}:{:^w$}", label, value,
+                         lw = self.label_width, w = width)
+            }
+        }
+    }
+}
+
+impl Default for ShapeFormatter {
+    fn default() -> Self {
+        ShapeFormatter::new()
+    }
+}
+
+/// Turn a label (e.g., `Side A`) into a key (e.g., `side_a`)
+pub(crate) fn to_key(label: &str) -> String {
+    label.to_lowercase().replace(' ', "_")
+}
+
+/// Round to a number of significant figures and write the result in fixed
+/// notation.
+fn format_significant(value: f64, figures: usize) -> String {
+    if value == 0.0 || !value.is_finite() {
+        return format!("{}", value);
+    }
+
+    let figures = figures.max(1) as i32;
+    let magnitude = value.abs().log10().floor() as i32;
+    let rounded = round_to_decimals(value, figures - 1 - magnitude);
+
+    // Rounding may carry into the next power of ten (e.g., 9.996 -> 10.00),
+    // which then needs one decimal fewer
+    match rounded.parse::<f64>() {
+        Ok(r) if r.abs() >= 10_f64.powi(magnitude + 1) => {
+            round_to_decimals(value, figures - 2 - magnitude)
+        }
+        _ => rounded,
+    }
+}
+
+/// Round to `decimals` places (to tens, hundreds, ... if negative) and write
+/// the result in fixed notation.
+fn round_to_decimals(value: f64, decimals: i32) -> String {
+    if decimals >= 0 {
+        format!("{:.*}", decimals as usize, value)
+    }
+    else {
+        let scale = 10_f64.powi(-decimals);
+        format!("{}", (value / scale).round() * scale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hamcrest2::prelude::*;
+
+    use crate::circle::Circle;
+    use crate::right_triangle::RightTriangle;
+    use crate::square::Square;
+
+    #[test]
+    fn test_default_matches_display() {
+        let fancy = RightTriangle::with_base_height(3.0, 4.0);
+
+        assert_that!(ShapeFormatter::default().format(&fancy),
+                     equal_to(fancy.to_string()));
+    }
+
+    #[test]
+    fn test_table_layout() {
+        let fancy = Square::with_side(2.0);
+        let fmt_str = ShapeFormatter::new()
+            .label_width(6)
+            .value_width(8)
+            .precision(1)
+            .alignment(Alignment::Left)
+            .format(&fancy);
+
+        assert_that!(fmt_str, equal_to(concat!("Name  :Square  \n",
+                                               "Side  :2.0     \n",
+                                               "Perimeter:8.0     \n",
+                                               "Area  :4.0     \n")));
+    }
+
+    #[test]
+    fn test_compact() {
+        let fancy = Square::with_side(2.0);
+        let fmt_str = ShapeFormatter::new()
+            .style(Style::Compact)
+            .precision(0)
+            .format(&fancy);
+
+        assert_that!(fmt_str,
+                     equal_to("Square(Side: 2, Perimeter: 8, Area: 4)"));
+    }
+
+    #[test]
+    fn test_key_value() {
+        let fancy = RightTriangle::with_base_height(3.0, 4.0);
+        let fmt_str = ShapeFormatter::new()
+            .style(Style::KeyValue)
+            .precision(1)
+            .format(&fancy);
+
+        assert_that!(fmt_str,
+                     equal_to(concat!("name=\"Right Triangle\" base=3.0 ",
+                                      "height=4.0 hypotenuse=5.0 ",
+                                      "perimeter=12.0 area=6.0")));
+    }
+
+    #[test]
+    fn test_notation() {
+        let sci = ShapeFormatter::new().notation(Notation::Scientific(2));
+        assert_that!(sci.format_value(1234.5), equal_to("1.23e3"));
+
+        let sig = ShapeFormatter::new().notation(Notation::Significant(3));
+        assert_that!(sig.format_value(1.23456), equal_to("1.23"));
+        assert_that!(sig.format_value(0.0012345), equal_to("0.00123"));
+        assert_that!(sig.format_value(123456.0), equal_to("123000"));
+        assert_that!(sig.format_value(0.0), equal_to("0"));
+
+        // Rounding up to the next power of ten keeps the number of figures
+        assert_that!(sig.format_value(9.996), equal_to("10.0"));
+        assert_that!(sig.format_value(-0.09996), equal_to("-0.100"));
+        assert_that!(sig.format_value(999.6), equal_to("1000"));
+        assert_that!(sig.format_value(99960.0), equal_to("100000"));
+
+        let circle = Circle::with_radius(1.0);
+        let fmt_str = ShapeFormatter::new()
+            .style(Style::Compact)
+            .notation(Notation::Significant(2))
+            .format(&circle);
+        assert!(fmt_str.contains("Area: 3.1"));
+    }
+
+    #[test]
+    fn test_locale() {
+        let fancy = Circle::with_radius(1000.0);
+        let german = Locale::by_code("de").unwrap();
+
+        let fmt_str = ShapeFormatter::new()
+            .locale(german.clone())
+            .format(&fancy);
+        assert!(fmt_str.starts_with(&format!("{:12}:{:>24}\n",
+                                             "Name", "Kreis")));
+        assert!(fmt_str.contains(&format!("{:12}:{:>24}\n",
+                                          "Radius", "1.000,0000")));
+
+        let fmt_str = ShapeFormatter::new()
+            .style(Style::Compact)
+            .precision(0)
+            .locale(german.clone())
+            .format(&Square::with_side(2.0));
+        assert_that!(fmt_str,
+                     equal_to("Quadrat(Seite: 2, Umfang: 8, Fläche: 4)"));
+
+        let fmt_str = ShapeFormatter::new()
+            .style(Style::KeyValue)
+            .precision(0)
+            .locale(german)
+            .format(&Square::with_side(2.0));
+        assert_that!(fmt_str,
+                     equal_to("name=\"Square\" side=2 perimeter=8 area=4"));
+    }
+}
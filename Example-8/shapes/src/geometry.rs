@@ -0,0 +1,298 @@
+//! Shapes as polygons with canonical coordinates.
+//!
+//! Every polygon is listed counter-clockwise, without repeating the first
+//! vertex:
+//!
+//!   * Square - lower-left corner at the origin
+//!   * Right Triangle - right angle at the origin, base along the x-axis
+//!   * Triangle and Equilateral Triangle - side A along the x-axis from the
+//!     origin, followed by sides B and C
+//!   * Circle - a regular polygon inscribed in the circle, centered at the
+//!     origin
+//!
+//! [`recognize`] reverses the process, turning a polygon back into a shape.
+
+use std::f64::consts::PI;
+
+use crate::circle::Circle;
+use crate::equilateral_triangle::EquilateralTriangle;
+use crate::known_shape::KnownShape;
+use crate::right_triangle::RightTriangle;
+use crate::square::Square;
+use crate::triangle::Triangle;
+
+/// An `(x, y)` coordinate
+pub type Point = (f64, f64);
+
+/// Fewest segments used to approximate a circle (so that circles are never
+/// mistaken for triangles or squares)
+pub const MIN_CIRCLE_SEGMENTS: usize = 8;
+
+/// Relative tolerance used when recognizing polygons
+const TOLERANCE: f64 = 1e-6;
+
+/// Number of segments needed for a circle of `radius` so that no point on a
+/// chord is more than `max_error` from the circle
+pub fn circle_segments(radius: f64, max_error: f64) -> usize {
+    if !(radius > 0.0 && max_error > 0.0) {
+        return MIN_CIRCLE_SEGMENTS;
+    }
+
+    // The error of one chord (its sagitta) is r (1 - cos(pi / n))
+    let angle = (1.0 - max_error / radius).max(-1.0).acos();
+    let segments = (PI / angle).ceil();
+
+    (segments as usize).max(MIN_CIRCLE_SEGMENTS)
+}
+
+/// The vertices of `shape`, or `None` if its sides can not form a triangle.
+///
+/// # Arguments
+///
+///  * `shape` - shape to convert
+///  * `max_error` - largest distance between a circle and its approximation
+///
+pub fn polygon(shape: &KnownShape, max_error: f64) -> Option<Vec<Point>> {
+    let points = match shape {
+        KnownShape::Square(s) => {
+            vec![(0.0, 0.0), (s.side, 0.0), (s.side, s.side), (0.0, s.side)]
+        }
+        KnownShape::RightTriangle(t) => {
+            vec![(0.0, 0.0), (t.base, 0.0), (0.0, t.height)]
+        }
+        KnownShape::EquilateralTriangle(t) => {
+            triangle(t.side, t.side, t.side)?
+        }
+        KnownShape::Triangle(t) => triangle(t.side_a, t.side_b, t.side_c)?,
+        KnownShape::Circle(c) => {
+            let n = circle_segments(c.radius, max_error);
+
+            (0..n)
+                .map(|k| 2.0 * PI * k as f64 / n as f64)
+                .map(|theta| (c.radius * theta.cos(), c.radius * theta.sin()))
+                .collect()
+        }
+    };
+
+    Some(points)
+}
+
+/// Place side `a` from the origin along the x-axis, then `b` and `c`
+fn triangle(a: f64, b: f64, c: f64) -> Option<Vec<Point>> {
+    let x = (a * a + c * c - b * b) / (2.0 * a);
+    let y = (c * c - x * x).sqrt();
+
+    if !(x.is_finite() && y.is_finite()) {
+        return None;
+    }
+
+    Some(vec![(0.0, 0.0), (a, 0.0), (x, y)])
+}
+
+fn distance(p: Point, q: Point) -> f64 {
+    (p.0 - q.0).hypot(p.1 - q.1)
+}
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() <= TOLERANCE * a.abs().max(b.abs()).max(1.0)
+}
+
+/// The lower-left and upper-right corners of the box around `points`
+pub fn bounds(points: &[Point]) -> (Point, Point) {
+    points.iter().fold(
+        ((f64::INFINITY, f64::INFINITY), (f64::NEG_INFINITY, f64::NEG_INFINITY)),
+        |(min, max), p| ((min.0.min(p.0), min.1.min(p.1)), (max.0.max(p.0), max.1.max(p.1))))
+}
+
+/// Turn a polygon back into a shape. A closing vertex equal to the first is
+/// ignored.
+///
+///   * 3 vertices - Equilateral Triangle, Right Triangle or Triangle
+///   * 4 vertices - Square (equal sides and equal diagonals)
+///   * 8 or more vertices - Circle (all vertices equally far from the
+///     centroid and equally spaced)
+///
+pub fn recognize(points: &[Point]) -> Result<KnownShape, String> {
+    let mut points = points.to_vec();
+
+    if points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+
+    let n = points.len();
+    let sides: Vec<f64> = (0..n)
+        .map(|i| distance(points[i], points[(i + 1) % n]))
+        .collect();
+
+    match n {
+        3 => Ok(recognize_triangle(&sides)),
+        4 => {
+            let diagonals = (distance(points[0], points[2]),
+                             distance(points[1], points[3]));
+
+            if sides.iter().all(|&s| close(s, sides[0])) && close(diagonals.0, diagonals.1) {
+                Ok(Square::with_side(sides[0]).into())
+            }
+            else {
+                Err("quadrilateral is not a square".to_string())
+            }
+        }
+        n if n >= MIN_CIRCLE_SEGMENTS => {
+            let center = points.iter()
+                .fold((0.0, 0.0), |acc, p| (acc.0 + p.0, acc.1 + p.1));
+            let center = (center.0 / n as f64, center.1 / n as f64);
+            let radius = distance(center, points[0]);
+
+            let regular = points.iter().all(|&p| close(distance(center, p), radius))
+                && sides.iter().all(|&s| close(s, sides[0]));
+
+            if regular {
+                Ok(Circle::with_radius(radius).into())
+            }
+            else {
+                Err(format!("{}-sided polygon is not a circle", n))
+            }
+        }
+        n => Err(format!("{}-sided polygon is not a known shape", n)),
+    }
+}
+
+fn recognize_triangle(sides: &[f64]) -> KnownShape {
+    let (a, b, c) = (sides[0], sides[1], sides[2]);
+
+    if close(a, b) && close(b, c) {
+        return EquilateralTriangle::with_side(a).into();
+    }
+
+    // A right angle at vertex k lies between side k (base) and the side
+    // before it (height)
+    for k in 0..3 {
+        let base = sides[k];
+        let height = sides[(k + 2) % 3];
+        let hypotenuse = sides[(k + 1) % 3];
+
+        if close(base * base + height * height, hypotenuse * hypotenuse) {
+            return RightTriangle::with_base_height(base, height).into();
+        }
+    }
+
+    Triangle::with_sides(a, b, c).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hamcrest2::prelude::*;
+
+    use crate::shape::Shape;
+
+    /// Shoelace formula (positive for counter-clockwise polygons)
+    fn signed_area(points: &[Point]) -> f64 {
+        let n = points.len();
+
+        (0..n).map(|i| {
+            let (p, q) = (points[i], points[(i + 1) % n]);
+            p.0 * q.1 - q.0 * p.1
+        })
+        .sum::<f64>() / 2.0
+    }
+
+    #[test]
+    fn test_polygons() {
+        let shapes: Vec<KnownShape> = vec![
+            Square::with_side(3.0).into(),
+            RightTriangle::with_base_height(3.0, 4.0).into(),
+            EquilateralTriangle::with_side(2.0).into(),
+            Triangle::with_sides(4.0, 5.0, 6.0).into(),
+        ];
+
+        for shape in shapes.iter() {
+            let points = polygon(shape, 0.01).unwrap();
+            assert_that!(signed_area(&points), close_to(shape.area(), 1e-9));
+        }
+
+        let points = polygon(&shapes[3], 0.01).unwrap();
+        assert_that!(distance(points[1], points[2]), close_to(5.0, 1e-9));
+        assert_that!(distance(points[2], points[0]), close_to(6.0, 1e-9));
+
+        assert!(polygon(&Triangle::with_sides(1.0, 1.0, 5.0).into(), 0.01).is_none());
+    }
+
+    #[test]
+    fn test_bounds() {
+        let points = polygon(&Triangle::with_sides(4.0, 5.0, 6.0).into(), 0.01).unwrap();
+        let (min, max) = bounds(&points);
+
+        assert_that!(min, equal_to((0.0, 0.0)));
+        assert_that!(max.0, close_to(4.0, 1e-9));
+        assert_that!(max.1, close_to(points[2].1, 1e-9));
+
+        assert_that!(bounds(&[(1.0, -2.0)]), equal_to(((1.0, -2.0), (1.0, -2.0))));
+    }
+
+    #[test]
+    fn test_circle_segments() {
+        assert_that!(circle_segments(1.0, 10.0), is(equal_to(MIN_CIRCLE_SEGMENTS)));
+        assert_that!(circle_segments(1.0, 0.0), is(equal_to(MIN_CIRCLE_SEGMENTS)));
+
+        for (radius, error) in [(1.0, 0.01), (100.0, 0.01), (5.0, 1e-4)] {
+            let n = circle_segments(radius, error);
+            let sagitta = radius * (1.0 - (PI / n as f64).cos());
+            let coarser = radius * (1.0 - (PI / (n - 1) as f64).cos());
+
+            assert!(sagitta <= error);
+            assert!(coarser > error);
+        }
+
+        let points = polygon(&Circle::with_radius(2.0).into(), 0.001).unwrap();
+        assert_that!(points.len(), is(equal_to(circle_segments(2.0, 0.001))));
+        assert!(points.iter().all(|&p| close(distance((0.0, 0.0), p), 2.0)));
+    }
+
+    #[test]
+    fn test_recognize() {
+        let shapes: Vec<KnownShape> = vec![
+            Square::with_side(3.0).into(),
+            RightTriangle::with_base_height(3.0, 4.0).into(),
+            EquilateralTriangle::with_side(2.0).into(),
+            Triangle::with_sides(4.0, 5.0, 6.0).into(),
+            Circle::with_radius(2.5).into(),
+        ];
+
+        for shape in shapes.iter() {
+            let recognized = recognize(&polygon(shape, 0.01).unwrap()).unwrap();
+
+            assert_that!(recognized.name(), equal_to(shape.name()));
+            for (p, q) in recognized.params().iter().zip(shape.params()) {
+                assert_that!(*p, close_to(q, 1e-9));
+            }
+        }
+    }
+
+    #[test]
+    fn test_recognize_rotated() {
+        // A right triangle with the right angle at the second vertex, and a
+        // closing vertex
+        let points = [(0.0, 0.0), (4.0, 0.0), (4.0, 3.0), (0.0, 0.0)];
+        let shape = recognize(&points).unwrap();
+
+        assert_that!(shape.name(), equal_to("Right Triangle"));
+        assert_that!(shape.area(), close_to(6.0, 1e-9));
+
+        let diamond = [(0.0, -1.0), (1.0, 0.0), (0.0, 1.0), (-1.0, 0.0)];
+        assert_that!(recognize(&diamond).unwrap().area(), close_to(2.0, 1e-9));
+    }
+
+    #[test]
+    fn test_recognize_errors() {
+        let rectangle = [(0.0, 0.0), (2.0, 0.0), (2.0, 1.0), (0.0, 1.0)];
+        let rhombus = [(0.0, 0.0), (2.0, 0.0), (3.0, 3_f64.sqrt()), (1.0, 3_f64.sqrt())];
+        let pentagon = [(0.0, 0.0), (1.0, 0.0), (1.5, 1.0), (0.5, 2.0), (-0.5, 1.0)];
+
+        assert!(recognize(&rectangle).is_err());
+        assert!(recognize(&rhombus).is_err());
+        assert_that!(recognize(&pentagon).unwrap_err(),
+                     equal_to("5-sided polygon is not a known shape".to_string()));
+        assert!(recognize(&[(0.0, 0.0)]).is_err());
+    }
+}
@@ -0,0 +1,284 @@
+//! WKT and GeoJSON export and import.
+//!
+//! Shapes are written as polygons using the canonical coordinates from
+//! [`geometry::polygon`]. Only single-ring polygons (no holes) are read back;
+//! they are recognized with [`geometry::recognize`].
+
+use serde_json::{json, Value};
+
+use crate::geometry::{self, Point};
+use crate::known_shape::KnownShape;
+use crate::shape::Shape;
+
+/// Close a ring by repeating its first vertex
+fn closed(mut points: Vec<Point>) -> Vec<Point> {
+    if let Some(&first) = points.first() {
+        points.push(first);
+    }
+
+    points
+}
+
+/// Describe `shape` as a WKT polygon, e.g.,
+/// `POLYGON ((0 0, 2 0, 2 2, 0 2, 0 0))`. Return `None` if the shape can not
+/// be drawn (e.g., a triangle with impossible sides).
+///
+/// # Arguments
+///
+///  * `shape` - shape to convert
+///  * `max_error` - largest distance between a circle and its approximation
+///
+pub fn to_wkt(shape: &KnownShape, max_error: f64) -> Option<String> {
+    let ring: Vec<String> = closed(geometry::polygon(shape, max_error)?)
+        .iter()
+        .map(|(x, y)| format!("{} {}", x, y))
+        .collect();
+
+    Some(format!("POLYGON (({}))", ring.join(", ")))
+}
+
+/// Read a shape from a WKT polygon (keywords are case-insensitive)
+pub fn from_wkt(text: &str) -> Result<KnownShape, String> {
+    let text = text.trim();
+
+    let (keyword, rest) = text.split_at(text.find('(').unwrap_or(text.len()));
+    if !keyword.trim().eq_ignore_ascii_case("POLYGON") {
+        return Err(format!("expected a POLYGON, found '{}'", keyword.trim()));
+    }
+
+    let rings = rest.trim()
+        .strip_prefix('(')
+        .and_then(|r| r.strip_suffix(')'))
+        .ok_or("expected '(' around the rings")?
+        .trim();
+
+    let ring = rings.strip_prefix('(')
+        .and_then(|r| r.strip_suffix(')'))
+        .ok_or("expected '(' around the vertices")?;
+
+    if ring.contains(['(', ')']) {
+        return Err("polygons with holes are not supported".to_string());
+    }
+
+    let points = ring.split(',')
+        .map(|vertex| {
+            let coords = vertex.split_whitespace()
+                .map(|c| c.parse::<f64>())
+                .collect::<Result<Vec<f64>, _>>()
+                .map_err(|_| format!("invalid vertex '{}'", vertex.trim()))?;
+
+            match coords[..] {
+                [x, y] => Ok((x, y)),
+                _ => Err(format!("expected 2 coordinates in '{}'", vertex.trim())),
+            }
+        })
+        .collect::<Result<Vec<Point>, String>>()?;
+
+    geometry::recognize(&points)
+}
+
+/// Describe `shape` as a GeoJSON Polygon geometry. Return `None` if the
+/// shape can not be drawn.
+pub fn to_geojson(shape: &KnownShape, max_error: f64) -> Option<Value> {
+    let ring: Vec<[f64; 2]> = closed(geometry::polygon(shape, max_error)?)
+        .into_iter()
+        .map(|(x, y)| [x, y])
+        .collect();
+
+    Some(json!({
+        "type": "Polygon",
+        "coordinates": [ring],
+    }))
+}
+
+/// Describe `shape` as a GeoJSON Feature with `name`, `area` and `perimeter`
+/// properties. Shapes that can not be drawn have a `null` geometry.
+pub fn feature(shape: &KnownShape, max_error: f64) -> Value {
+    json!({
+        "type": "Feature",
+        "geometry": to_geojson(shape, max_error),
+        "properties": {
+            "name": shape.name(),
+            "area": shape.area(),
+            "perimeter": shape.perimeter(),
+        },
+    })
+}
+
+/// Describe every shape as one GeoJSON FeatureCollection
+///
+/// # Example
+///
+/// ```
+/// use shapes::gis;
+/// use shapes::square::Square;
+///
+/// let shapes = vec![Square::with_side(2.0).into()];
+/// let collection = gis::feature_collection(&shapes, 0.01);
+///
+/// assert_eq!(collection["features"][0]["properties"]["area"], 4.0);
+/// ```
+pub fn feature_collection<'a, I>(shapes: I, max_error: f64) -> Value
+    where I: IntoIterator<Item = &'a KnownShape> {
+
+    let features: Vec<Value> = shapes.into_iter()
+        .map(|shape| feature(shape, max_error))
+        .collect();
+
+    json!({
+        "type": "FeatureCollection",
+        "features": features,
+    })
+}
+
+/// Read shapes from a GeoJSON Polygon, Feature or FeatureCollection.
+///
+/// Return an error if `text` is not GeoJSON; otherwise, return one result
+/// per geometry (properties are ignored--each shape is recognized from its
+/// coordinates).
+pub fn from_geojson(text: &str) -> Result<Vec<Result<KnownShape, String>>, String> {
+    let value: Value = serde_json::from_str(text).map_err(|err| err.to_string())?;
+
+    match value["type"].as_str() {
+        Some("FeatureCollection") => {
+            let features = value["features"].as_array()
+                .ok_or("FeatureCollection has no features")?;
+
+            Ok(features.iter().map(|f| read_geometry(&f["geometry"])).collect())
+        }
+        Some("Feature") => Ok(vec![read_geometry(&value["geometry"])]),
+        Some(_) => Ok(vec![read_geometry(&value)]),
+        None => Err("not a GeoJSON object".to_string()),
+    }
+}
+
+fn read_geometry(geometry: &Value) -> Result<KnownShape, String> {
+    match geometry["type"].as_str() {
+        Some("Polygon") => {}
+        Some(other) => return Err(format!("expected a Polygon, found {}", other)),
+        None => return Err("missing geometry".to_string()),
+    }
+
+    let rings = geometry["coordinates"].as_array()
+        .ok_or("Polygon has no coordinates")?;
+
+    let ring = match &rings[..] {
+        [ring] => ring.as_array().ok_or("ring is not an array")?,
+        [] => return Err("Polygon has no rings".to_string()),
+        _ => return Err("polygons with holes are not supported".to_string()),
+    };
+
+    let points = ring.iter()
+        .map(|position| {
+            let x = position[0].as_f64();
+            let y = position[1].as_f64();
+
+            x.zip(y).ok_or(format!("invalid position {}", position))
+        })
+        .collect::<Result<Vec<Point>, String>>()?;
+
+    geometry::recognize(&points)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hamcrest2::prelude::*;
+
+    use crate::right_triangle::RightTriangle;
+    use crate::square::Square;
+    use crate::testing::some_shapes;
+    use crate::triangle::Triangle;
+
+    fn assert_same(actual: &KnownShape, expected: &KnownShape) {
+        assert_that!(actual.name(), equal_to(expected.name()));
+        for (a, e) in actual.params().iter().zip(expected.params()) {
+            assert_that!(*a, close_to(e, 1e-9));
+        }
+    }
+
+    #[test]
+    fn test_to_wkt() {
+        assert_that!(to_wkt(&Square::with_side(2.0).into(), 0.01),
+                     equal_to(Some("POLYGON ((0 0, 2 0, 2 2, 0 2, 0 0))".to_string())));
+        assert_that!(to_wkt(&RightTriangle::with_base_height(3.0, 4.5).into(), 0.01),
+                     equal_to(Some("POLYGON ((0 0, 3 0, 0 4.5, 0 0))".to_string())));
+        assert!(to_wkt(&Triangle::with_sides(1.0, 1.0, 3.0).into(), 0.01).is_none());
+    }
+
+    #[test]
+    fn test_wkt_round_trip() {
+        for shape in some_shapes() {
+            let wkt = to_wkt(&shape, 0.001).unwrap();
+            assert_same(&from_wkt(&wkt).unwrap(), &shape);
+        }
+
+        let shape = from_wkt("polygon((0 0,3 0,3 3,0 3))").unwrap();
+        assert_that!(shape.area(), close_to(9.0, 1e-9));
+    }
+
+    #[test]
+    fn test_wkt_errors() {
+        assert_that!(from_wkt("POINT (1 2)").unwrap_err(),
+                     equal_to("expected a POLYGON, found 'POINT'".to_string()));
+        assert_that!(from_wkt("POLYGON ((0 0, 4 0, 4 4, 0 4), (1 1, 2 1, 2 2))").unwrap_err(),
+                     equal_to("polygons with holes are not supported".to_string()));
+        assert_that!(from_wkt("POLYGON ((0 0 1, 4 0 1, 0 4 1))").unwrap_err(),
+                     equal_to("expected 2 coordinates in '0 0 1'".to_string()));
+        assert!(from_wkt("POLYGON ((0 0, x 0, 0 4))").is_err());
+        assert!(from_wkt("POLYGON 0 0, 4 0, 0 4").is_err());
+    }
+
+    #[test]
+    fn test_feature_collection() {
+        let shapes = some_shapes();
+        let collection = feature_collection(&shapes, 0.01);
+        let features = collection["features"].as_array().unwrap();
+
+        assert_that!(features.len(), is(equal_to(7)));
+        assert_that!(features[2]["properties"]["name"].as_str(),
+                     equal_to(Some("Right Triangle")));
+        assert_that!(features[2]["properties"]["perimeter"].as_f64(),
+                     equal_to(Some(12.0)));
+        assert_that!(features[0]["geometry"]["coordinates"][0].as_array().unwrap().len(),
+                     is(equal_to(5)));
+
+        let impossible = feature(&Triangle::with_sides(1.0, 1.0, 3.0).into(), 0.01);
+        assert!(impossible["geometry"].is_null());
+    }
+
+    #[test]
+    fn test_geojson_round_trip() {
+        let shapes = some_shapes();
+        let text = feature_collection(&shapes, 0.001).to_string();
+        let read = from_geojson(&text).unwrap();
+
+        assert_that!(read.len(), is(equal_to(shapes.len())));
+        for (actual, expected) in read.iter().zip(shapes.iter()) {
+            assert_same(actual.as_ref().unwrap(), expected);
+        }
+
+        let geometry = to_geojson(&shapes[0], 0.01).unwrap().to_string();
+        assert_same(from_geojson(&geometry).unwrap()[0].as_ref().unwrap(), &shapes[0]);
+    }
+
+    #[test]
+    fn test_geojson_errors() {
+        assert!(from_geojson("not json").is_err());
+        assert!(from_geojson("[1, 2]").is_err());
+
+        let text = r#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "geometry": {"type": "Point", "coordinates": [1, 2]}},
+            {"type": "Feature", "geometry": null},
+            {"type": "Feature", "geometry": {"type": "Polygon", "coordinates": [
+                [[0, 0], [2, 0], [2, 2], [0, 2], [0, 0]]
+            ]}}
+        ]}"#;
+
+        let read = from_geojson(text).unwrap();
+        assert_that!(read[0].clone().unwrap_err(),
+                     equal_to("expected a Polygon, found Point".to_string()));
+        assert_that!(read[1].clone().unwrap_err(), equal_to("missing geometry".to_string()));
+        assert_that!(read[2].as_ref().unwrap().area(), close_to(4.0, 1e-9));
+    }
+}
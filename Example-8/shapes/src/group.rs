@@ -0,0 +1,288 @@
+//! Group shapes by a key and aggregate a metric within each group.
+//!
+//! A key is any function from a shape to an ordered value. The most common
+//! keys are provided here: the shape name ([`by_name`]) and fixed-width size
+//! buckets ([`by_size`]).
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use ordered_float::OrderedFloat;
+
+use crate::collection::Summary;
+use crate::known_shape::KnownShape;
+use crate::shape::Shape;
+
+/// Key shapes by name, e.g., "Square"
+pub fn by_name(shape: &KnownShape) -> &'static str {
+    shape.name()
+}
+
+/// Key shapes by which `width`-wide interval of `metric` they fall in, e.g.,
+/// `by_size(Shape::area, 10.0)` puts a shape with area 25 in `[20, 30)`.
+///
+/// # Panics
+///
+/// If `width` is not positive.
+pub fn by_size<F>(metric: F, width: f64) -> impl Fn(&KnownShape) -> SizeBucket
+    where F: Fn(&KnownShape) -> f64 {
+
+    assert!(width > 0.0, "bucket width must be positive");
+
+    move |shape| {
+        let index = (metric(shape) / width).floor();
+
+        SizeBucket {
+            lower: OrderedFloat(index * width),
+            upper: OrderedFloat((index + 1.0) * width),
+        }
+    }
+}
+
+/// A half-open interval `[lower, upper)` of sizes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SizeBucket {
+    pub lower: OrderedFloat<f64>,
+    pub upper: OrderedFloat<f64>,
+}
+
+impl fmt::Display for SizeBucket {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = format!("[{}, {})", self.lower, self.upper);
+
+        // Honor width and alignment so buckets line up in tables
+        f.pad(&text)
+    }
+}
+
+/// Shapes grouped by key, with groups ordered by key
+#[derive(Debug)]
+pub struct ShapeGroups<'a, K> {
+    groups: BTreeMap<K, Vec<&'a KnownShape>>,
+}
+
+impl<'a, K: Ord> ShapeGroups<'a, K> {
+    /// Group `shapes` by `key`. Shapes keep their original order within each
+    /// group.
+    pub fn new<I, F>(shapes: I, key: F) -> Self
+        where I: IntoIterator<Item = &'a KnownShape>,
+              F: Fn(&KnownShape) -> K {
+
+        let mut groups: BTreeMap<K, Vec<&'a KnownShape>> = BTreeMap::new();
+
+        for shape in shapes {
+            groups.entry(key(shape)).or_default().push(shape);
+        }
+
+        ShapeGroups { groups }
+    }
+
+    /// Number of groups
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// Check whether there are no groups
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Retrieve the shapes in one group
+    pub fn get(&self, key: &K) -> Option<&[&'a KnownShape]> {
+        self.groups.get(key).map(|shapes| shapes.as_slice())
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.groups.keys()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &[&'a KnownShape])> {
+        self.groups.iter().map(|(key, shapes)| (key, shapes.as_slice()))
+    }
+
+    /// Number of shapes in each group
+    pub fn count(&self) -> BTreeMap<&K, usize> {
+        self.groups.iter()
+            .map(|(key, shapes)| (key, shapes.len()))
+            .collect()
+    }
+
+    /// Sum `metric` within each group
+    pub fn sum<F>(&self, metric: F) -> BTreeMap<&K, f64>
+        where F: Fn(&KnownShape) -> f64 {
+
+        self.map_values(|shapes| shapes.iter().map(|s| metric(s)).sum())
+    }
+
+    pub fn mean<F>(&self, metric: F) -> BTreeMap<&K, f64>
+        where F: Fn(&KnownShape) -> f64 {
+
+        self.map_summary(metric, |s| s.mean)
+    }
+
+    pub fn min<F>(&self, metric: F) -> BTreeMap<&K, f64>
+        where F: Fn(&KnownShape) -> f64 {
+
+        self.map_summary(metric, |s| s.min)
+    }
+
+    pub fn max<F>(&self, metric: F) -> BTreeMap<&K, f64>
+        where F: Fn(&KnownShape) -> f64 {
+
+        self.map_summary(metric, |s| s.max)
+    }
+
+    /// Compute all descriptive statistics for `metric` within each group
+    pub fn summary<F>(&self, metric: F) -> BTreeMap<&K, Summary>
+        where F: Fn(&KnownShape) -> f64 {
+
+        self.groups.iter()
+            .filter_map(|(key, shapes)| {
+                let values: Vec<f64> = shapes.iter().map(|s| metric(s)).collect();
+                Some((key, Summary::of(&values)?))
+            })
+            .collect()
+    }
+
+    /// Tabulate count, sum, mean, min and max of `metric` for every group
+    pub fn table<F>(&self, metric: F) -> GroupTable
+        where F: Fn(&KnownShape) -> f64,
+              K: fmt::Display {
+
+        let rows = self.summary(metric)
+            .into_iter()
+            .map(|(key, summary)| (key.to_string(), summary))
+            .collect();
+
+        GroupTable { rows }
+    }
+
+    fn map_values<F>(&self, aggregate: F) -> BTreeMap<&K, f64>
+        where F: Fn(&[&KnownShape]) -> f64 {
+
+        self.groups.iter()
+            .map(|(key, shapes)| (key, aggregate(shapes)))
+            .collect()
+    }
+
+    fn map_summary<F, G>(&self, metric: F, stat: G) -> BTreeMap<&K, f64>
+        where F: Fn(&KnownShape) -> f64,
+              G: Fn(&Summary) -> f64 {
+
+        self.summary(metric)
+            .into_iter()
+            .map(|(key, summary)| (key, stat(&summary)))
+            .collect()
+    }
+}
+
+/// Per-group aggregates of one metric, ready for display
+#[derive(Clone, Debug, PartialEq)]
+pub struct GroupTable {
+    rows: Vec<(String, Summary)>,
+}
+
+impl GroupTable {
+    /// Group labels with their statistics, in key order
+    pub fn rows(&self) -> &[(String, Summary)] {
+        &self.rows
+    }
+}
+
+impl fmt::Display for GroupTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:24}{:>8}{:>16}{:>16}{:>16}{:>16}",
+                 "Group", "Count", "Sum", "Mean", "Min", "Max")?;
+
+        for (label, summary) in self.rows.iter() {
+            writeln!(f, "{:24}{:>8}{:>16.4}{:>16.4}{:>16.4}{:>16.4}",
+                     label, summary.count, summary.sum, summary.mean,
+                     summary.min, summary.max)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hamcrest2::prelude::*;
+
+    use std::f64::consts::PI;
+
+    use crate::collection::ShapeCollection;
+    use crate::testing::some_shapes;
+
+    #[test]
+    fn test_by_name() {
+        let shapes: ShapeCollection = some_shapes().into();
+        let groups = shapes.group_by(by_name);
+
+        assert_that!(groups.len(), is(equal_to(5)));
+        assert_that!(groups.keys().copied().collect::<Vec<_>>(),
+                     equal_to(vec!["Circle", "Equilateral Triangle", "Right Triangle",
+                                   "Square", "Triangle"]));
+
+        let squares = groups.get(&"Square").unwrap();
+        assert_that!(squares.iter().map(|s| s.area()).collect::<Vec<_>>(),
+                     equal_to(vec![4.0, 16.0]));
+
+        assert_that!(groups.count()[&"Square"], is(equal_to(2)));
+        assert_that!(groups.sum(Shape::area)[&"Square"], close_to(20.0, 1e-9));
+        assert_that!(groups.mean(Shape::perimeter)[&"Square"], close_to(12.0, 1e-9));
+        assert_that!(groups.min(Shape::area)[&"Square"], close_to(4.0, 1e-9));
+        assert_that!(groups.max(Shape::area)[&"Circle"], close_to(4.0 * PI, 1e-9));
+    }
+
+    #[test]
+    fn test_by_closure() {
+        let shapes: ShapeCollection = some_shapes().into();
+        let groups = shapes.group_by(|s| s.fields().len());
+
+        assert_that!(groups.keys().copied().collect::<Vec<_>>(),
+                     equal_to(vec![1, 2, 3]));
+        assert_that!(groups.count()[&1], is(equal_to(2)));
+        assert_that!(groups.count()[&2], is(equal_to(3)));
+    }
+
+    #[test]
+    fn test_by_size() {
+        let shapes: ShapeCollection = some_shapes().into();
+        let groups = shapes.group_by(by_size(Shape::area, 10.0));
+
+        let labels: Vec<String> = groups.keys().map(|k| k.to_string()).collect();
+        assert_that!(labels, equal_to(vec!["[0, 10)", "[10, 20)"]));
+        assert_that!(groups.iter().next().unwrap().1.len(), is(equal_to(5)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_by_size_zero_width() {
+        let _ = by_size(Shape::area, 0.0);
+    }
+
+    #[test]
+    fn test_table() {
+        let shapes: ShapeCollection = some_shapes().into();
+        let table = shapes.group_by(by_name).table(Shape::area);
+        let text = table.to_string();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_that!(table.rows().len(), is(equal_to(5)));
+        assert_that!(lines.len(), is(equal_to(6)));
+        assert!(lines[0].starts_with("Group"));
+        assert!(lines[4].starts_with("Square"));
+        assert!(lines[4].ends_with("16.0000"));
+    }
+
+    #[test]
+    fn test_empty() {
+        let shapes = ShapeCollection::new();
+        let groups = shapes.group_by(by_name);
+
+        assert!(groups.is_empty());
+        assert!(groups.sum(Shape::area).is_empty());
+        assert_that!(groups.table(Shape::area).rows().len(), is(equal_to(0)));
+    }
+}
@@ -1,5 +1,4 @@
 #[cfg(test)]
-#[macro_use]
 extern crate hamcrest2;
 #[macro_use]
 extern crate lazy_static;
@@ -13,3 +12,32 @@ pub mod square;
 pub mod known_shape;
 
 pub mod factory;
+pub mod collection;
+pub mod group;
+pub mod dedup;
+pub mod packing;
+pub mod table;
+pub mod binary;
+pub mod query;
+pub mod sort;
+pub mod formatter;
+pub mod locale;
+pub mod named_params;
+pub mod report;
+pub mod compress;
+pub mod csv;
+pub mod geometry;
+pub mod gis;
+pub mod dxf;
+pub mod svg;
+pub mod detect;
+pub mod render;
+
+#[cfg(feature = "exact")]
+pub mod exact;
+
+#[cfg(feature = "parallel")]
+pub mod parallel;
+
+#[cfg(test)]
+mod testing;
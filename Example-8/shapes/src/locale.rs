@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use std::env;
+
+use crate::known_shape::KnownShape;
+
+/// Built-in message catalogs, keyed by language code
+const CATALOGS: [(&str, &str); 4] = [
+    ("en", include_str!("../locales/en.txt")),
+    ("es", include_str!("../locales/es.txt")),
+    ("de", include_str!("../locales/de.txt")),
+    ("fr", include_str!("../locales/fr.txt")),
+];
+
+/// A message catalog and number formatting rules for one language.
+///
+/// Messages are keyed by the English text used in the source code (e.g.,
+/// `"Right Triangle"` or `"Hypotenuse"`). Keys without a translation are
+/// returned unchanged.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Locale {
+    code: String,
+    messages: HashMap<String, String>,
+    decimal_separator: char,
+    grouping_separator: Option<char>,
+}
+
+impl Locale {
+    /// Create the English locale (i.e., the untranslated labels)
+    pub fn english() -> Self {
+        Locale::by_code("en").unwrap()
+    }
+
+    /// Load a built-in locale by language code (e.g., `"de"`).
+    ///
+    /// Region suffixes and encodings are ignored, so `"de_DE.UTF-8"` selects
+    /// the German catalog.
+    pub fn by_code(code: &str) -> Option<Self> {
+        let language = code.split(['_', '-', '.'])
+            .next()
+            .unwrap_or("")
+            .to_lowercase();
+
+        CATALOGS.iter()
+            .find(|(c, _)| *c == language)
+            .map(|(c, catalog)| Locale::from_catalog(c, catalog))
+    }
+
+    /// Select a locale from `SHAPES_LOCALE`, then `LANG`, falling back to
+    /// English.
+    pub fn from_env() -> Self {
+        ["SHAPES_LOCALE", "LANG"].iter()
+            .filter_map(|var| env::var(var).ok())
+            .find_map(|code| Locale::by_code(&code))
+            .unwrap_or_else(Locale::english)
+    }
+
+    /// Language codes of the built-in catalogs
+    pub fn available() -> Vec<&'static str> {
+        CATALOGS.iter().map(|(code, _)| *code).collect()
+    }
+
+    /// Build a locale from the text of a message catalog.
+    ///
+    /// Each non-blank line that does not start with `#` has the form
+    /// `key = translation`. A translation may be wrapped in double quotes to
+    /// preserve surrounding whitespace. The special keys `@decimal` and
+    /// `@grouping` set the number separators.
+    ///
+    /// # Arguments
+    ///
+    ///  * `code` - language code
+    ///  * `catalog` - catalog text
+    ///
+    pub fn from_catalog(code: &str, catalog: &str) -> Self {
+        let mut locale = Locale {
+            code: code.to_string(),
+            messages: HashMap::new(),
+            decimal_separator: '.',
+            grouping_separator: None,
+        };
+
+        let entries = catalog.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| line.split_once('='));
+
+        for (key, value) in entries {
+            let key = key.trim();
+            let value = unquote(value.trim());
+
+            match key {
+                "@decimal" => {
+                    locale.decimal_separator = value.chars().next()
+                        .unwrap_or('.');
+                }
+                "@grouping" => {
+                    locale.grouping_separator = value.chars().next();
+                }
+                _ => {
+                    locale.messages.insert(key.to_string(), value.to_string());
+                }
+            }
+        }
+
+        locale
+    }
+
+    /// Language code of this locale
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// Translate a message, returning the key if there is no translation
+    pub fn translate<'a>(&'a self, key: &'a str) -> &'a str {
+        self.messages.get(key).map(String::as_str).unwrap_or(key)
+    }
+
+    /// Map a (possibly localized) shape name to the name the factory uses.
+    ///
+    /// English names are always accepted.
+    pub fn canonical_name(&self, name: &str) -> Option<&'static str> {
+        KnownShape::KNOWN_NAMES.iter()
+            .find(|&&known| known == name || self.translate(known) == name)
+            .copied()
+    }
+
+    /// Replace the separators in a number written by `format!` (e.g.,
+    /// `-1234.5678` becomes `-1.234,5678` in German).
+    pub fn localize_number(&self, number: &str) -> String {
+        let (sign, unsigned) = match number.strip_prefix('-') {
+            Some(rest) => ("-", rest),
+            None => ("", number),
+        };
+
+        let (int_part, rest) = match unsigned.find(|c: char| !c.is_ascii_digit()) {
+            Some(idx) => unsigned.split_at(idx),
+            None => (unsigned, ""),
+        };
+
+        let mut localized = String::from(sign);
+
+        match self.grouping_separator {
+            Some(sep) => {
+                for (idx, digit) in int_part.chars().enumerate() {
+                    if idx > 0 && (int_part.len() - idx) % 3 == 0 {
+                        localized.push(sep);
+                    }
+                    localized.push(digit);
+                }
+            }
+            None => localized.push_str(int_part),
+        }
+
+        match rest.strip_prefix('.') {
+            Some(fraction) => {
+                localized.push(self.decimal_separator);
+                localized.push_str(fraction);
+            }
+            None => localized.push_str(rest),
+        }
+
+        localized
+    }
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Locale::english()
+    }
+}
+
+fn unquote(value: &str) -> &str {
+    value.strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hamcrest2::prelude::*;
+
+    #[test]
+    fn test_by_code() {
+        assert_that!(Locale::by_code("de_DE.UTF-8").unwrap().code(),
+                     equal_to("de"));
+        assert_that!(Locale::by_code("ES").unwrap().code(), equal_to("es"));
+        assert!(Locale::by_code("xx").is_none());
+
+        for code in Locale::available() {
+            assert!(Locale::by_code(code).is_some());
+        }
+    }
+
+    #[test]
+    fn test_translate() {
+        let german = Locale::by_code("de").unwrap();
+
+        assert_that!(german.translate("Circle"), equal_to("Kreis"));
+        assert_that!(german.translate("Area"), equal_to("Fläche"));
+        assert_that!(german.translate("Unknown"), equal_to("Unknown"));
+
+        let english = Locale::english();
+        assert_that!(english.translate("Right Triangle"),
+                     equal_to("Right Triangle"));
+    }
+
+    #[test]
+    fn test_catalogs_complete() {
+        let german = Locale::by_code("de").unwrap();
+        let mut keys: Vec<&String> = german.messages.keys().collect();
+        keys.sort();
+
+        for code in ["es", "fr"] {
+            let locale = Locale::by_code(code).unwrap();
+            let mut other: Vec<&String> = locale.messages.keys().collect();
+            other.sort();
+
+            assert_that!(other, equal_to(keys.clone()));
+        }
+
+        assert_that!(german.translate("Top {} Shapes"), equal_to("Die ersten {} Formen"));
+        assert_that!(german.translate("Std Dev"), equal_to("Std.-Abw."));
+    }
+
+    #[test]
+    fn test_canonical_name() {
+        let spanish = Locale::by_code("es").unwrap();
+
+        assert_that!(spanish.canonical_name("Triángulo Rectángulo"),
+                     equal_to(Some("Right Triangle")));
+        assert_that!(spanish.canonical_name("Circle"),
+                     equal_to(Some("Circle")));
+        assert_that!(spanish.canonical_name("Kreis"), equal_to(None));
+    }
+
+    #[test]
+    fn test_localize_number() {
+        let german = Locale::by_code("de").unwrap();
+        assert_that!(german.localize_number("-1234567.5000"),
+                     equal_to("-1.234.567,5000"));
+        assert_that!(german.localize_number("12.5"), equal_to("12,5"));
+
+        let french = Locale::by_code("fr").unwrap();
+        assert_that!(french.localize_number("1234.5"), equal_to("1 234,5"));
+
+        let english = Locale::english();
+        assert_that!(english.localize_number("1234.5"), equal_to("1234.5"));
+        assert_that!(english.localize_number("1.5e3"), equal_to("1.5e3"));
+    }
+
+    #[test]
+    fn test_from_catalog() {
+        let catalog = r#"
+            # comment
+            @decimal = ,
+            @grouping = "'"
+            Circle = Kreis
+        "#;
+        let locale = Locale::from_catalog("custom", catalog);
+
+        assert_that!(locale.translate("Circle"), equal_to("Kreis"));
+        assert_that!(locale.localize_number("1000.25"),
+                     equal_to("1'000,25"));
+    }
+}
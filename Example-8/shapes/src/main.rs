@@ -1,19 +1,113 @@
 extern crate shapes;
-extern crate ordered_float;
-
-use ordered_float::OrderedFloat;
 
 use shapes::shape::Shape;
+use shapes::collection::{ShapeCollection, Summary};
+use shapes::compress;
+use shapes::detect::{self, InputFormat};
+use shapes::known_shape::KnownShape;
 use shapes::factory;
+use shapes::formatter::ShapeFormatter;
+use shapes::locale::Locale;
+use shapes::query::Query;
+use shapes::render::{RenderMode, Renderer};
+use shapes::report::{self, InputTotals, ReportFormat};
+use shapes::sort::SortSpec;
 
-use std::io::BufReader;
-use std::fs::File;
+use std::io;
 use std::env;
+use std::path::Path;
 use std::vec::Vec;
 
+/// File name that stands for Standard In
+const STDIN: &str = "-";
+
+/// A per-shape measurement used for statistics
+type Metric = fn(&KnownShape) -> f64;
+
 const PROGRAM_HEADING: [&str; 2] = ["Objects & Traits: 2-D Shapes",
                                     "Thomas J. Kennedy"];
 
+/// Command line options
+struct Options {
+    /// Files or glob patterns to read (`-` is Standard In)
+    file_names: Vec<String>,
+
+    /// Only report shapes that satisfy this query
+    filter: Option<Query>,
+
+    /// Order in which shapes are displayed
+    sort: Option<SortSpec>,
+
+    /// Number of shapes to list in the "Top Shapes" section
+    top: Option<usize>,
+
+    /// Write a CSV, Markdown or HTML report instead of the usual output
+    format: Option<ReportFormat>,
+
+    /// Format of the input file (detected if not given)
+    input_format: Option<InputFormat>,
+
+    /// Draw each shape in the terminal with these characters
+    draw: Option<RenderMode>,
+}
+
+/// Parse `[--filter EXPR] [--sort SPEC] [--top K] [--format FMT]
+/// [--input-format FMT] [--draw MODE] file_name...`. Options may also be
+/// written as `--option=value`.
+fn parse_args(argv: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        file_names: Vec::new(),
+        filter: None,
+        sort: None,
+        top: None,
+        format: None,
+        input_format: None,
+        draw: None,
+    };
+
+    let mut args = argv.iter().skip(1);
+
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            options.file_names.push(arg.clone());
+            continue;
+        }
+
+        let (flag, value) = match arg.split_once('=') {
+            Some((flag, value)) => (flag, value.to_string()),
+            None => {
+                let value = args.next()
+                    .ok_or(format!("{} requires a value", arg))?;
+                (arg.as_str(), value.clone())
+            }
+        };
+
+        match flag {
+            "--filter" => {
+                let query = Query::parse(&value)
+                    .map_err(|err| format!("Invalid filter: {}", err))?;
+                options.filter = Some(query);
+            }
+            "--sort" => options.sort = Some(SortSpec::parse(&value)?),
+            "--top" => {
+                let k = value.parse()
+                    .map_err(|_| format!("Invalid count '{}'", value))?;
+                options.top = Some(k);
+            }
+            "--format" => options.format = Some(value.parse()?),
+            "--input-format" => options.input_format = Some(value.parse()?),
+            "--draw" => options.draw = Some(value.parse()?),
+            _ => return Err(format!("Unknown option '{}'", flag)),
+        }
+    }
+
+    if options.file_names.is_empty() {
+        return Err("Missing file_name".to_string());
+    }
+
+    Ok(options)
+}
+
 /// Utility function to print the program heading to Standard Out
 #[cfg_attr(tarpaulin, skip)]
 fn print_heading() {
@@ -71,47 +165,341 @@ fn print_section_heading(title: &str) {
 #[cfg_attr(tarpaulin, skip)]
 fn main() {
 
-    print_heading();
-
     let argv: Vec<String> = env::args().collect();
 
-    if argv.len() < 2 {
-        println!("Usage: {} file_name", argv[0]);
-        std::process::exit(1);
+    let options = match parse_args(&argv) {
+        Ok(options) => options,
+        Err(err) => {
+            print_heading();
+            println!("{}", err);
+            println!("Usage: {} [--filter EXPR] [--sort SPEC] [--top K] \
+                      [--format csv|markdown|html] [--input-format FMT] \
+                      [--draw ascii|block|braille] file_name... \
+                      (- reads Standard In)", argv[0]);
+            std::process::exit(1);
+        }
+    };
+
+    // Reports are meant to be redirected to a file, so they are written
+    // without any of the usual headings
+    if let Some(format) = options.format {
+        write_report(&options, format);
+        return;
     }
 
+    print_heading();
+
+    // Shape names, labels, headings and numbers follow SHAPES_LOCALE or LANG
+    let locale = Locale::from_env();
+    let formatter = ShapeFormatter::new().locale(locale.clone());
+
     // Examine the ShapeFactory
-    print_section_heading("Available Shapes");
-    print!("{}", factory::list_known());
+    print_section_heading(locale.translate("Available Shapes"));
+    let mut names: Vec<&str> = KnownShape::KNOWN_NAMES.iter()
+        .map(|name| locale.translate(name))
+        .collect();
+    names.sort();
+    for name in names {
+        println!("  {}", name);
+    }
     println!("{}", "-".repeat(38));
-    println!("{:>2} shapes available.", factory::number_known());
+    println!("{:>2} {}", factory::number_known(), locale.translate("shapes available."));
     println!();
 
-    let f = File::open(&argv[1]).expect("Could not open file");
-    let ins = BufReader::new(f);
+    let (shapes, inputs) = read_inputs(&options, &locale);
 
-    let shapes = factory::read_shapes_with(ins);
-
-    print_section_heading("Display All Shapes");
+    print_section_heading(locale.translate("Display All Shapes"));
     for s in shapes.iter() {
-        println!("{}", s);
+        println!("{}", formatter.format(s));
+
+        if let Some(mode) = options.draw {
+            if let Some(drawing) = Renderer::new().mode(mode).render(s) {
+                println!("{}", drawing);
+            }
+        }
     }
 
-    print_section_heading("Display Shape Names");
+    print_section_heading(locale.translate("Display Shape Names"));
     for s in shapes.iter() {
-        println!("{}", s.name());
+        println!("{}", locale.translate(s.name()));
     }
     println!();
 
     // Find the "largest" and "smallest" shapes
-    print_section_heading("Largest Shape by Area");
-    let largest = &*shapes.iter()
-                          .max_by_key(|s| OrderedFloat(s.area())).unwrap();
-    println!("{}", largest);
+    print_section_heading(locale.translate("Largest Shape by Area"));
+    if let Some(largest) = shapes.max_by(Shape::area) {
+        println!("{}", formatter.format(largest));
+    }
+
+    print_section_heading(locale.translate("Smallest Shape by Perimeter"));
+    if let Some(smallest) = shapes.min_by(Shape::perimeter) {
+        println!("{}", formatter.format(smallest));
+    }
+
+    // List the first K shapes by the requested order (largest area first
+    // if no order was given)
+    if let Some(k) = options.top {
+        let spec = options.sort.clone()
+            .unwrap_or_else(|| SortSpec::parse("area desc").unwrap());
+
+        print_section_heading(&locale.translate("Top {} Shapes")
+            .replace("{}", &k.to_string()));
+        for s in shapes.top_k(k, &spec) {
+            println!("{}", formatter.format(s));
+        }
+    }
+
+    print_statistics(&shapes, &locale);
+
+    if inputs.len() > 1 {
+        print_input_totals(&inputs, &locale);
+    }
+}
+
+/// Utility function to read and filter every input, then sort the combined
+/// shapes. Inputs that can not be read are reported to Standard Error and
+/// skipped; the program exits if none of them can be read.
+#[cfg_attr(tarpaulin, skip)]
+fn read_inputs(options: &Options, locale: &Locale)
+    -> (ShapeCollection, Vec<InputTotals>) {
 
+    let mut shapes = ShapeCollection::new();
+    let mut inputs = Vec::new();
+
+    for name in expand_patterns(&options.file_names) {
+        let found = name.and_then(|name| {
+            read_input(&name, options, locale).map(|found| (name, found))
+        });
+
+        match found {
+            Ok((name, found)) => {
+                let mut found: ShapeCollection = found.into();
+
+                if let Some(query) = &options.filter {
+                    found = found.filter(query);
+                }
+
+                inputs.push(InputTotals::new(input_name(&name), &found));
+                shapes.extend(found);
+            }
+            Err(err) => eprintln!("{}", err),
+        }
+    }
+
+    if inputs.is_empty() {
+        std::process::exit(1);
+    }
+
+    if let Some(spec) = &options.sort {
+        shapes.sort_by_spec(spec);
+    }
+
+    (shapes, inputs)
+}
+
+/// Utility function to expand glob patterns (e.g., `data/*.txt`) into the
+/// matching file names, in alphabetical order. Other names, including `-`,
+/// are kept as is.
+#[cfg_attr(tarpaulin, skip)]
+fn expand_patterns(names: &[String]) -> Vec<Result<String, String>> {
+    let mut expanded = Vec::new();
+
+    for name in names {
+        if !name.contains(['*', '?', '[']) {
+            expanded.push(Ok(name.clone()));
+            continue;
+        }
+
+        let paths = match glob::glob(name) {
+            Ok(paths) => paths,
+            Err(err) => {
+                expanded.push(Err(format!("{}: {}", name, err)));
+                continue;
+            }
+        };
+
+        let before = expanded.len();
+        expanded.extend(paths.map(|path| {
+            path.map(|path| path.display().to_string())
+                .map_err(|err| err.to_string())
+        }));
+
+        if expanded.len() == before {
+            expanded.push(Err(format!("{}: no matching files", name)));
+        }
+    }
+
+    expanded
+}
+
+/// Utility function to name an input in messages and totals
+fn input_name(name: &str) -> &str {
+    if name == STDIN { "(stdin)" } else { name }
+}
+
+/// Utility function to read every shape from one input (a file, or Standard
+/// In for `-`), decompressing it if needed and detecting its format from its
+/// start unless one was given. Guesses and entries that are not valid shapes
+/// are reported to Standard Error. Text is parsed as it is read.
+#[cfg_attr(tarpaulin, skip)]
+fn read_input(name: &str, options: &Options, locale: &Locale)
+    -> Result<Vec<KnownShape>, String> {
+
+    let path = (name != STDIN).then(|| Path::new(name));
+
+    let ins = match path {
+        Some(path) => compress::open(path),
+        None => compress::decompress(io::stdin().lock()),
+    };
+    let (start, ins) = ins.and_then(detect::sample)
+        .map_err(|err| format!("{}: {}", input_name(name), err))?;
+
+    let format = match options.input_format {
+        Some(format) => format,
+        None => {
+            let detection = detect::detect(path, &start);
+
+            if detection.is_ambiguous() {
+                eprintln!("Warning: guessed the input format of {} as {}",
+                          input_name(name), detection);
+            }
+            detection.format()
+        }
+    };
+
+    // Errors from files already name the file
+    let located = |err: String| match path {
+        Some(_) => err,
+        None => format!("{}: {}", input_name(name), err),
+    };
+    let shapes = detect::read_as(format, ins, path, locale).map_err(located)?;
+
+    // Entries that are not valid shapes are reported, and the rest kept
+    Ok(shapes.into_iter()
+        .filter_map(|shape| shape.map_err(|err| eprintln!("{}", located(err))).ok())
+        .collect())
+}
+
+#[cfg(not(feature = "parallel"))]
+#[cfg_attr(tarpaulin, skip)]
+fn summarize(shapes: &ShapeCollection, metric: Metric) -> Option<Summary> {
+    shapes.summary(metric)
+}
+
+#[cfg(feature = "parallel")]
+#[cfg_attr(tarpaulin, skip)]
+fn summarize(shapes: &ShapeCollection, metric: Metric) -> Option<Summary> {
+    shapes::parallel::summary(shapes.as_slice(), metric)
+}
+
+/// Utility function to read, filter and sort the inputs, then write a report
+/// to Standard Out
+#[cfg_attr(tarpaulin, skip)]
+fn write_report(options: &Options, format: ReportFormat) {
+
+    // Input may use localized names; the report itself is always English
+    let (shapes, inputs) = read_inputs(options, &Locale::from_env());
+
+    // Per-input totals are only worth reporting for several inputs
+    let inputs = if inputs.len() > 1 { &inputs[..] } else { &[] };
+
+    let stdout = io::stdout();
+    report::write_report_with_inputs(&mut stdout.lock(), &shapes, inputs,
+                                     format)
+        .expect("Could not write report");
+}
+
+/// Utility function to print counts and area/perimeter statistics, overall
+/// and per shape name
+#[cfg_attr(tarpaulin, skip)]
+fn print_statistics(shapes: &ShapeCollection, locale: &Locale) {
+
+    let tr = |key| locale.translate(key);
+
+    print_section_heading(tr("Shape Counts"));
+    for (name, count) in shapes.count_by_name() {
+        println!("{:24}:{:>12}", tr(name), count);
+    }
+    println!("{:24}:{:>12}", tr("Total"), shapes.len());
+    println!();
+
+    // Headings are whole catalog keys, since word order varies by language
+    let metrics: [(&str, &str, Metric); 2] = [
+        ("Area Statistics", "Area by Shape", Shape::area),
+        ("Perimeter Statistics", "Perimeter by Shape", Shape::perimeter),
+    ];
+
+    for (statistics_heading, by_shape_heading, metric) in metrics {
+        print_section_heading(tr(statistics_heading));
+        print_summary(summarize(shapes, metric), locale);
+
+        for p in [25.0, 75.0, 90.0] {
+            if let Some(value) = shapes.percentile(metric, p) {
+                println!("{:12}:{:>24}", format!("P{}", p), number(value, locale));
+            }
+        }
+        println!();
+
+        print_section_heading(tr(by_shape_heading));
+        println!("{:24}{:>8}{:>16}{:>16}{:>16}",
+                 tr("Name"), tr("Count"), tr("Sum"), tr("Mean"), tr("Median"));
+        for (name, summary) in shapes.summary_by_name(metric) {
+            println!("{:24}{:>8}{:>16}{:>16}{:>16}",
+                     tr(name), summary.count, number(summary.sum, locale),
+                     number(summary.mean, locale), number(summary.median, locale));
+        }
+        println!();
+    }
+}
+
+/// Utility function to print the count, total area and total perimeter of
+/// each input
+#[cfg_attr(tarpaulin, skip)]
+fn print_input_totals(inputs: &[InputTotals], locale: &Locale) {
+
+    let tr = |key| locale.translate(key);
+
+    print_section_heading(tr("Totals by Input"));
+    println!("{:24}{:>8}{:>16}{:>16}",
+             tr("Input"), tr("Count"), tr("Area"), tr("Perimeter"));
+    for input in inputs {
+        println!("{:24}{:>8}{:>16}{:>16}",
+                 input.name, input.count, number(input.area, locale),
+                 number(input.perimeter, locale));
+    }
+    println!();
+}
+
+/// Utility function to print a summary with localized labels and numbers
+#[cfg_attr(tarpaulin, skip)]
+fn print_summary(summary: Option<Summary>, locale: &Locale) {
+    let summary = match summary {
+        Some(summary) => summary,
+        None => {
+            println!("{}\n", locale.translate("No shapes"));
+            return;
+        }
+    };
+
+    println!("{:12}:{:>24}", locale.translate("Count"), summary.count);
+
+    let values = [
+        ("Sum", summary.sum),
+        ("Min", summary.min),
+        ("Max", summary.max),
+        ("Mean", summary.mean),
+        ("Median", summary.median),
+        ("Variance", summary.variance),
+        ("Std Dev", summary.std_dev()),
+    ];
+
+    for (label, value) in values {
+        println!("{:12}:{:>24}", locale.translate(label), number(value, locale));
+    }
+}
 
-    print_section_heading("Smallest Shape by Perimeter");
-    let smallest = &*shapes.iter()
-                           .min_by_key(|s| OrderedFloat(s.perimeter())).unwrap();
-    println!("{}", smallest);
+/// Utility function to write a statistic to 4 decimal places with the
+/// separators of `locale`
+fn number(value: f64, locale: &Locale) -> String {
+    locale.localize_number(&format!("{:.4}", value))
 }
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+
+/// Dimensions given by name, e.g., `base=4 height=5`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NamedParams {
+    values: HashMap<String, f64>,
+}
+
+impl NamedParams {
+    /// Parse whitespace separated `key=value` pairs.
+    ///
+    /// Return `None` if any token is not a `key=value` pair, a value is not a
+    /// number, or a key appears more than once.
+    ///
+    /// # Arguments
+    ///
+    ///  * `text` - the dimension portion of an input line
+    ///
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut values = HashMap::new();
+
+        for token in text.split_whitespace() {
+            let (key, value) = token.split_once('=')?;
+            let value: f64 = value.trim().parse().ok()?;
+
+            if values.insert(normalized(key), value).is_some() {
+                return None;
+            }
+        }
+
+        Some(NamedParams { values })
+    }
+
+    /// Add (or replace) one parameter, returning the previous value
+    pub fn insert(&mut self, key: &str, value: f64) -> Option<f64> {
+        self.values.insert(normalized(key), value)
+    }
+
+    /// Remove one parameter, returning its value
+    pub fn remove(&mut self, key: &str) -> Option<f64> {
+        self.values.remove(&normalized(key))
+    }
+
+    /// Retrieve a single value by name
+    pub fn get(&self, key: &str) -> Option<f64> {
+        self.values.get(&normalized(key)).copied()
+    }
+
+    /// Parameter names, sorted
+    pub fn keys(&self) -> Vec<&str> {
+        let mut keys: Vec<&str> = self.values.keys().map(|k| k.as_str()).collect();
+        keys.sort();
+        keys
+    }
+
+    /// Number of parameters
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Check whether there are no parameters
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Retrieve the values for `keys` (in the same order), but only if the
+    /// parameters are exactly those keys--no more, no fewer.
+    pub fn exactly<const N: usize>(&self, keys: [&str; N]) -> Option<[f64; N]> {
+        if self.values.len() != N {
+            return None;
+        }
+
+        let mut found = [0.0; N];
+
+        for (slot, key) in found.iter_mut().zip(keys.iter()) {
+            *slot = self.get(key)?;
+        }
+
+        Some(found)
+    }
+}
+
+/// Keys are case insensitive and ignore surrounding whitespace
+fn normalized(key: &str) -> String {
+    key.trim().to_lowercase()
+}
+
+/// Build a shape from any equivalent set of named parameters (e.g., a
+/// Circle from `radius`, `diameter`, `perimeter` or `area`).
+pub trait FromNamedParams: Sized {
+    /// Return `None` if the parameters do not describe this shape
+    fn from_named(params: &NamedParams) -> Option<Self>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hamcrest2::prelude::*;
+
+    #[test]
+    fn test_parse() {
+        let params = NamedParams::parse("base=4  Height=5").unwrap();
+
+        assert_that!(params.len(), is(equal_to(2)));
+        assert_that!(params.keys(), equal_to(vec!["base", "height"]));
+        assert_that!(params.get("base"), equal_to(Some(4.0)));
+        assert_that!(params.get("height"), equal_to(Some(5.0)));
+        assert_that!(params.get("Height"), equal_to(Some(5.0)));
+
+        assert!(NamedParams::parse("4 5").is_none());
+        assert!(NamedParams::parse("base=four").is_none());
+        assert!(NamedParams::parse("base=4 base=5").is_none());
+    }
+
+    #[test]
+    fn test_insert_remove() {
+        let mut params = NamedParams::default();
+
+        assert_that!(params.insert("Base", 4.0), equal_to(None));
+        assert_that!(params.insert("base", 5.0), equal_to(Some(4.0)));
+        assert_that!(params.get("base"), equal_to(Some(5.0)));
+        assert_that!(params.get(" BASE "), equal_to(Some(5.0)));
+        assert_that!(params.remove("Base"), equal_to(Some(5.0)));
+        assert!(params.is_empty());
+    }
+
+    #[test]
+    fn test_exactly() {
+        let params = NamedParams::parse("base=4 height=5").unwrap();
+
+        assert_that!(params.exactly(["height", "base"]),
+                     equal_to(Some([5.0, 4.0])));
+        assert_that!(params.exactly(["base"]), equal_to(None));
+        assert_that!(params.exactly(["base", "area"]), equal_to(None));
+    }
+}
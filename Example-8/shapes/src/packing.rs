@@ -0,0 +1,578 @@
+//! Lay shapes out on rectangular stock sheets.
+//!
+//! Every shape is packed by its bounding box in a reference orientation:
+//!
+//!   * Square and Circle - axis-aligned
+//!   * Right Triangle - legs along the axes (base horizontal)
+//!   * Triangle and Equilateral Triangle - longest side along the bottom
+//!
+//! A placement may rotate that box by 90 degrees. Shapes that do not fit on
+//! the current sheets open a new sheet; shapes too large for an empty sheet
+//! are reported as unplaced.
+
+use crate::known_shape::KnownShape;
+use crate::shape::Shape;
+
+/// Allow for rounding when comparing lengths
+const EPSILON: f64 = 1e-9;
+
+/// How free space on a sheet is tracked and chosen
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Heuristic {
+    /// Rows ("shelves") as tall as their tallest shape, filled left to right
+    Shelf,
+
+    /// Free space is split into two rectangles after every placement; the
+    /// free rectangle that wastes the least area is chosen
+    Guillotine,
+
+    /// Free space is every maximal empty rectangle; the one that leaves the
+    /// shortest leftover side is chosen
+    MaxRects,
+}
+
+/// Where one shape was placed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Placement {
+    /// Position of the shape in the input
+    pub index: usize,
+
+    /// Which sheet (starting at 0)
+    pub sheet: usize,
+
+    /// Lower-left corner of the bounding box
+    pub x: f64,
+    pub y: f64,
+
+    /// Size of the bounding box, after rotation
+    pub width: f64,
+    pub height: f64,
+
+    /// Whether the reference orientation was rotated by 90 degrees
+    pub rotated: bool,
+}
+
+/// The result of packing a set of shapes
+#[derive(Clone, Debug, PartialEq)]
+pub struct Packing {
+    /// Placements, ordered by input position
+    pub placements: Vec<Placement>,
+
+    /// Input positions of shapes too large for a sheet
+    pub unplaced: Vec<usize>,
+
+    /// Number of sheets used
+    pub sheets: usize,
+
+    sheet_area: f64,
+    used_area: Vec<f64>,
+}
+
+impl Packing {
+    /// Fraction (0 to 1) of all sheets covered by shapes (using the shapes'
+    /// actual areas, not their bounding boxes)
+    pub fn utilization(&self) -> f64 {
+        if self.sheets == 0 {
+            return 0.0;
+        }
+
+        self.used_area.iter().sum::<f64>() / (self.sheets as f64 * self.sheet_area)
+    }
+
+    /// Fraction (0 to 1) of one sheet covered by shapes
+    pub fn sheet_utilization(&self, sheet: usize) -> Option<f64> {
+        self.used_area.get(sheet).map(|used| used / self.sheet_area)
+    }
+}
+
+/// Width and height of `shape`'s bounding box in its reference orientation
+pub fn bounding_box(shape: &KnownShape) -> (f64, f64) {
+    match shape {
+        KnownShape::Square(s) => (s.side, s.side),
+        KnownShape::Circle(c) => (2.0 * c.radius, 2.0 * c.radius),
+        KnownShape::RightTriangle(t) => (t.base, t.height),
+        KnownShape::EquilateralTriangle(_) | KnownShape::Triangle(_) => {
+            let longest = shape.fields()
+                .iter()
+                .filter(|(label, _)| label.starts_with("Side"))
+                .map(|(_, side)| *side)
+                .fold(0.0, f64::max);
+
+            (longest, 2.0 * shape.area() / longest)
+        }
+    }
+}
+
+/// Pack shapes onto identical rectangular sheets.
+///
+/// # Example
+///
+/// ```
+/// use shapes::packing::{Heuristic, SheetPacker};
+/// use shapes::square::Square;
+///
+/// let shapes = vec![Square::with_side(2.0).into(); 5];
+/// let packing = SheetPacker::new(4.0, 4.0)
+///     .heuristic(Heuristic::MaxRects)
+///     .pack(&shapes);
+///
+/// assert_eq!(packing.sheets, 2);
+/// assert_eq!(packing.utilization(), 20.0 / 32.0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct SheetPacker {
+    width: f64,
+    height: f64,
+    heuristic: Heuristic,
+    spacing: f64,
+    allow_rotation: bool,
+}
+
+impl SheetPacker {
+    /// Pack onto `width` x `height` sheets using the MaxRects heuristic,
+    /// no spacing and rotation allowed
+    pub fn new(width: f64, height: f64) -> Self {
+        SheetPacker {
+            width,
+            height,
+            heuristic: Heuristic::MaxRects,
+            spacing: 0.0,
+            allow_rotation: true,
+        }
+    }
+
+    pub fn heuristic(mut self, heuristic: Heuristic) -> Self {
+        self.heuristic = heuristic;
+        self
+    }
+
+    /// Minimum gap between shapes (e.g., the width of the cut)
+    pub fn spacing(mut self, spacing: f64) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    pub fn allow_rotation(mut self, allow_rotation: bool) -> Self {
+        self.allow_rotation = allow_rotation;
+        self
+    }
+
+    /// Place every shape. Larger shapes (by bounding box area) are placed
+    /// first.
+    pub fn pack(&self, shapes: &[KnownShape]) -> Packing {
+        match self.heuristic {
+            Heuristic::Shelf => self.pack_with::<ShelfSheet>(shapes),
+            Heuristic::Guillotine => self.pack_with::<GuillotineSheet>(shapes),
+            Heuristic::MaxRects => self.pack_with::<MaxRectsSheet>(shapes),
+        }
+    }
+
+    fn pack_with<S: Sheet>(&self, shapes: &[KnownShape]) -> Packing {
+        // Each box claims `spacing` extra to its right and above it. The sheet
+        // grows by the same amount so shapes may still touch the far edges.
+        let sheet_width = self.width + self.spacing;
+        let sheet_height = self.height + self.spacing;
+
+        let boxes: Vec<(f64, f64)> = shapes.iter().map(bounding_box).collect();
+
+        let mut order: Vec<usize> = (0..shapes.len()).collect();
+        order.sort_by(|&i, &j| {
+            let area = |(w, h): (f64, f64)| w * h;
+            area(boxes[j]).total_cmp(&area(boxes[i])).then(i.cmp(&j))
+        });
+
+        let mut sheets: Vec<S> = Vec::new();
+        let mut used_area: Vec<f64> = Vec::new();
+        let mut placements = Vec::new();
+        let mut unplaced = Vec::new();
+
+        for index in order {
+            let (w, h) = boxes[index];
+            let (w, h) = (w + self.spacing, h + self.spacing);
+
+            let existing = sheets.iter_mut()
+                .enumerate()
+                .find_map(|(n, sheet)| {
+                    sheet.insert(w, h, self.allow_rotation).map(|spot| (n, spot))
+                });
+
+            let found = existing.or_else(|| {
+                let mut sheet = S::new(sheet_width, sheet_height);
+                let spot = sheet.insert(w, h, self.allow_rotation)?;
+
+                sheets.push(sheet);
+                used_area.push(0.0);
+
+                Some((sheets.len() - 1, spot))
+            });
+
+            match found {
+                Some((sheet, spot)) => {
+                    let (width, height) = if spot.rotated {
+                        (boxes[index].1, boxes[index].0)
+                    }
+                    else {
+                        boxes[index]
+                    };
+
+                    used_area[sheet] += shapes[index].area();
+                    placements.push(Placement {
+                        index,
+                        sheet,
+                        x: spot.x,
+                        y: spot.y,
+                        width,
+                        height,
+                        rotated: spot.rotated,
+                    });
+                }
+                None => unplaced.push(index),
+            }
+        }
+
+        placements.sort_by_key(|p| p.index);
+        unplaced.sort();
+
+        Packing {
+            placements,
+            unplaced,
+            sheets: sheets.len(),
+            sheet_area: self.width * self.height,
+            used_area,
+        }
+    }
+}
+
+/// Lower-left corner of a placed box
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Spot {
+    x: f64,
+    y: f64,
+    rotated: bool,
+}
+
+/// An axis-aligned rectangle
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Rect {
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+}
+
+impl Rect {
+    fn fits(&self, w: f64, h: f64) -> bool {
+        w <= self.w + EPSILON && h <= self.h + EPSILON
+    }
+
+    fn is_empty(&self) -> bool {
+        self.w <= EPSILON || self.h <= EPSILON
+    }
+
+    fn right(&self) -> f64 {
+        self.x + self.w
+    }
+
+    fn top(&self) -> f64 {
+        self.y + self.h
+    }
+
+    fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.right() - EPSILON && other.x < self.right() - EPSILON
+            && self.y < other.top() - EPSILON && other.y < self.top() - EPSILON
+    }
+
+    fn contains(&self, other: &Rect) -> bool {
+        other.x >= self.x - EPSILON && other.y >= self.y - EPSILON
+            && other.right() <= self.right() + EPSILON
+            && other.top() <= self.top() + EPSILON
+    }
+}
+
+/// Orientations of a `w` x `h` box to try, as `(w, h, rotated)`
+fn orientations(w: f64, h: f64, allow_rotation: bool) -> Vec<(f64, f64, bool)> {
+    if allow_rotation && (w - h).abs() > EPSILON {
+        vec![(w, h, false), (h, w, true)]
+    }
+    else {
+        vec![(w, h, false)]
+    }
+}
+
+/// Free-space bookkeeping for one sheet
+trait Sheet {
+    fn new(width: f64, height: f64) -> Self;
+
+    /// Reserve space for a `w` x `h` box, if it fits
+    fn insert(&mut self, w: f64, h: f64, allow_rotation: bool) -> Option<Spot>;
+}
+
+struct Shelf {
+    y: f64,
+    height: f64,
+    used: f64,
+}
+
+struct ShelfSheet {
+    width: f64,
+    height: f64,
+    shelves: Vec<Shelf>,
+}
+
+impl Sheet for ShelfSheet {
+    fn new(width: f64, height: f64) -> Self {
+        ShelfSheet { width, height, shelves: Vec::new() }
+    }
+
+    fn insert(&mut self, w: f64, h: f64, allow_rotation: bool) -> Option<Spot> {
+        // Lay boxes flat (wider than tall) so shelves stay low
+        let mut options = orientations(w, h, allow_rotation);
+        options.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        for shelf in self.shelves.iter_mut() {
+            for &(w, h, rotated) in options.iter() {
+                if h <= shelf.height + EPSILON && shelf.used + w <= self.width + EPSILON {
+                    let spot = Spot { x: shelf.used, y: shelf.y, rotated };
+                    shelf.used += w;
+                    return Some(spot);
+                }
+            }
+        }
+
+        let top = self.shelves.last().map_or(0.0, |s| s.y + s.height);
+
+        for &(w, h, rotated) in options.iter() {
+            if top + h <= self.height + EPSILON && w <= self.width + EPSILON {
+                self.shelves.push(Shelf { y: top, height: h, used: w });
+                return Some(Spot { x: 0.0, y: top, rotated });
+            }
+        }
+
+        None
+    }
+}
+
+struct GuillotineSheet {
+    free: Vec<Rect>,
+}
+
+impl Sheet for GuillotineSheet {
+    fn new(width: f64, height: f64) -> Self {
+        GuillotineSheet { free: vec![Rect { x: 0.0, y: 0.0, w: width, h: height }] }
+    }
+
+    fn insert(&mut self, w: f64, h: f64, allow_rotation: bool) -> Option<Spot> {
+        let options = orientations(w, h, allow_rotation);
+
+        // Best area fit
+        let (i, w, h, rotated) = self.free.iter()
+            .enumerate()
+            .flat_map(|(i, r)| {
+                options.iter()
+                    .filter(move |(w, h, _)| r.fits(*w, *h))
+                    .map(move |&(w, h, rotated)| (i, w, h, rotated))
+            })
+            .min_by(|a, b| {
+                let waste = |&(i, w, h, _): &(usize, f64, f64, bool)| {
+                    self.free[i].w * self.free[i].h - w * h
+                };
+                waste(a).total_cmp(&waste(b))
+            })?;
+
+        let r = self.free.swap_remove(i);
+
+        // Split along the shorter leftover axis
+        let (right, top) = if r.w - w < r.h - h {
+            (Rect { x: r.x + w, y: r.y, w: r.w - w, h },
+             Rect { x: r.x, y: r.y + h, w: r.w, h: r.h - h })
+        }
+        else {
+            (Rect { x: r.x + w, y: r.y, w: r.w - w, h: r.h },
+             Rect { x: r.x, y: r.y + h, w, h: r.h - h })
+        };
+
+        self.free.extend([right, top].into_iter().filter(|r| !r.is_empty()));
+
+        Some(Spot { x: r.x, y: r.y, rotated })
+    }
+}
+
+struct MaxRectsSheet {
+    free: Vec<Rect>,
+}
+
+impl Sheet for MaxRectsSheet {
+    fn new(width: f64, height: f64) -> Self {
+        MaxRectsSheet { free: vec![Rect { x: 0.0, y: 0.0, w: width, h: height }] }
+    }
+
+    fn insert(&mut self, w: f64, h: f64, allow_rotation: bool) -> Option<Spot> {
+        let options = orientations(w, h, allow_rotation);
+
+        // Best short side fit (then best long side fit)
+        let (x, y, w, h, rotated) = self.free.iter()
+            .flat_map(|r| {
+                options.iter()
+                    .filter(move |(w, h, _)| r.fits(*w, *h))
+                    .map(move |&(w, h, rotated)| {
+                        let (dw, dh) = (r.w - w, r.h - h);
+                        ((dw.min(dh), dw.max(dh)), (r.x, r.y, w, h, rotated))
+                    })
+            })
+            .min_by(|(a, _), (b, _)| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)))
+            .map(|(_, found)| found)?;
+
+        let used = Rect { x, y, w, h };
+        let mut free = Vec::with_capacity(self.free.len() + 4);
+
+        for r in self.free.iter() {
+            if !r.intersects(&used) {
+                free.push(*r);
+                continue;
+            }
+
+            // Keep the parts of `r` to the left, right, below and above
+            let pieces = [
+                Rect { w: used.x - r.x, ..*r },
+                Rect { x: used.right(), w: r.right() - used.right(), ..*r },
+                Rect { h: used.y - r.y, ..*r },
+                Rect { y: used.top(), h: r.top() - used.top(), ..*r },
+            ];
+            free.extend(pieces.into_iter().filter(|p| !p.is_empty()));
+        }
+
+        // Drop rectangles inside another (keeping the first of equal ones)
+        self.free = free.iter()
+            .enumerate()
+            .filter(|&(i, r)| {
+                !free.iter().enumerate().any(|(j, other)| {
+                    j != i && other.contains(r) && (!r.contains(other) || j < i)
+                })
+            })
+            .map(|(_, r)| *r)
+            .collect();
+
+        Some(Spot { x, y, rotated })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hamcrest2::prelude::*;
+
+    use crate::circle::Circle;
+    use crate::equilateral_triangle::EquilateralTriangle;
+    use crate::right_triangle::RightTriangle;
+    use crate::square::Square;
+    use crate::testing::some_shapes;
+    use crate::triangle::Triangle;
+
+    const HEURISTICS: [Heuristic; 3] = [
+        Heuristic::Shelf,
+        Heuristic::Guillotine,
+        Heuristic::MaxRects,
+    ];
+
+    /// Every placement is on its sheet and no two placements overlap
+    fn assert_valid(packing: &Packing, width: f64, height: f64) {
+        for (n, a) in packing.placements.iter().enumerate() {
+            let r = Rect { x: a.x, y: a.y, w: a.width, h: a.height };
+            let sheet = Rect { x: 0.0, y: 0.0, w: width, h: height };
+
+            assert!(sheet.contains(&r), "{:?} is off the sheet", a);
+
+            for b in packing.placements[n + 1..].iter().filter(|b| b.sheet == a.sheet) {
+                let other = Rect { x: b.x, y: b.y, w: b.width, h: b.height };
+                assert!(!r.intersects(&other), "{:?} overlaps {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let (w, h) = bounding_box(&Triangle::with_sides(3.0, 4.0, 5.0).into());
+        assert_that!(w, close_to(5.0, 1e-9));
+        assert_that!(h, close_to(2.4, 1e-9));
+
+        let (w, h) = bounding_box(&EquilateralTriangle::with_side(2.0).into());
+        assert_that!(w, close_to(2.0, 1e-9));
+        assert_that!(h, close_to(3_f64.sqrt(), 1e-9));
+
+        assert_that!(bounding_box(&Circle::with_radius(1.0).into()),
+                     equal_to((2.0, 2.0)));
+        assert_that!(bounding_box(&RightTriangle::with_base_height(3.0, 4.0).into()),
+                     equal_to((3.0, 4.0)));
+    }
+
+    #[test]
+    fn test_all_heuristics() {
+        let shapes = some_shapes();
+
+        for heuristic in HEURISTICS {
+            let packing = SheetPacker::new(8.0, 6.0)
+                .heuristic(heuristic)
+                .pack(&shapes);
+
+            assert_that!(packing.placements.len(), is(equal_to(shapes.len())));
+            assert!(packing.unplaced.is_empty());
+            assert_valid(&packing, 8.0, 6.0);
+
+            let indices: Vec<usize> = packing.placements.iter().map(|p| p.index).collect();
+            assert_that!(indices, equal_to((0..shapes.len()).collect::<Vec<_>>()));
+
+            let total: f64 = shapes.iter().map(|s| s.area()).sum();
+            assert_that!(packing.utilization(),
+                         close_to(total / (48.0 * packing.sheets as f64), 1e-9));
+        }
+    }
+
+    #[test]
+    fn test_multiple_sheets() {
+        let shapes: Vec<KnownShape> = vec![Square::with_side(2.0).into(); 9];
+
+        for heuristic in HEURISTICS {
+            let packing = SheetPacker::new(4.0, 4.0)
+                .heuristic(heuristic)
+                .pack(&shapes);
+
+            assert_that!(packing.sheets, is(equal_to(3)));
+            assert_that!(packing.sheet_utilization(0), equal_to(Some(1.0)));
+            assert_that!(packing.sheet_utilization(2), equal_to(Some(0.25)));
+            assert_that!(packing.sheet_utilization(3), equal_to(None));
+            assert_valid(&packing, 4.0, 4.0);
+        }
+    }
+
+    #[test]
+    fn test_rotation() {
+        let shapes: Vec<KnownShape> = vec![
+            RightTriangle::with_base_height(2.0, 10.0).into(),
+        ];
+
+        let packing = SheetPacker::new(10.0, 2.0).pack(&shapes);
+        assert!(packing.placements[0].rotated);
+        assert_that!(packing.placements[0].width, is(equal_to(10.0)));
+
+        let packing = SheetPacker::new(10.0, 2.0)
+            .allow_rotation(false)
+            .pack(&shapes);
+        assert_that!(packing.unplaced.clone(), equal_to(vec![0]));
+        assert_that!(packing.sheets, is(equal_to(0)));
+        assert_that!(packing.utilization(), is(equal_to(0.0)));
+    }
+
+    #[test]
+    fn test_spacing() {
+        let shapes: Vec<KnownShape> = vec![Square::with_side(2.0).into(); 4];
+
+        // Four 2x2 squares fill a 4x4 sheet exactly, but not with a gap
+        let packing = SheetPacker::new(4.0, 4.0).spacing(0.5).pack(&shapes);
+        assert_that!(packing.sheets, is(equal_to(4)));
+
+        let packing = SheetPacker::new(4.5, 4.5).spacing(0.5).pack(&shapes);
+        assert_that!(packing.sheets, is(equal_to(1)));
+        assert_that!(packing.placements[1].x - packing.placements[0].x,
+                     close_to(2.5, 1e-9));
+    }
+}
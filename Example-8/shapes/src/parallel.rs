@@ -0,0 +1,354 @@
+//! Parallel parsing and statistics for large inputs.
+//!
+//! The input is split into line-aligned chunks, each chunk is parsed on the
+//! rayon (work-stealing) thread pool, and the per-chunk results are joined in
+//! input order. The shapes returned are identical, and in the same order, to
+//! those from [`factory::read_shapes_with`].
+
+use std::io::{BufRead, Read};
+use std::path::Path;
+
+use ordered_float::OrderedFloat;
+use rayon::prelude::*;
+
+use crate::collection::{self, Summary, SUM_CHUNK_SIZE};
+use crate::factory::{self, ShapeLines};
+use crate::known_shape::KnownShape;
+use crate::locale::Locale;
+
+/// Smallest chunk (in bytes) worth handing to another thread
+const MIN_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Parallel counterpart of [`factory::read_shapes_with`].
+///
+/// # Arguments
+///
+///  * `ins` - input source
+///
+pub fn read_shapes_with<R>(ins: R) -> Vec<KnownShape>
+    where R: Read {
+
+    read_shapes_with_locale(ins, &Locale::english())
+}
+
+/// Parallel counterpart of [`factory::read_shapes_with_locale`]. Lines that
+/// are not valid UTF-8 are skipped along with the other invalid lines, and
+/// reading stops at an error.
+///
+/// # Arguments
+///
+///  * `ins` - input source
+///  * `locale` - language of the shape names
+///
+pub fn read_shapes_with_locale<R>(mut ins: R, locale: &Locale) -> Vec<KnownShape>
+    where R: Read {
+
+    // Whatever was read before an error is kept
+    let mut bytes = Vec::new();
+    let _ = ins.read_to_end(&mut bytes);
+
+    parse_shapes(&String::from_utf8_lossy(&bytes), locale)
+        .into_iter()
+        .filter_map(Result::ok)
+        .collect()
+}
+
+/// Parallel counterpart of [`factory::read_shapes_file`].
+///
+/// # Arguments
+///
+///  * `path` - file to read
+///  * `locale` - language of the shape names
+///
+pub fn read_shapes_file<P>(path: P, locale: &Locale) -> Result<Vec<KnownShape>, String>
+    where P: AsRef<Path> {
+
+    parse_shapes(&factory::expand_includes(path)?, locale).into_iter().collect()
+}
+
+/// Parse every line of `text` in parallel, in order. Each shape is returned,
+/// as is an error (prefixed with its line number) for an unsupported version
+/// header or an `include` directive (see [`factory::parse_line`]). Other
+/// lines that are not valid shapes are skipped.
+///
+/// # Arguments
+///
+///  * `text` - complete input
+///  * `locale` - language of the shape names
+///
+pub fn parse_shapes(text: &str, locale: &Locale) -> Vec<Result<KnownShape, String>> {
+    let header = text.lines()
+        .next()
+        .and_then(factory::header_version)
+        .and_then(Result::err)
+        .map(|err| Err(format!("line 1: {}", err)));
+
+    let chunk_size = (text.len() / rayon::current_num_threads().max(1))
+        .max(MIN_CHUNK_SIZE);
+    let chunks = line_chunks(text, chunk_size);
+
+    // Number of the line before each chunk
+    let starts: Vec<usize> = chunks.iter()
+        .scan(0, |before, chunk| {
+            let start = *before;
+            *before += chunk.lines().count();
+            Some(start)
+        })
+        .collect();
+
+    let parsed: Vec<Vec<Result<KnownShape, String>>> = chunks.par_iter()
+        .zip(&starts)
+        .map(|(chunk, start)| {
+            chunk.lines()
+                .enumerate()
+                .filter_map(|(i, line)| {
+                    factory::parse_line_with(line, locale, false)
+                        .map_err(|err| format!("line {}: {}", start + i + 1, err))
+                        .transpose()
+                })
+                .collect()
+        })
+        .collect();
+
+    header.into_iter().chain(parsed.into_iter().flatten()).collect()
+}
+
+/// Parallel counterpart of a strict [`factory::ShapeReader`]. Every line of
+/// `lines` is read in order, then parsed in parallel. Each shape is returned,
+/// as is an error (prefixed with its file and line number) for every line
+/// that is not a valid shape or can not be followed.
+///
+/// # Arguments
+///
+///  * `lines` - input lines, with `include` directives followed
+///  * `locale` - language of the shape names
+///
+pub fn parse_lines<B>(mut lines: ShapeLines<B>, locale: &Locale)
+    -> Vec<Result<KnownShape, String>>
+    where B: BufRead {
+
+    let mut read = Vec::new();
+
+    while let Some(line) = lines.next() {
+        let (path, line_number) = lines.location();
+
+        read.push(line.map(|line| (line, path.map(Path::to_path_buf), line_number)));
+    }
+
+    read.into_par_iter()
+        .filter_map(|line| {
+            let (line, path, line_number) = match line {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err)),
+            };
+
+            factory::parse_line(&line, locale)
+                .map_err(|err| factory::located_at(path.as_deref(), line_number, err))
+                .transpose()
+        })
+        .collect()
+}
+
+/// Split `text` into chunks of roughly `chunk_size` bytes, each ending at a
+/// line break (or the end of `text`).
+pub fn line_chunks(text: &str, chunk_size: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        let mut end = chunk_size.max(1).min(rest.len());
+
+        // Never split a multi-byte character
+        while !rest.is_char_boundary(end) {
+            end += 1;
+        }
+
+        end = rest[end..].find('\n')
+            .map(|nl| end + nl + 1)
+            .unwrap_or(rest.len());
+
+        let (chunk, tail) = rest.split_at(end);
+        chunks.push(chunk);
+        rest = tail;
+    }
+
+    chunks
+}
+
+/// Parallel counterpart of [`ShapeCollection::summary`]. The result is
+/// identical, bit for bit, to the sequential version.
+///
+/// [`ShapeCollection::summary`]: crate::collection::ShapeCollection::summary
+pub fn summary<F>(shapes: &[KnownShape], metric: F) -> Option<Summary>
+    where F: Fn(&KnownShape) -> f64 + Sync {
+
+    let mut values: Vec<f64> = shapes.par_iter().map(&metric).collect();
+
+    if values.is_empty() {
+        return None;
+    }
+
+    // Same (stable) order as Summary::of, with NaN largest
+    values.par_sort_by_key(|v| OrderedFloat(*v));
+
+    let count = values.len();
+    let sum = chunked_sum(&values, |v| v);
+    let mean = sum / count as f64;
+    let variance = chunked_sum(&values, |v| (v - mean).powi(2)) / count as f64;
+
+    Some(Summary {
+        count,
+        sum,
+        min: values[0],
+        max: values[count - 1],
+        mean,
+        median: collection::percentile_of_sorted(&values, 50.0),
+        variance,
+    })
+}
+
+/// Sum `term` over `values` in the same chunks, and the same order, as
+/// `Summary::of`
+fn chunked_sum<F>(values: &[f64], term: F) -> f64
+    where F: Fn(f64) -> f64 + Sync {
+
+    values.par_chunks(SUM_CHUNK_SIZE)
+        .map(|chunk| chunk.iter().map(|&v| term(v)).sum::<f64>())
+        .collect::<Vec<_>>()
+        .into_iter()
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hamcrest2::prelude::*;
+
+    use std::io::BufReader;
+
+    use crate::circle::Circle;
+    use crate::collection::ShapeCollection;
+    use crate::shape::Shape;
+
+    fn some_input(copies: usize) -> String {
+        let lines = [
+            "Triangle; 3 4 5",
+            "Circle; 5",
+            "",
+            "Not a Shape; 1 2",
+            "Right Triangle; base=4 height=5",
+            "Square; 9\r",
+            "Equilateral Triangle; 3",
+        ];
+
+        (0..copies)
+            .flat_map(|_| lines.iter())
+            .map(|line| format!("{}\n", line))
+            .collect()
+    }
+
+    #[test]
+    fn test_line_chunks() {
+        let text = "a\nbb\nccc\ndddd";
+
+        assert_that!(line_chunks(text, 1), equal_to(vec!["a\n", "bb\n", "ccc\n", "dddd"]));
+        assert_that!(line_chunks(text, 4), equal_to(vec!["a\nbb\n", "ccc\ndddd"]));
+        assert_that!(line_chunks(text, 100), equal_to(vec![text]));
+        assert_that!(line_chunks("", 4).len(), is(equal_to(0)));
+        assert_that!(line_chunks("ab\u{e9}cd\nx", 3), equal_to(vec!["ab\u{e9}cd\n", "x"]));
+        assert_that!(line_chunks(text, 1).concat(), equal_to(text));
+    }
+
+    #[test]
+    fn test_same_as_sequential() {
+        let text = some_input(20_000);
+
+        let sequential = factory::read_shapes_with(BufReader::new(text.as_bytes()));
+        let parallel = read_shapes_with(text.as_bytes());
+
+        assert_that!(parallel.len(), is(equal_to(sequential.len())));
+        assert_that!(parallel.len(), is(equal_to(5 * 20_000)));
+
+        for (par, seq) in parallel.iter().zip(sequential.iter()) {
+            assert_that!(par.to_string(), equal_to(seq.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_directive_errors() {
+        let locale = Locale::english();
+
+        // Line numbers count every chunk before the error, and the lines
+        // after it are still read
+        let text = some_input(20_000) + "include \"other.txt\"\nSquare; 1\n";
+        let results = parse_shapes(&text, &locale);
+        let errors: Vec<String> = results.iter().filter_map(|r| r.clone().err()).collect();
+
+        assert_that!(errors, equal_to(vec![
+            format!("line {}: 'include' can only be used in files", 7 * 20_000 + 1),
+        ]));
+        assert!(results.last().unwrap().is_ok());
+
+        let results = parse_shapes("#!shapes v3\nSquare; 1\n", &locale);
+        assert_that!(results[0].clone().unwrap_err(),
+                     equal_to("line 1: unsupported format version 3 (newest is 2)".to_string()));
+        assert_that!(results.len(), is(equal_to(2)));
+
+        // The readers skip them
+        assert_that!(read_shapes_with("include \"a.txt\"\nSquare; 1\n".as_bytes()).len(),
+                     is(equal_to(1)));
+    }
+
+    #[test]
+    fn test_summary() {
+        let shapes: ShapeCollection =
+            read_shapes_with(some_input(1000).as_bytes()).into();
+
+        let par = summary(shapes.as_slice(), Shape::area).unwrap();
+        let seq = shapes.summary(Shape::area).unwrap();
+
+        assert_that!(par.count, is(equal_to(seq.count)));
+        assert_that!(par.min, is(equal_to(seq.min)));
+        assert_that!(par.max, is(equal_to(seq.max)));
+        assert_that!(par.median, is(equal_to(seq.median)));
+        assert_that!(par.sum, is(equal_to(seq.sum)));
+        assert_that!(par.mean, is(equal_to(seq.mean)));
+        assert_that!(par.variance, is(equal_to(seq.variance)));
+
+        assert!(summary(&[], Shape::area).is_none());
+    }
+
+    #[test]
+    fn test_summary_exact_sum() {
+        // Enough values for many chunks, with sizes spread over several
+        // orders of magnitude so that the order of addition matters
+        let shapes: ShapeCollection = (1..100_000)
+            .map(|i| Circle::with_radius((i % 997) as f64 * 1.37 + 1e-3 * i as f64).into())
+            .collect();
+
+        for metric in [Shape::area as fn(&KnownShape) -> f64, Shape::perimeter] {
+            let par = summary(shapes.as_slice(), metric).unwrap();
+            let seq = shapes.summary(metric).unwrap();
+
+            assert_that!(par.sum.to_bits(), is(equal_to(seq.sum.to_bits())));
+            assert_that!(par.mean.to_bits(), is(equal_to(seq.mean.to_bits())));
+            assert_that!(par.variance.to_bits(), is(equal_to(seq.variance.to_bits())));
+        }
+    }
+
+    #[test]
+    fn test_summary_nan() {
+        // Sides that can not form a triangle give a NaN area
+        let shapes: ShapeCollection =
+            read_shapes_with("Triangle; 1 2 5\nSquare; 2\nCircle; 1\n".as_bytes()).into();
+
+        let par = summary(shapes.as_slice(), Shape::area).unwrap();
+        let seq = shapes.summary(Shape::area).unwrap();
+
+        assert!(seq.max.is_nan());
+        assert!(par.max.is_nan());
+        assert_that!(par.min, is(equal_to(seq.min)));
+        assert_that!(par.min, is(equal_to(std::f64::consts::PI)));
+        assert!(par.sum.is_nan() && seq.sum.is_nan());
+    }
+}
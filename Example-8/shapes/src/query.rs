@@ -0,0 +1,507 @@
+//! A small expression language for selecting shapes, e.g.,
+//!
+//! ```text
+//! name = "Circle" and area > 10
+//! name contains "Triangle" and perimeter between 5 and 20
+//! not (radius < 1 or side >= 4)
+//! ```
+//!
+//! # Grammar
+//!
+//! ```text
+//! expr       := and_expr ("or" and_expr)*
+//! and_expr   := not_expr ("and" not_expr)*
+//! not_expr   := "not" not_expr | "(" expr ")" | comparison
+//! comparison := operand ("=" | "!=" | "<" | "<=" | ">" | ">=" | "contains")
+//!               operand
+//!             | operand "between" operand "and" operand
+//! operand    := identifier | number | "string"
+//! ```
+//!
+//! Identifiers are `name`, `area`, `perimeter` and the field labels shown by
+//! `Display` written as keys (e.g., `radius`, `side_a`, `hypotenuse`).
+//! Keywords are case-insensitive. A comparison that refers to a field the
+//! shape does not have (e.g., `radius` for a Square) is false.
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::formatter::to_key;
+use crate::known_shape::KnownShape;
+use crate::shape::Shape;
+
+/// Why a query could not be parsed or type-checked
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryError {
+    /// Byte offset in the query text
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (at position {})", self.message, self.position)
+    }
+}
+
+impl Error for QueryError {}
+
+/// A parsed and type-checked query
+#[derive(Clone, Debug, PartialEq)]
+pub struct Query {
+    expr: Expr,
+}
+
+impl Query {
+    /// Parse and type-check a query
+    pub fn parse(text: &str) -> Result<Query, QueryError> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser { tokens, pos: 0, end: text.len() };
+
+        let expr = parser.expr()?;
+
+        if let Some(tok) = parser.peek() {
+            return Err(parser.error_at(tok.position, "unexpected input"));
+        }
+
+        Ok(Query { expr })
+    }
+
+    /// Check whether a shape satisfies the query
+    pub fn matches(&self, shape: &KnownShape) -> bool {
+        self.expr.eval(shape)
+    }
+}
+
+impl FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Query::parse(text)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Type {
+    Number,
+    Text,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Operand {
+    Field(String),
+    Number(f64),
+    Text(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Operand, CmpOp, Operand),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Number(f64),
+    Text(String),
+}
+
+impl Operand {
+    fn value(&self, shape: &KnownShape) -> Option<Value> {
+        match self {
+            Operand::Number(n) => Some(Value::Number(*n)),
+            Operand::Text(s) => Some(Value::Text(s.clone())),
+            Operand::Field(field) => match field.as_str() {
+                "name" => Some(Value::Text(shape.name().to_string())),
+                "area" => Some(Value::Number(shape.area())),
+                "perimeter" => Some(Value::Number(shape.perimeter())),
+                _ => shape.fields().into_iter()
+                    .find(|(label, _)| to_key(label) == *field)
+                    .map(|(_, value)| Value::Number(value)),
+            },
+        }
+    }
+}
+
+impl Expr {
+    fn eval(&self, shape: &KnownShape) -> bool {
+        match self {
+            Expr::Or(lhs, rhs) => lhs.eval(shape) || rhs.eval(shape),
+            Expr::And(lhs, rhs) => lhs.eval(shape) && rhs.eval(shape),
+            Expr::Not(inner) => !inner.eval(shape),
+            Expr::Compare(lhs, op, rhs) => {
+                match (lhs.value(shape), rhs.value(shape)) {
+                    (Some(Value::Number(l)), Some(Value::Number(r))) => {
+                        compare(*op, l.partial_cmp(&r))
+                    }
+                    (Some(Value::Text(l)), Some(Value::Text(r))) => {
+                        match op {
+                            CmpOp::Contains => l.contains(&r),
+                            _ => compare(*op, Some(l.cmp(&r))),
+                        }
+                    }
+                    _ => false,
+                }
+            }
+        }
+    }
+}
+
+fn compare(op: CmpOp, ordering: Option<std::cmp::Ordering>) -> bool {
+    use std::cmp::Ordering::*;
+
+    matches!((op, ordering),
+             (CmpOp::Eq, Some(Equal))
+             | (CmpOp::Ne, Some(Less | Greater))
+             | (CmpOp::Lt, Some(Less))
+             | (CmpOp::Le, Some(Less | Equal))
+             | (CmpOp::Gt, Some(Greater))
+             | (CmpOp::Ge, Some(Greater | Equal)))
+}
+
+/// Every identifier a query may use, with its type
+fn field_type(field: &str) -> Option<Type> {
+    match field {
+        "name" => Some(Type::Text),
+        "area" | "perimeter" => Some(Type::Number),
+        _ if is_shape_field(field) => Some(Type::Number),
+        _ => None,
+    }
+}
+
+/// Is `field` a displayed field of some shape, written as a key (e.g.,
+/// `radius` or `side_a`)
+pub(crate) fn is_shape_field(field: &str) -> bool {
+    KnownShape::KNOWN_NAMES.iter()
+        .filter_map(|name| KnownShape::from_name(name))
+        .flat_map(|s| s.fields())
+        .any(|(label, _)| to_key(label) == field)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum TokenKind {
+    Ident(String),
+    Number(f64),
+    Text(String),
+    Op(CmpOp),
+    LParen,
+    RParen,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Token {
+    kind: TokenKind,
+    position: usize,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+
+    while let Some(&(position, c)) = chars.peek() {
+        let error = |message: &str| QueryError {
+            position,
+            message: message.to_string(),
+        };
+
+        let kind = if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        else if c == '(' || c == ')' {
+            chars.next();
+            if c == '(' { TokenKind::LParen } else { TokenKind::RParen }
+        }
+        else if c == '"' {
+            chars.next();
+            let mut literal = String::new();
+
+            loop {
+                match chars.next() {
+                    Some((_, '"')) => break,
+                    Some((_, ch)) => literal.push(ch),
+                    None => return Err(error("unterminated string")),
+                }
+            }
+
+            TokenKind::Text(literal)
+        }
+        else if "=!<>".contains(c) {
+            chars.next();
+            let followed_by_eq = chars.next_if(|&(_, ch)| ch == '=').is_some();
+
+            TokenKind::Op(match (c, followed_by_eq) {
+                ('=', _) => CmpOp::Eq,
+                ('!', true) => CmpOp::Ne,
+                ('<', false) => CmpOp::Lt,
+                ('<', true) => CmpOp::Le,
+                ('>', false) => CmpOp::Gt,
+                ('>', true) => CmpOp::Ge,
+                _ => return Err(error("expected '!='")),
+            })
+        }
+        else if c.is_ascii_digit() || c == '.' || c == '-' {
+            let mut literal = String::new();
+
+            while let Some((_, ch)) = chars.next_if(|&(_, ch)| {
+                ch.is_ascii_alphanumeric() || ch == '.' || ch == '-'
+                    || ch == '+'
+            }) {
+                literal.push(ch);
+            }
+
+            TokenKind::Number(literal.parse()
+                .map_err(|_| error("invalid number"))?)
+        }
+        else if c.is_alphabetic() || c == '_' {
+            let mut word = String::new();
+
+            while let Some((_, ch)) = chars.next_if(|&(_, ch)| {
+                ch.is_alphanumeric() || ch == '_'
+            }) {
+                word.push(ch);
+            }
+
+            let word = word.to_lowercase();
+            if word == "contains" {
+                TokenKind::Op(CmpOp::Contains)
+            }
+            else {
+                TokenKind::Ident(word)
+            }
+        }
+        else {
+            return Err(error(&format!("unexpected character '{}'", c)));
+        };
+
+        tokens.push(Token { kind, position });
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let tok = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        tok
+    }
+
+    fn position(&self) -> usize {
+        self.peek().map(|t| t.position).unwrap_or(self.end)
+    }
+
+    fn error_at(&self, position: usize, message: &str) -> QueryError {
+        QueryError { position, message: message.to_string() }
+    }
+
+    /// Consume the keyword `word` if it is next
+    fn keyword(&mut self, word: &str) -> bool {
+        match self.peek() {
+            Some(Token { kind: TokenKind::Ident(w), .. }) if w == word => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, QueryError> {
+        let mut lhs = self.and_expr()?;
+
+        while self.keyword("or") {
+            lhs = Expr::Or(Box::new(lhs), Box::new(self.and_expr()?));
+        }
+
+        Ok(lhs)
+    }
+
+    fn and_expr(&mut self) -> Result<Expr, QueryError> {
+        let mut lhs = self.not_expr()?;
+
+        while self.keyword("and") {
+            lhs = Expr::And(Box::new(lhs), Box::new(self.not_expr()?));
+        }
+
+        Ok(lhs)
+    }
+
+    fn not_expr(&mut self) -> Result<Expr, QueryError> {
+        if self.keyword("not") {
+            return Ok(Expr::Not(Box::new(self.not_expr()?)));
+        }
+
+        if let Some(Token { kind: TokenKind::LParen, .. }) = self.peek() {
+            self.pos += 1;
+            let inner = self.expr()?;
+
+            return match self.next() {
+                Some(Token { kind: TokenKind::RParen, .. }) => Ok(inner),
+                _ => Err(self.error_at(self.position(), "expected ')'")),
+            };
+        }
+
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, QueryError> {
+        let lhs_pos = self.position();
+        let (lhs, lhs_type) = self.operand()?;
+
+        if self.keyword("between") {
+            let (low, low_type) = self.operand()?;
+
+            if !self.keyword("and") {
+                return Err(self.error_at(self.position(), "expected 'and'"));
+            }
+
+            let (high, high_type) = self.operand()?;
+
+            if lhs_type != Type::Number
+                || low_type != Type::Number
+                || high_type != Type::Number {
+                return Err(self.error_at(lhs_pos,
+                                         "'between' requires numbers"));
+            }
+
+            return Ok(Expr::And(
+                Box::new(Expr::Compare(lhs.clone(), CmpOp::Ge, low)),
+                Box::new(Expr::Compare(lhs, CmpOp::Le, high)),
+            ));
+        }
+
+        let op = match self.next() {
+            Some(Token { kind: TokenKind::Op(op), .. }) => op,
+            _ => {
+                self.pos -= 1;
+                return Err(self.error_at(self.position(),
+                                         "expected a comparison"));
+            }
+        };
+
+        let (rhs, rhs_type) = self.operand()?;
+
+        if lhs_type != rhs_type {
+            return Err(self.error_at(lhs_pos,
+                                     "can not compare text with a number"));
+        }
+
+        match (op, lhs_type) {
+            (CmpOp::Contains, Type::Number) => {
+                Err(self.error_at(lhs_pos, "'contains' requires text"))
+            }
+            (CmpOp::Lt | CmpOp::Le | CmpOp::Gt | CmpOp::Ge, Type::Text) => {
+                Err(self.error_at(lhs_pos, "text can only be compared with \
+                                            '=', '!=' or 'contains'"))
+            }
+            _ => Ok(Expr::Compare(lhs, op, rhs)),
+        }
+    }
+
+    fn operand(&mut self) -> Result<(Operand, Type), QueryError> {
+        let position = self.position();
+
+        match self.next().map(|t| t.kind) {
+            Some(TokenKind::Number(n)) => Ok((Operand::Number(n), Type::Number)),
+            Some(TokenKind::Text(s)) => Ok((Operand::Text(s), Type::Text)),
+            Some(TokenKind::Ident(field)) => {
+                match field_type(&field) {
+                    Some(field_type) => Ok((Operand::Field(field), field_type)),
+                    None => Err(self.error_at(
+                        position, &format!("unknown field '{}'", field))),
+                }
+            }
+            _ => Err(self.error_at(position, "expected a field or value")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hamcrest2::prelude::*;
+
+    use crate::circle::Circle;
+    use crate::testing::some_shapes;
+
+    fn count(query: &str) -> usize {
+        let query = Query::parse(query).unwrap();
+        some_shapes().iter().filter(|s| query.matches(s)).count()
+    }
+
+    #[test]
+    fn test_comparisons() {
+        assert_that!(count(r#"name = "Circle""#), is(equal_to(2)));
+        assert_that!(count(r#"name != "Circle""#), is(equal_to(5)));
+        assert_that!(count(r#"name = "Circle" and area > 10"#),
+                     is(equal_to(1)));
+        assert_that!(count("area >= 12.5663706"), is(equal_to(2)));
+        assert_that!(count("perimeter between 5 and 13"), is(equal_to(5)));
+        assert_that!(count(r#"name contains "Triangle""#), is(equal_to(3)));
+        assert_that!(count("3 < area"), is(equal_to(6)));
+    }
+
+    #[test]
+    fn test_fields() {
+        assert_that!(count("radius > 1"), is(equal_to(1)));
+        assert_that!(count("hypotenuse = 5"), is(equal_to(1)));
+        assert_that!(count("not radius > 1"), is(equal_to(6)));
+    }
+
+    #[test]
+    fn test_precedence() {
+        // and binds tighter than or
+        assert_that!(count(r#"name = "Square" or name = "Circle" and radius = 2"#),
+                     is(equal_to(3)));
+        assert_that!(count(r#"(name = "Square" or name = "Circle") and area > 4"#),
+                     is(equal_to(2)));
+        assert_that!(count(r#"NOT (name = "Circle") AND side < 5"#),
+                     is(equal_to(3)));
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = Query::parse("volume > 3").unwrap_err();
+        assert_that!(err.position, is(equal_to(0)));
+        assert!(err.message.contains("volume"));
+
+        assert!(Query::parse(r#"name > "A""#).is_err());
+        assert!(Query::parse("area = \"big\"").is_err());
+        assert!(Query::parse("area contains 3").is_err());
+        assert!(Query::parse("area >").is_err());
+        assert!(Query::parse("(area > 3").is_err());
+        assert!(Query::parse("area > 3 area").is_err());
+        assert!(Query::parse("name = \"Circle").is_err());
+        assert!(Query::parse("area # 3").is_err());
+    }
+
+    #[test]
+    fn test_from_str() {
+        let query: Query = "area < 5".parse().unwrap();
+        assert!(query.matches(&Circle::new().into()));
+    }
+}
@@ -0,0 +1,358 @@
+//! Draw shapes in the terminal.
+//!
+//! A shape is rasterized from its polygon (see [`geometry::polygon`]) by
+//! testing the center of every "dot" against it. Each character cell holds
+//! one or more dots, depending on the mode:
+//!
+//!   * Ascii - 1 dot per cell (`#`)
+//!   * Block - 1 x 2 dots per cell (`▀`, `▄` and `█`)
+//!   * Braille - 2 x 4 dots per cell (U+2800 to U+28FF)
+//!
+//! Terminal cells are taller than they are wide, so the number of rows is
+//! scaled by the cell ratio (height / width) to keep the shape's proportions.
+//! A shape is scaled down until it fits in both the width and the height
+//! limit, so a long, thin shape never fills the terminal.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::geometry::{self, Point};
+use crate::known_shape::KnownShape;
+
+/// Bit of each braille dot, indexed by `[row][column]` within a cell
+const BRAILLE_DOTS: [[u32; 2]; 4] = [
+    [0x01, 0x08],
+    [0x02, 0x10],
+    [0x04, 0x20],
+    [0x40, 0x80],
+];
+
+/// Characters used to draw
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderMode {
+    Ascii,
+    Block,
+    Braille,
+}
+
+impl RenderMode {
+    /// Dots per character cell, across and down
+    fn dots(&self) -> (usize, usize) {
+        match self {
+            RenderMode::Ascii => (1, 1),
+            RenderMode::Block => (1, 2),
+            RenderMode::Braille => (2, 4),
+        }
+    }
+
+    /// The character for one cell. `filled` is indexed by `[row][column]`
+    /// within the cell.
+    fn cell(&self, filled: &[[bool; 2]; 4]) -> char {
+        match self {
+            RenderMode::Ascii => if filled[0][0] { '#' } else { ' ' },
+            RenderMode::Block => match (filled[0][0], filled[1][0]) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            },
+            RenderMode::Braille => {
+                let mut bits = 0;
+
+                for (row, dots) in BRAILLE_DOTS.iter().enumerate() {
+                    for (col, dot) in dots.iter().enumerate() {
+                        if filled[row][col] {
+                            bits |= dot;
+                        }
+                    }
+                }
+
+                // An empty braille cell is drawn as a space so that lines
+                // can be trimmed
+                if bits == 0 { ' ' } else { char::from_u32(0x2800 + bits).unwrap() }
+            }
+        }
+    }
+}
+
+impl FromStr for RenderMode {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_lowercase().as_str() {
+            "ascii" => Ok(RenderMode::Ascii),
+            "block" => Ok(RenderMode::Block),
+            "braille" => Ok(RenderMode::Braille),
+            _ => Err(format!("Unknown drawing mode '{}'", name)),
+        }
+    }
+}
+
+impl fmt::Display for RenderMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            RenderMode::Ascii => "ascii",
+            RenderMode::Block => "block",
+            RenderMode::Braille => "braille",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// Configurable terminal drawing for shapes.
+///
+/// # Example
+///
+/// ```
+/// use shapes::render::Renderer;
+/// use shapes::square::Square;
+///
+/// let drawing = Renderer::new()
+///     .width(4)
+///     .render(&Square::with_side(1.0).into())
+///     .unwrap();
+///
+/// assert_eq!(drawing, "####\n####\n");
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Renderer {
+    mode: RenderMode,
+    width: usize,
+    height: usize,
+    cell_ratio: f64,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Renderer::new()
+    }
+}
+
+impl Renderer {
+    /// Draw ASCII, at most 40 columns by 40 rows, in cells twice as tall as
+    /// they are wide
+    pub fn new() -> Self {
+        Renderer {
+            mode: RenderMode::Ascii,
+            width: 40,
+            height: 40,
+            cell_ratio: 2.0,
+        }
+    }
+
+    /// Set the characters used to draw
+    pub fn mode(mut self, mode: RenderMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Set the largest number of columns a drawing may span
+    pub fn width(mut self, columns: usize) -> Self {
+        self.width = columns.max(1);
+        self
+    }
+
+    /// Set the largest number of rows a drawing may span
+    pub fn height(mut self, rows: usize) -> Self {
+        self.height = rows.max(1);
+        self
+    }
+
+    /// Set the height of a character cell divided by its width
+    pub fn cell_ratio(mut self, ratio: f64) -> Self {
+        self.cell_ratio = ratio;
+        self
+    }
+
+    /// Draw `shape`, one line per row (trailing spaces removed). Return
+    /// `None` if the shape can not be drawn--e.g., a triangle with impossible
+    /// sides or a shape with no area.
+    pub fn render(&self, shape: &KnownShape) -> Option<String> {
+        let (min, max) = geometry::bounds(&geometry::polygon(shape, f64::INFINITY)?);
+        let (width, height) = (max.0 - min.0, max.1 - min.1);
+
+        let usable = |length: f64| length.is_finite() && length > 0.0;
+        if !(usable(width) && usable(height) && usable(self.cell_ratio)) {
+            return None;
+        }
+
+        let (dots_across, dots_down) = self.mode.dots();
+
+        // The smallest cells that fit the shape in both limits
+        let cell_width = (width / self.width as f64)
+            .max(height / (self.height as f64 * self.cell_ratio));
+        let cell_height = cell_width * self.cell_ratio;
+
+        // Allow for rounding, so that an exact fit does not get an extra
+        // row or column
+        let cells = |length: f64, size: f64| {
+            ((length / size * (1.0 - 1e-9)).ceil() as usize).max(1)
+        };
+        let columns = cells(width, cell_width);
+        let rows = cells(height, cell_height);
+
+        let dot_width = cell_width / dots_across as f64;
+        let dot_height = cell_height / dots_down as f64;
+
+        // Fine enough that circles look round at any size
+        let polygon = geometry::polygon(shape, dot_width.min(dot_height) / 4.0)?;
+
+        let mut drawing = String::new();
+
+        for row in 0..rows {
+            let mut line = String::new();
+
+            for col in 0..columns {
+                let mut filled = [[false; 2]; 4];
+
+                for (j, dots) in filled.iter_mut().enumerate().take(dots_down) {
+                    for (i, dot) in dots.iter_mut().enumerate().take(dots_across) {
+                        let x = min.0 + ((col * dots_across + i) as f64 + 0.5) * dot_width;
+                        let y = max.1 - ((row * dots_down + j) as f64 + 0.5) * dot_height;
+
+                        *dot = encloses(&polygon, (x, y));
+                    }
+                }
+
+                line.push(self.mode.cell(&filled));
+            }
+
+            drawing.push_str(line.trim_end());
+            drawing.push('\n');
+        }
+
+        Some(drawing)
+    }
+}
+
+/// Even-odd test: does a ray from `point` cross the polygon's edges an odd
+/// number of times?
+fn encloses(polygon: &[Point], (x, y): Point) -> bool {
+    let mut inside = false;
+
+    for (i, &(xi, yi)) in polygon.iter().enumerate() {
+        let (xj, yj) = polygon[(i + polygon.len() - 1) % polygon.len()];
+
+        if (yi > y) != (yj > y) && x < xi + (y - yi) * (xj - xi) / (yj - yi) {
+            inside = !inside;
+        }
+    }
+
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hamcrest2::prelude::*;
+
+    use crate::circle::Circle;
+    use crate::right_triangle::RightTriangle;
+    use crate::square::Square;
+    use crate::triangle::Triangle;
+
+    fn lines(drawing: &str) -> Vec<&str> {
+        drawing.lines().collect()
+    }
+
+    #[test]
+    fn test_square() {
+        let square: KnownShape = Square::with_side(3.0).into();
+
+        let drawing = Renderer::new().width(10).render(&square).unwrap();
+        assert_that!(lines(&drawing), equal_to(vec!["##########"; 5]));
+
+        // Square cells need as many rows as columns
+        let drawing = Renderer::new().width(6).cell_ratio(1.0).render(&square).unwrap();
+        assert_that!(lines(&drawing).len(), is(equal_to(6)));
+
+        let drawing = Renderer::new().width(4).mode(RenderMode::Block)
+            .render(&square).unwrap();
+        assert_that!(drawing, equal_to("████\n████\n".to_string()));
+
+        let drawing = Renderer::new().width(3).mode(RenderMode::Braille)
+            .render(&square).unwrap();
+        assert_that!(drawing, equal_to("⣿⣿⣿\n⠛⠛⠛\n".to_string()));
+    }
+
+    #[test]
+    fn test_right_triangle() {
+        // Right angle at the lower left, so rows widen toward the bottom
+        let triangle: KnownShape = RightTriangle::with_base_height(4.0, 2.0).into();
+        let drawing = Renderer::new().width(8).render(&triangle).unwrap();
+        let widths: Vec<usize> = lines(&drawing).iter().map(|l| l.len()).collect();
+
+        assert_that!(widths.len(), is(equal_to(2)));
+        assert!(widths[0] < widths[1]);
+        assert!(lines(&drawing).iter().all(|l| l.starts_with('#')));
+    }
+
+    #[test]
+    fn test_tall_shape() {
+        // 100 times taller than wide, so the height limit sets the scale
+        let triangle: KnownShape = RightTriangle::with_base_height(1.0, 100.0).into();
+        let drawing = Renderer::new().render(&triangle).unwrap();
+
+        assert_that!(lines(&drawing).len(), is(equal_to(40)));
+        assert!(lines(&drawing).iter().all(|l| l.len() <= 1));
+        assert_that!(lines(&drawing)[39], equal_to("#"));
+
+        let drawing = Renderer::new().width(10).height(5).render(&triangle).unwrap();
+        assert_that!(lines(&drawing).len(), is(equal_to(5)));
+
+        // A wide shape is still limited by the width
+        let square: KnownShape = Square::with_side(1.0).into();
+        let drawing = Renderer::new().width(8).height(100).render(&square).unwrap();
+        assert_that!(lines(&drawing), equal_to(vec!["########"; 4]));
+    }
+
+    #[test]
+    fn test_circle() {
+        let circle: KnownShape = Circle::with_radius(5.0).into();
+        let drawing = Renderer::new().width(20).render(&circle).unwrap();
+        let rows = lines(&drawing);
+
+        assert_that!(rows.len(), is(equal_to(10)));
+
+        // Symmetric top to bottom, and widest in the middle
+        for (top, bottom) in rows.iter().zip(rows.iter().rev()) {
+            assert_that!(top.trim(), equal_to(bottom.trim()));
+        }
+        assert!(rows[0].trim().len() < rows[4].trim().len());
+        assert_that!(rows[4].to_string(), equal_to("#".repeat(20)));
+
+        let braille = Renderer::new().width(10).mode(RenderMode::Braille)
+            .render(&circle).unwrap();
+        assert!(braille.chars().all(|c| c == ' ' || c == '\n'
+                                    || ('\u{2800}'..='\u{28ff}').contains(&c)));
+    }
+
+    #[test]
+    fn test_not_drawable() {
+        let renderer = Renderer::new();
+
+        assert!(renderer.render(&Triangle::with_sides(1.0, 1.0, 5.0).into()).is_none());
+        assert!(renderer.render(&Square::with_side(0.0).into()).is_none());
+        assert!(renderer.clone().cell_ratio(0.0)
+            .render(&Square::with_side(1.0).into()).is_none());
+    }
+
+    #[test]
+    fn test_mode_names() {
+        for mode in [RenderMode::Ascii, RenderMode::Block, RenderMode::Braille] {
+            assert_that!(mode.to_string().parse(), equal_to(Ok(mode)));
+        }
+
+        assert!("sixel".parse::<RenderMode>().is_err());
+    }
+
+    #[test]
+    fn test_encloses() {
+        let square = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+
+        assert!(encloses(&square, (0.5, 0.5)));
+        assert!(!encloses(&square, (1.5, 0.5)));
+        assert!(!encloses(&square, (0.5, -0.1)));
+    }
+}
@@ -0,0 +1,494 @@
+//! Report writers.
+//!
+//! Each report contains a table of shapes (name, dimensions, area and
+//! perimeter) followed by a summary of the area and perimeter statistics and
+//! a count of each kind of shape. Reports on shapes from several inputs end
+//! with the totals of each input.
+
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use crate::collection::{ShapeCollection, Summary};
+use crate::known_shape::KnownShape;
+use crate::shape::Shape;
+
+/// Supported report formats
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportFormat {
+    Csv,
+    Markdown,
+
+    /// A self-contained HTML page (no external style sheets or scripts)
+    Html,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_lowercase().as_str() {
+            "csv" => Ok(ReportFormat::Csv),
+            "markdown" | "md" => Ok(ReportFormat::Markdown),
+            "html" => Ok(ReportFormat::Html),
+            _ => Err(format!("Unknown report format '{}'", name)),
+        }
+    }
+}
+
+impl fmt::Display for ReportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ReportFormat::Csv => "csv",
+            ReportFormat::Markdown => "markdown",
+            ReportFormat::Html => "html",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// Write a report about `shapes`.
+///
+/// # Arguments
+///
+///  * `out` - output destination
+///  * `shapes` - shapes to report on
+///  * `format` - report format
+///
+pub fn write_report<W: Write>(out: &mut W, shapes: &ShapeCollection,
+                              format: ReportFormat) -> io::Result<()> {
+    write_report_with_inputs(out, shapes, &[], format)
+}
+
+/// Totals for the shapes read from one input (e.g., a file)
+#[derive(Clone, Debug, PartialEq)]
+pub struct InputTotals {
+    pub name: String,
+    pub count: usize,
+    pub area: f64,
+    pub perimeter: f64,
+}
+
+impl InputTotals {
+    /// Total the shapes read from the input called `name`
+    pub fn new(name: &str, shapes: &ShapeCollection) -> Self {
+        InputTotals {
+            name: name.to_string(),
+            count: shapes.len(),
+            area: shapes.sum(Shape::area),
+            perimeter: shapes.sum(Shape::perimeter),
+        }
+    }
+}
+
+/// Write a report about `shapes`, combined from several inputs, followed by
+/// the totals of each input (omitted if `inputs` is empty).
+///
+/// # Arguments
+///
+///  * `out` - output destination
+///  * `shapes` - shapes to report on (from every input)
+///  * `inputs` - totals of each input, in order
+///  * `format` - report format
+///
+pub fn write_report_with_inputs<W: Write>(out: &mut W, shapes: &ShapeCollection,
+                                          inputs: &[InputTotals],
+                                          format: ReportFormat)
+    -> io::Result<()> {
+
+    let report = Report::new(shapes, inputs);
+
+    match format {
+        ReportFormat::Csv => report.write_csv(out),
+        ReportFormat::Markdown => report.write_markdown(out),
+        ReportFormat::Html => report.write_html(out),
+    }
+}
+
+/// A single statistic drawn from a Summary
+type Statistic = fn(&Summary) -> f64;
+
+const SHAPE_HEADER: [&str; 4] = ["Name", "Dimensions", "Area", "Perimeter"];
+const SUMMARY_HEADER: [&str; 3] = ["Statistic", "Area", "Perimeter"];
+const COUNT_HEADER: [&str; 2] = ["Name", "Count"];
+const INPUT_HEADER: [&str; 4] = ["Input", "Count", "Area", "Perimeter"];
+
+/// Every cell of a report, already formatted as text
+struct Report {
+    shapes: Vec<[String; 4]>,
+    summary: Vec<[String; 3]>,
+    counts: Vec<[String; 2]>,
+
+    /// Empty unless the shapes came from several inputs
+    inputs: Vec<[String; 4]>,
+}
+
+impl Report {
+    fn new(shapes: &ShapeCollection, inputs: &[InputTotals]) -> Self {
+        let shape_rows = shapes.iter()
+            .map(|s| [
+                s.name().to_string(),
+                dimensions(s),
+                number(s.area()),
+                number(s.perimeter()),
+            ])
+            .collect();
+
+        let summary = match (shapes.summary(Shape::area),
+                             shapes.summary(Shape::perimeter)) {
+            (Some(area), Some(perimeter)) => summary_rows(&area, &perimeter),
+            _ => Vec::new(),
+        };
+
+        let mut counts: Vec<[String; 2]> = shapes.count_by_name()
+            .into_iter()
+            .map(|(name, count)| [name.to_string(), count.to_string()])
+            .collect();
+        counts.push(["Total".to_string(), shapes.len().to_string()]);
+
+        Report {
+            shapes: shape_rows,
+            summary,
+            counts,
+            inputs: input_rows(inputs),
+        }
+    }
+
+    fn write_csv<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        write_csv_table(out, &SHAPE_HEADER, &self.shapes)?;
+        writeln!(out)?;
+        write_csv_table(out, &SUMMARY_HEADER, &self.summary)?;
+        writeln!(out)?;
+        write_csv_table(out, &COUNT_HEADER, &self.counts)?;
+
+        if !self.inputs.is_empty() {
+            writeln!(out)?;
+            write_csv_table(out, &INPUT_HEADER, &self.inputs)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_markdown<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "# Shapes Report")?;
+        writeln!(out)?;
+        writeln!(out, "## Shapes")?;
+        writeln!(out)?;
+        write_markdown_table(out, &SHAPE_HEADER, &self.shapes)?;
+        writeln!(out)?;
+        writeln!(out, "## Summary")?;
+        writeln!(out)?;
+        write_markdown_table(out, &SUMMARY_HEADER, &self.summary)?;
+        writeln!(out)?;
+        writeln!(out, "## Counts")?;
+        writeln!(out)?;
+        write_markdown_table(out, &COUNT_HEADER, &self.counts)?;
+
+        if !self.inputs.is_empty() {
+            writeln!(out)?;
+            writeln!(out, "## Inputs")?;
+            writeln!(out)?;
+            write_markdown_table(out, &INPUT_HEADER, &self.inputs)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_html<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "<!DOCTYPE html>")?;
+        writeln!(out, "<html lang=\"en\">")?;
+        writeln!(out, "<head>")?;
+        writeln!(out, "<meta charset=\"utf-8\">")?;
+        writeln!(out, "<title>Shapes Report</title>")?;
+        writeln!(out, "<style>")?;
+        writeln!(out, "body {{ font-family: sans-serif; margin: 2em; }}")?;
+        writeln!(out, "table {{ border-collapse: collapse; margin-bottom: 2em; }}")?;
+        writeln!(out, "th, td {{ border: 1px solid #999; padding: 0.25em 0.75em; }}")?;
+        writeln!(out, "th {{ background: #eee; }}")?;
+        writeln!(out, "td.num {{ text-align: right; }}")?;
+        writeln!(out, "</style>")?;
+        writeln!(out, "</head>")?;
+        writeln!(out, "<body>")?;
+        writeln!(out, "<h1>Shapes Report</h1>")?;
+        writeln!(out, "<h2>Shapes</h2>")?;
+        write_html_table(out, &SHAPE_HEADER, &self.shapes)?;
+        writeln!(out, "<h2>Summary</h2>")?;
+        write_html_table(out, &SUMMARY_HEADER, &self.summary)?;
+        writeln!(out, "<h2>Counts</h2>")?;
+        write_html_table(out, &COUNT_HEADER, &self.counts)?;
+        if !self.inputs.is_empty() {
+            writeln!(out, "<h2>Inputs</h2>")?;
+            write_html_table(out, &INPUT_HEADER, &self.inputs)?;
+        }
+        writeln!(out, "</body>")?;
+        writeln!(out, "</html>")
+    }
+}
+
+fn number(value: f64) -> String {
+    format!("{:.4}", value)
+}
+
+/// Describe a shape's dimensions, e.g., `Base=3.0000; Height=4.0000`
+fn dimensions(shape: &KnownShape) -> String {
+    shape.fields()
+        .iter()
+        .map(|(label, value)| format!("{}={}", label, number(*value)))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+fn summary_rows(area: &Summary, perimeter: &Summary) -> Vec<[String; 3]> {
+    let stats: [(&str, Statistic); 6] = [
+        ("Sum", |s| s.sum),
+        ("Min", |s| s.min),
+        ("Max", |s| s.max),
+        ("Mean", |s| s.mean),
+        ("Median", |s| s.median),
+        ("Std Dev", Summary::std_dev),
+    ];
+
+    let mut rows = vec![[
+        "Count".to_string(),
+        area.count.to_string(),
+        perimeter.count.to_string(),
+    ]];
+
+    rows.extend(stats.iter().map(|(label, stat)| [
+        label.to_string(),
+        number(stat(area)),
+        number(stat(perimeter)),
+    ]));
+
+    rows
+}
+
+/// One row per input, then a Total row (no rows at all without inputs)
+fn input_rows(inputs: &[InputTotals]) -> Vec<[String; 4]> {
+    if inputs.is_empty() {
+        return Vec::new();
+    }
+
+    let total = InputTotals {
+        name: "Total".to_string(),
+        count: inputs.iter().map(|i| i.count).sum(),
+        area: inputs.iter().map(|i| i.area).sum(),
+        perimeter: inputs.iter().map(|i| i.perimeter).sum(),
+    };
+
+    inputs.iter()
+        .chain([&total])
+        .map(|i| [
+            i.name.clone(),
+            i.count.to_string(),
+            number(i.area),
+            number(i.perimeter),
+        ])
+        .collect()
+}
+
+fn write_csv_table<W: Write, const N: usize>(out: &mut W, header: &[&str; N],
+                                             rows: &[[String; N]])
+    -> io::Result<()> {
+
+    writeln!(out, "{}", header.map(csv_escape).join(","))?;
+
+    for row in rows {
+        let cells: Vec<String> = row.iter().map(|c| csv_escape(c)).collect();
+        writeln!(out, "{}", cells.join(","))?;
+    }
+
+    Ok(())
+}
+
+fn write_markdown_table<W: Write, const N: usize>(out: &mut W,
+                                                  header: &[&str; N],
+                                                  rows: &[[String; N]])
+    -> io::Result<()> {
+
+    // Numeric columns are right-aligned
+    let align: Vec<&str> = (0..N)
+        .map(|col| if col == 0 || is_text_column(header[col]) { "---" }
+                   else { "---:" })
+        .collect();
+
+    writeln!(out, "| {} |", header.join(" | "))?;
+    writeln!(out, "|{}|", align.join("|"))?;
+
+    for row in rows {
+        let cells: Vec<String> = row.iter()
+            .map(|c| c.replace('|', "\\|"))
+            .collect();
+        writeln!(out, "| {} |", cells.join(" | "))?;
+    }
+
+    Ok(())
+}
+
+fn write_html_table<W: Write, const N: usize>(out: &mut W, header: &[&str; N],
+                                              rows: &[[String; N]])
+    -> io::Result<()> {
+
+    writeln!(out, "<table>")?;
+    writeln!(out, "<tr>{}</tr>",
+             header.iter()
+                 .map(|h| format!("<th>{}</th>", html_escape(h)))
+                 .collect::<String>())?;
+
+    for row in rows {
+        let cells: String = row.iter()
+            .enumerate()
+            .map(|(col, cell)| {
+                if col == 0 || is_text_column(header[col]) {
+                    format!("<td>{}</td>", html_escape(cell))
+                }
+                else {
+                    format!("<td class=\"num\">{}</td>", html_escape(cell))
+                }
+            })
+            .collect();
+
+        writeln!(out, "<tr>{}</tr>", cells)?;
+    }
+
+    writeln!(out, "</table>")
+}
+
+fn is_text_column(header: &str) -> bool {
+    header == "Dimensions"
+}
+
+pub(crate) fn csv_escape(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    }
+    else {
+        cell.to_string()
+    }
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hamcrest2::prelude::*;
+
+    use crate::circle::Circle;
+    use crate::testing::some_shapes;
+
+    fn report(format: ReportFormat) -> String {
+        let mut out = Vec::new();
+        write_report(&mut out, &some_shapes().into(), format).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_format_from_str() {
+        assert_that!("CSV".parse(), equal_to(Ok(ReportFormat::Csv)));
+        assert_that!("md".parse(), equal_to(Ok(ReportFormat::Markdown)));
+        assert_that!("html".parse(), equal_to(Ok(ReportFormat::Html)));
+        assert!("pdf".parse::<ReportFormat>().is_err());
+    }
+
+    #[test]
+    fn test_csv() {
+        let csv = report(ReportFormat::Csv);
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_that!(lines[0], equal_to("Name,Dimensions,Area,Perimeter"));
+        assert_that!(lines[1], equal_to("Square,Side=2.0000,4.0000,8.0000"));
+        assert!(lines[2].starts_with("Circle,Radius=1.0000;"));
+        assert_that!(lines[3], equal_to(concat!(
+            "Right Triangle,Base=3.0000; Height=4.0000; Hypotenuse=5.0000,",
+            "6.0000,12.0000")));
+        assert_that!(lines[8], equal_to(""));
+        assert_that!(lines[9], equal_to("Statistic,Area,Perimeter"));
+        assert_that!(lines[10], equal_to("Count,7,7"));
+        assert!(csv.contains("\nTotal,7\n"));
+    }
+
+    #[test]
+    fn test_csv_escape() {
+        assert_that!(csv_escape("a,b"), equal_to("\"a,b\""));
+        assert_that!(csv_escape("say \"hi\""), equal_to("\"say \"\"hi\"\"\""));
+        assert_that!(csv_escape("two\nlines"), equal_to("\"two\nlines\""));
+        assert_that!(csv_escape("two\r\nlines"), equal_to("\"two\r\nlines\""));
+        assert_that!(csv_escape("carriage\rreturn"), equal_to("\"carriage\rreturn\""));
+        assert_that!(csv_escape("plain"), equal_to("plain"));
+    }
+
+    #[test]
+    fn test_markdown() {
+        let md = report(ReportFormat::Markdown);
+
+        assert!(md.starts_with("# Shapes Report\n"));
+        assert!(md.contains("| Name | Dimensions | Area | Perimeter |\n"));
+        assert!(md.contains("|---|---|---:|---:|\n"));
+        assert!(md.contains("| Circle | Radius=1.0000; Diameter=2.0000 | "));
+        assert!(md.contains("## Summary"));
+        assert!(md.contains("| Mean | "));
+    }
+
+    #[test]
+    fn test_html() {
+        let html = report(ReportFormat::Html);
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.trim_end().ends_with("</html>"));
+        assert!(html.contains("<style>"));
+        assert!(!html.contains("<link"));
+        assert!(html.contains("<td>Right Triangle</td>"));
+        assert!(html.contains("<td class=\"num\">6.0000</td>"));
+        assert_that!(html.matches("<table>").count(), is(equal_to(3)));
+        assert_that!(html_escape("<a & b>"), equal_to("&lt;a &amp; b&gt;"));
+    }
+
+    #[test]
+    fn test_inputs() {
+        let a: ShapeCollection = some_shapes().into();
+        let b: ShapeCollection = vec![Circle::with_radius(2.0).into()].into();
+        let inputs = [InputTotals::new("a.txt", &a), InputTotals::new("b.txt", &b)];
+
+        let mut combined = a;
+        combined.extend(b);
+
+        let mut out = Vec::new();
+        write_report_with_inputs(&mut out, &combined, &inputs, ReportFormat::Csv)
+            .unwrap();
+        let csv = String::from_utf8(out).unwrap();
+
+        assert!(csv.contains("\nTotal,8\n"));
+        assert!(csv.ends_with(concat!(
+            "\nInput,Count,Area,Perimeter\n",
+            "a.txt,7,53.3616,75.8496\n",
+            "b.txt,1,12.5664,12.5664\n",
+            "Total,8,65.9280,88.4159\n")));
+
+        let mut out = Vec::new();
+        write_report_with_inputs(&mut out, &combined, &inputs, ReportFormat::Html)
+            .unwrap();
+        let html = String::from_utf8(out).unwrap();
+
+        assert_that!(html.matches("<table>").count(), is(equal_to(4)));
+        assert!(html.contains("<td>b.txt</td>"));
+        assert!(!report(ReportFormat::Markdown).contains("## Inputs"));
+    }
+
+    #[test]
+    fn test_empty() {
+        let mut out = Vec::new();
+        write_report(&mut out, &ShapeCollection::new(), ReportFormat::Csv)
+            .unwrap();
+        let csv = String::from_utf8(out).unwrap();
+
+        assert!(csv.starts_with("Name,Dimensions,Area,Perimeter\n\n"));
+        assert!(csv.contains("Total,0"));
+    }
+}
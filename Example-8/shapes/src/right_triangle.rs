@@ -1,4 +1,6 @@
 use crate::shape::Shape;
+use crate::formatter::ShapeFormatter;
+use crate::named_params::{FromNamedParams, NamedParams};
 
 use std::fmt;
 
@@ -43,6 +45,40 @@ impl Shape for RightTriangle {
     fn area(&self) -> f64 {
         0.5 * self.base * self.height
     }
+
+    fn fields(&self) -> Vec<(&'static str, f64)> {
+        vec![
+            ("Base", self.base),
+            ("Height", self.height),
+            ("Hypotenuse", self.hypotenuse()),
+        ]
+    }
+}
+
+impl FromNamedParams for RightTriangle {
+    /// Accept any two of `base`, `height`, `hypotenuse` and `area`
+    fn from_named(params: &NamedParams) -> Option<Self> {
+        let leg = |c: f64, a: f64| Some((c * c - a * a).sqrt())
+            .filter(|b| b.is_finite());
+
+        params.exactly(["base", "height"]).map(|[b, h]| (b, h))
+            .or_else(|| {
+                let [b, c] = params.exactly(["base", "hypotenuse"])?;
+                Some((b, leg(c, b)?))
+            })
+            .or_else(|| {
+                let [h, c] = params.exactly(["height", "hypotenuse"])?;
+                Some((leg(c, h)?, h))
+            })
+            .or_else(|| {
+                params.exactly(["base", "area"]).map(|[b, a]| (b, 2.0 * a / b))
+            })
+            .or_else(|| {
+                params.exactly(["height", "area"])
+                    .map(|[h, a]| (2.0 * a / h, h))
+            })
+            .map(|(b, h)| RightTriangle::with_base_height(b, h))
+    }
 }
 
 impl Default for RightTriangle {
@@ -53,12 +89,7 @@ impl Default for RightTriangle {
 
 impl fmt::Display for RightTriangle {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(f, "{:12}:{:>24}", "Name", self.name())?;
-        writeln!(f, "{:12}:{:>24.4}", "Base", self.base)?;
-        writeln!(f, "{:12}:{:>24.4}", "Height", self.height)?;
-        writeln!(f, "{:12}:{:>24.4}", "Hypotenuse", self.hypotenuse())?;
-        writeln!(f, "{:12}:{:>24.4}", "Perimeter", self.perimeter())?;
-        writeln!(f, "{:12}:{:>24.4}", "Area", self.area())
+        ShapeFormatter::default().write(f, self)
     }
 }
 
@@ -66,7 +97,6 @@ impl fmt::Display for RightTriangle {
 mod tests {
     use super::*;
     use std::f64;
-    use std::ptr;
     use hamcrest2::prelude::*;
 
     #[test]
@@ -142,4 +172,23 @@ mod tests {
 
         assert!(fancy_str.ends_with("\n"));
     }
+
+    #[test]
+    fn test_from_named() {
+        let params = NamedParams::parse("base=4 height=5").unwrap();
+        let fancy = RightTriangle::from_named(&params).unwrap();
+        assert_that!(fancy.base, close_to(4.0, 1e-8));
+        assert_that!(fancy.height, close_to(5.0, 1e-8));
+
+        let params = NamedParams::parse("hypotenuse=5 height=4").unwrap();
+        let fancy = RightTriangle::from_named(&params).unwrap();
+        assert_that!(fancy.base, close_to(3.0, 1e-8));
+
+        let params = NamedParams::parse("base=3 area=6").unwrap();
+        let fancy = RightTriangle::from_named(&params).unwrap();
+        assert_that!(fancy.height, close_to(4.0, 1e-8));
+
+        let params = NamedParams::parse("base=5 hypotenuse=3").unwrap();
+        assert!(RightTriangle::from_named(&params).is_none());
+    }
 }
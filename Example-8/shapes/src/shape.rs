@@ -9,6 +9,10 @@ pub trait Shape {
 
     /// Compute the perimeter of a 2D shape
     fn perimeter(&self) -> f64;
-}
-
 
+    /// List the labeled dimensions (and any derived measurements other than
+    /// perimeter and area) shown when the shape is displayed
+    fn fields(&self) -> Vec<(&'static str, f64)> {
+        Vec::new()
+    }
+}
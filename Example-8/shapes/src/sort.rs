@@ -0,0 +1,416 @@
+//! Multi-key sorting, ranking and top-k selection.
+//!
+//! A [`SortSpec`] lists keys in priority order, e.g., name ascending then
+//! area descending. All orderings are stable (shapes that compare equal keep
+//! their input order) and NaN values are placed explicitly, first or last,
+//! regardless of the key's direction.
+
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::formatter::to_key;
+use crate::known_shape::KnownShape;
+use crate::query;
+use crate::shape::Shape;
+
+/// Something to sort by
+#[derive(Clone, Debug, PartialEq)]
+pub enum SortKey {
+    Name,
+    Area,
+    Perimeter,
+
+    /// A displayed field written as a key (e.g., `radius` or `side_a`).
+    /// Shapes without the field sort as if the value were NaN.
+    Field(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Ascending,
+    Descending,
+}
+
+/// Where NaN (and missing) values go
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NanOrder {
+    First,
+    Last,
+}
+
+/// An ordered list of sort keys.
+///
+/// # Example
+///
+/// ```
+/// use shapes::sort::SortSpec;
+///
+/// let by_hand = SortSpec::new().ascending_by(shapes::sort::SortKey::Name)
+///                              .descending_by(shapes::sort::SortKey::Area);
+/// let parsed = SortSpec::parse("name asc, area desc").unwrap();
+///
+/// assert_eq!(by_hand, parsed);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct SortSpec {
+    keys: Vec<(SortKey, Direction)>,
+    nans: NanOrder,
+}
+
+impl SortSpec {
+    /// Create a spec with no keys (i.e., every shape compares equal)
+    pub fn new() -> Self {
+        SortSpec { keys: Vec::new(), nans: NanOrder::Last }
+    }
+
+    /// Add a key
+    pub fn then_by(mut self, key: SortKey, direction: Direction) -> Self {
+        self.keys.push((key, direction));
+        self
+    }
+
+    pub fn ascending_by(self, key: SortKey) -> Self {
+        self.then_by(key, Direction::Ascending)
+    }
+
+    pub fn descending_by(self, key: SortKey) -> Self {
+        self.then_by(key, Direction::Descending)
+    }
+
+    /// Set where NaN values go (last by default)
+    pub fn nans(mut self, nans: NanOrder) -> Self {
+        self.nans = nans;
+        self
+    }
+
+    /// Parse a comma separated list of `key [asc|desc]` clauses. A clause of
+    /// `nan first` or `nan last` sets the NaN placement.
+    ///
+    /// A key is `name`, `area`, `perimeter` or a field of some shape (e.g.,
+    /// `radius`); anything else is an error rather than a key every shape is
+    /// missing.
+    ///
+    /// # Arguments
+    ///
+    ///  * `text` - e.g., `"name, area desc, nan first"`
+    ///
+    pub fn parse(text: &str) -> Result<SortSpec, String> {
+        let mut spec = SortSpec::new();
+
+        for clause in text.split(',') {
+            let words: Vec<String> = clause.split_whitespace()
+                .map(str::to_lowercase)
+                .collect();
+            let words: Vec<&str> = words.iter().map(String::as_str).collect();
+
+            match words[..] {
+                ["nan", "first"] => spec.nans = NanOrder::First,
+                ["nan", "last"] => spec.nans = NanOrder::Last,
+                [key] => spec = spec.ascending_by(parse_key(key)?),
+                [key, "asc"] => spec = spec.ascending_by(parse_key(key)?),
+                [key, "desc"] => spec = spec.descending_by(parse_key(key)?),
+                _ => return Err(format!("Invalid sort clause '{}'",
+                                        clause.trim())),
+            }
+        }
+
+        Ok(spec)
+    }
+
+    /// Compare two shapes by every key in turn
+    pub fn compare(&self, lhs: &KnownShape, rhs: &KnownShape) -> Ordering {
+        self.keys.iter()
+            .map(|(key, direction)| self.compare_key(key, *direction, lhs, rhs))
+            .find(|ord| *ord != Ordering::Equal)
+            .unwrap_or(Ordering::Equal)
+    }
+
+    fn compare_key(&self, key: &SortKey, direction: Direction,
+                   lhs: &KnownShape, rhs: &KnownShape) -> Ordering {
+
+        let ordering = match key {
+            SortKey::Name => lhs.name().cmp(rhs.name()),
+            _ => {
+                let l = numeric_value(key, lhs);
+                let r = numeric_value(key, rhs);
+
+                match (l.is_nan(), r.is_nan()) {
+                    (true, true) => return Ordering::Equal,
+                    (true, false) => return self.nan_ordering(),
+                    (false, true) => return self.nan_ordering().reverse(),
+                    (false, false) => l.partial_cmp(&r).unwrap(),
+                }
+            }
+        };
+
+        match direction {
+            Direction::Ascending => ordering,
+            Direction::Descending => ordering.reverse(),
+        }
+    }
+
+    /// Ordering of a NaN relative to a number
+    fn nan_ordering(&self) -> Ordering {
+        match self.nans {
+            NanOrder::First => Ordering::Less,
+            NanOrder::Last => Ordering::Greater,
+        }
+    }
+}
+
+impl Default for SortSpec {
+    fn default() -> Self {
+        SortSpec::new()
+    }
+}
+
+fn parse_key(key: &str) -> Result<SortKey, String> {
+    match key {
+        "name" => Ok(SortKey::Name),
+        "area" => Ok(SortKey::Area),
+        "perimeter" => Ok(SortKey::Perimeter),
+        field if query::is_shape_field(field) => Ok(SortKey::Field(field.to_string())),
+        field => Err(format!("Unknown sort field '{}'", field)),
+    }
+}
+
+fn numeric_value(key: &SortKey, shape: &KnownShape) -> f64 {
+    match key {
+        SortKey::Area => shape.area(),
+        SortKey::Perimeter => shape.perimeter(),
+        SortKey::Field(field) => shape.fields().into_iter()
+            .find(|(label, _)| to_key(label) == *field)
+            .map(|(_, value)| value)
+            .unwrap_or(f64::NAN),
+        SortKey::Name => f64::NAN,
+    }
+}
+
+/// Sort shapes in place (stable)
+pub fn sort<T: Borrow<KnownShape>>(shapes: &mut [T], spec: &SortSpec) {
+    shapes.sort_by(|a, b| spec.compare(a.borrow(), b.borrow()));
+}
+
+/// Compute the rank (starting at 1) of each shape. Shapes that compare equal
+/// share a rank and the next rank is skipped (i.e., "1224" ranking).
+pub fn rank<T: Borrow<KnownShape>>(shapes: &[T], spec: &SortSpec)
+    -> Vec<usize> {
+
+    let mut order: Vec<usize> = (0..shapes.len()).collect();
+    order.sort_by(|&a, &b| spec.compare(shapes[a].borrow(),
+                                        shapes[b].borrow()));
+
+    let mut ranks = vec![0; shapes.len()];
+
+    for (pos, &idx) in order.iter().enumerate() {
+        ranks[idx] = match pos {
+            0 => 1,
+            _ => {
+                let prev = order[pos - 1];
+                let tied = spec.compare(shapes[prev].borrow(),
+                                        shapes[idx].borrow()) == Ordering::Equal;
+
+                if tied { ranks[prev] } else { pos + 1 }
+            }
+        };
+    }
+
+    ranks
+}
+
+/// Select the first `k` shapes in sort order without sorting everything.
+///
+/// Works with any iterator (e.g., a `ShapeReader` or `collection.iter()`)
+/// and keeps at most `k` shapes in memory. The result is in sort order and
+/// ties are broken by input order, exactly as a stable sort would.
+pub fn top_k<T, I>(shapes: I, k: usize, spec: &SortSpec) -> Vec<T>
+    where T: Borrow<KnownShape>, I: IntoIterator<Item = T> {
+
+    select(shapes, k, spec, false)
+}
+
+/// Select the last `k` shapes in sort order without sorting everything.
+///
+/// The result is in sort order (i.e., the very last shape comes last).
+pub fn bottom_k<T, I>(shapes: I, k: usize, spec: &SortSpec) -> Vec<T>
+    where T: Borrow<KnownShape>, I: IntoIterator<Item = T> {
+
+    let mut selected = select(shapes, k, spec, true);
+    selected.reverse();
+    selected
+}
+
+/// Bounded heap selection. With `reversed` the comparison (including the
+/// input order tie-break) is flipped, which selects the bottom `k`.
+fn select<T, I>(shapes: I, k: usize, spec: &SortSpec, reversed: bool)
+    -> Vec<T>
+    where T: Borrow<KnownShape>, I: IntoIterator<Item = T> {
+
+    if k == 0 {
+        return Vec::new();
+    }
+
+    let mut heap = BinaryHeap::with_capacity(k + 1);
+
+    for (index, shape) in shapes.into_iter().enumerate() {
+        heap.push(Ranked { shape, index, spec, reversed });
+
+        // The "largest" entry is the one furthest down the order
+        if heap.len() > k {
+            heap.pop();
+        }
+    }
+
+    heap.into_sorted_vec().into_iter().map(|r| r.shape).collect()
+}
+
+struct Ranked<'a, T> {
+    shape: T,
+    index: usize,
+    spec: &'a SortSpec,
+    reversed: bool,
+}
+
+impl<T: Borrow<KnownShape>> Ord for Ranked<'_, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let ordering = self.spec
+            .compare(self.shape.borrow(), other.shape.borrow())
+            .then(self.index.cmp(&other.index));
+
+        if self.reversed { ordering.reverse() } else { ordering }
+    }
+}
+
+impl<T: Borrow<KnownShape>> PartialOrd for Ranked<'_, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Borrow<KnownShape>> PartialEq for Ranked<'_, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T: Borrow<KnownShape>> Eq for Ranked<'_, T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hamcrest2::prelude::*;
+
+    use crate::square::Square;
+    use crate::testing;
+
+    /// The shared shapes, then a square with no area and a copy of the first
+    /// square
+    fn some_shapes() -> Vec<KnownShape> {
+        let mut shapes = testing::some_shapes();
+        shapes.push(Square::with_side(f64::NAN).into());
+        shapes.push(Square::with_side(2.0).into());
+
+        shapes
+    }
+
+    fn areas<T: Borrow<KnownShape>>(shapes: &[T]) -> Vec<String> {
+        shapes.iter()
+            .map(|s| format!("{}:{:.1}", s.borrow().name(), s.borrow().area()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let spec = SortSpec::parse("Name, area DESC, nan first").unwrap();
+        let expected = SortSpec::new()
+            .ascending_by(SortKey::Name)
+            .descending_by(SortKey::Area)
+            .nans(NanOrder::First);
+
+        assert_that!(spec, equal_to(expected));
+        assert!(SortSpec::parse("area sideways").is_err());
+
+        assert_that!(SortSpec::parse("side_a desc"),
+                     equal_to(Ok(SortSpec::new().descending_by(SortKey::Field("side_a".into())))));
+        assert_that!(SortSpec::parse("name, raduis"),
+                     equal_to(Err("Unknown sort field 'raduis'".to_string())));
+    }
+
+    #[test]
+    fn test_sort_multi_key() {
+        let mut shapes = some_shapes();
+        sort(&mut shapes, &SortSpec::parse("name, area desc").unwrap());
+
+        assert_that!(areas(&shapes), equal_to(vec![
+            "Circle:12.6", "Circle:3.1", "Equilateral Triangle:1.7", "Right Triangle:6.0",
+            "Square:16.0", "Square:4.0", "Square:4.0", "Square:NaN", "Triangle:9.9",
+        ]));
+    }
+
+    #[test]
+    fn test_sort_nan_first() {
+        let mut shapes = some_shapes();
+        sort(&mut shapes, &SortSpec::parse("area, nan first").unwrap());
+
+        assert_that!(areas(&shapes)[0].as_str(), equal_to("Square:NaN"));
+        assert_that!(areas(&shapes)[8].as_str(), equal_to("Square:16.0"));
+    }
+
+    #[test]
+    fn test_sort_missing_field() {
+        let mut shapes = some_shapes();
+        sort(&mut shapes, &SortSpec::parse("radius desc").unwrap());
+
+        assert_that!(areas(&shapes)[0].as_str(), equal_to("Circle:12.6"));
+        assert_that!(areas(&shapes)[1].as_str(), equal_to("Circle:3.1"));
+    }
+
+    #[test]
+    fn test_rank() {
+        let shapes = some_shapes();
+        let ranks = rank(&shapes, &SortSpec::parse("area desc").unwrap());
+
+        assert_that!(ranks, equal_to(vec![5, 7, 4, 8, 2, 3, 1, 9, 5]));
+    }
+
+    #[test]
+    fn test_top_k_matches_stable_sort() {
+        let spec = SortSpec::parse("name, area desc").unwrap();
+
+        let mut sorted: Vec<&KnownShape> = Vec::new();
+        let shapes = some_shapes();
+        sorted.extend(shapes.iter());
+        sort(&mut sorted, &spec);
+
+        for k in 0..=10 {
+            let top = top_k(shapes.iter(), k, &spec);
+            let expected = &sorted[..k.min(sorted.len())];
+
+            // Identical shapes must come back in input order
+            let top_ptrs: Vec<_> = top.iter().map(|s| *s as *const _).collect();
+            let exp_ptrs: Vec<_> = expected.iter()
+                .map(|s| *s as *const _)
+                .collect();
+            assert_that!(top_ptrs, equal_to(exp_ptrs));
+
+            let bottom = bottom_k(shapes.iter(), k, &spec);
+            let expected = &sorted[sorted.len() - k.min(sorted.len())..];
+            let bottom_ptrs: Vec<_> = bottom.iter()
+                .map(|s| *s as *const _)
+                .collect();
+            let exp_ptrs: Vec<_> = expected.iter()
+                .map(|s| *s as *const _)
+                .collect();
+            assert_that!(bottom_ptrs, equal_to(exp_ptrs));
+        }
+    }
+
+    #[test]
+    fn test_top_k_owned() {
+        let spec = SortSpec::parse("area desc").unwrap();
+        let top = top_k(some_shapes(), 2, &spec);
+
+        assert_that!(areas(&top), equal_to(vec!["Square:16.0", "Circle:12.6"]));
+    }
+}
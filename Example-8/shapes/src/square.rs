@@ -1,4 +1,6 @@
 use crate::shape::Shape;
+use crate::formatter::ShapeFormatter;
+use crate::named_params::{FromNamedParams, NamedParams};
 
 use std::fmt;
 
@@ -39,6 +41,25 @@ impl Shape for Square {
     fn perimeter(&self) -> f64 {
         4.0 * self.side
     }
+
+    fn fields(&self) -> Vec<(&'static str, f64)> {
+        vec![
+            ("Side", self.side),
+        ]
+    }
+}
+
+impl FromNamedParams for Square {
+    /// Accept `side`, `perimeter`, `area` or `diagonal`
+    fn from_named(params: &NamedParams) -> Option<Self> {
+        params.exactly(["side"]).map(|[s]| s)
+            .or_else(|| params.exactly(["perimeter"]).map(|[p]| p / 4.0))
+            .or_else(|| params.exactly(["area"]).map(|[a]| a.sqrt()))
+            .or_else(|| {
+                params.exactly(["diagonal"]).map(|[d]| d / 2_f64.sqrt())
+            })
+            .map(Square::with_side)
+    }
 }
 
 impl Default for Square {
@@ -49,10 +70,7 @@ impl Default for Square {
 
 impl fmt::Display for Square {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(f, "{:12}:{:>24}", "Name", self.name())?;
-        writeln!(f, "{:12}:{:>24.4}", "Side", self.side)?;
-        writeln!(f, "{:12}:{:>24.4}", "Perimeter", self.perimeter())?;
-        writeln!(f, "{:12}:{:>24.4}", "Area", self.area())
+        ShapeFormatter::default().write(f, self)
     }
 }
 
@@ -81,7 +99,7 @@ mod tests {
     #[test]
     fn test_area() {
         let generic = Square::new();
-        let fancy = Square::with_side(2 as f64);
+        let fancy = Square::with_side(2_f64);
 
         assert_that!(generic.area(), close_to(1.0, 1e-6));
 
@@ -131,4 +149,18 @@ mod tests {
 
         assert!(fancy_str.ends_with("\n"));
     }
+
+    #[test]
+    fn test_from_named() {
+        let params = NamedParams::parse("area=16").unwrap();
+        let fancy = Square::from_named(&params).unwrap();
+        assert_that!(fancy.side, close_to(4.0, 1e-8));
+
+        let params = NamedParams::parse("perimeter=12").unwrap();
+        let fancy = Square::from_named(&params).unwrap();
+        assert_that!(fancy.side, close_to(3.0, 1e-8));
+
+        let params = NamedParams::parse("side=1 area=1").unwrap();
+        assert!(Square::from_named(&params).is_none());
+    }
 }
@@ -0,0 +1,389 @@
+//! Read shapes from SVG documents.
+//!
+//! Each rendered shape element becomes one result:
+//!
+//!   * `<circle>` - Circle
+//!   * `<ellipse>` - Circle (if both radii are equal once transformed)
+//!   * `<rect>` - Square (if its sides are equal once transformed)
+//!   * `<polygon>` - a triangle (Equilateral, Right or general) or Square,
+//!     recognized from its vertices
+//!
+//! The `transform` attributes of an element and its ancestors are applied
+//! first, so rotated, translated and uniformly scaled shapes are read at
+//! their drawn size. Lengths are in user units (`viewBox` is ignored).
+//!
+//! Other drawing elements (e.g., `<path>` or `<line>`) and shapes that no
+//! `KnownShape` can represent are reported as errors carrying the element's
+//! `id`. Elements inside `<defs>` and similar containers are not rendered and
+//! are skipped.
+
+use std::error::Error;
+use std::fmt;
+
+use roxmltree::{Document, Node};
+
+use crate::circle::Circle;
+use crate::geometry::{self, Point};
+use crate::known_shape::KnownShape;
+
+/// Relative tolerance used when comparing transformed lengths
+const TOLERANCE: f64 = 1e-6;
+
+/// Containers whose content is never rendered directly
+const HIDDEN: &[&str] = &["defs", "clipPath", "mask", "marker", "pattern", "symbol"];
+
+/// Drawing elements that are reported as unsupported
+const UNSUPPORTED: &[&str] = &["path", "line", "polyline", "text", "image", "use"];
+
+/// An element that could not be read as a shape
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SvgError {
+    /// Element name, e.g., `rect`
+    pub element: String,
+
+    /// The element's `id` attribute, if it has one
+    pub id: Option<String>,
+
+    pub message: String,
+}
+
+impl fmt::Display for SvgError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.id {
+            Some(id) => write!(f, "<{} id=\"{}\">: {}", self.element, id, self.message),
+            None => write!(f, "<{}>: {}", self.element, self.message),
+        }
+    }
+}
+
+impl Error for SvgError {}
+
+/// An affine transformation `[a b c d e f]`, mapping `(x, y)` to
+/// `(a x + c y + e, b x + d y + f)`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform([f64; 6]);
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform([1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+
+    /// Parse an SVG `transform` attribute, e.g.,
+    /// `translate(10, 20) rotate(45)`
+    pub fn parse(text: &str) -> Result<Transform, String> {
+        let mut transform = Transform::IDENTITY;
+        let mut rest = text.trim();
+
+        while !rest.is_empty() {
+            let open = rest.find('(')
+                .ok_or(format!("invalid transform '{}'", text))?;
+            let close = rest[open..].find(')')
+                .map(|i| open + i)
+                .ok_or(format!("invalid transform '{}'", text))?;
+
+            let name = rest[..open].trim();
+            let args = numbers(&rest[open + 1..close])?;
+
+            transform = transform.then(Transform::from_function(name, &args)?);
+            rest = rest[close + 1..].trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        }
+
+        Ok(transform)
+    }
+
+    fn from_function(name: &str, args: &[f64]) -> Result<Transform, String> {
+        let transform = match (name, args) {
+            ("matrix", &[a, b, c, d, e, f]) => Transform([a, b, c, d, e, f]),
+            ("translate", &[tx]) => Transform([1.0, 0.0, 0.0, 1.0, tx, 0.0]),
+            ("translate", &[tx, ty]) => Transform([1.0, 0.0, 0.0, 1.0, tx, ty]),
+            ("scale", &[s]) => Transform([s, 0.0, 0.0, s, 0.0, 0.0]),
+            ("scale", &[sx, sy]) => Transform([sx, 0.0, 0.0, sy, 0.0, 0.0]),
+            ("rotate", &[angle]) => Transform::rotate(angle),
+            ("rotate", &[angle, cx, cy]) => {
+                Transform([1.0, 0.0, 0.0, 1.0, cx, cy])
+                    .then(Transform::rotate(angle))
+                    .then(Transform([1.0, 0.0, 0.0, 1.0, -cx, -cy]))
+            }
+            ("skewX", &[angle]) => {
+                Transform([1.0, 0.0, angle.to_radians().tan(), 1.0, 0.0, 0.0])
+            }
+            ("skewY", &[angle]) => {
+                Transform([1.0, angle.to_radians().tan(), 0.0, 1.0, 0.0, 0.0])
+            }
+            _ => {
+                return Err(format!("invalid transform function {}({} arguments)",
+                                   name, args.len()));
+            }
+        };
+
+        Ok(transform)
+    }
+
+    fn rotate(degrees: f64) -> Transform {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+
+        Transform([cos, sin, -sin, cos, 0.0, 0.0])
+    }
+
+    /// Apply `self` after `inner`, i.e., `self * inner`
+    pub fn then(&self, inner: Transform) -> Transform {
+        let [a, b, c, d, e, f] = self.0;
+        let [p, q, r, s, t, u] = inner.0;
+
+        Transform([
+            a * p + c * q,
+            b * p + d * q,
+            a * r + c * s,
+            b * r + d * s,
+            a * t + c * u + e,
+            b * t + d * u + f,
+        ])
+    }
+
+    pub fn apply(&self, (x, y): Point) -> Point {
+        let [a, b, c, d, e, f] = self.0;
+
+        (a * x + c * y + e, b * x + d * y + f)
+    }
+
+    /// The semi-axes of the ellipse that an axis-aligned ellipse with radii
+    /// `rx` and `ry` becomes, longest first
+    fn semi_axes(&self, rx: f64, ry: f64) -> (f64, f64) {
+        let [a, b, c, d, _, _] = self.0;
+        let (p, q, r, s) = (a * rx, b * rx, c * ry, d * ry);
+
+        // Singular values of the 2x2 matrix [p r; q s]
+        let mean = ((p + s) / 2.0).hypot((q - r) / 2.0);
+        let spread = ((p - s) / 2.0).hypot((q + r) / 2.0);
+
+        (mean + spread, (mean - spread).abs())
+    }
+}
+
+/// Read every shape in an SVG document.
+///
+/// Return an error if `text` is not well-formed XML; otherwise, return one
+/// result per drawing element, in document order.
+pub fn read_svg(text: &str) -> Result<Vec<Result<KnownShape, SvgError>>, String> {
+    let document = Document::parse(text).map_err(|err| err.to_string())?;
+
+    let results = document.descendants()
+        .filter(|node| node.is_element() && !hidden(node))
+        .filter_map(|node| {
+            let element = node.tag_name().name();
+
+            let result = match element {
+                "circle" | "ellipse" | "rect" | "polygon" => read_element(&node),
+                name if UNSUPPORTED.contains(&name) => {
+                    Err(format!("<{}> is not supported", name))
+                }
+                _ => return None,
+            };
+
+            Some(result.map_err(|message| SvgError {
+                element: element.to_string(),
+                id: node.attribute("id").map(str::to_string),
+                message,
+            }))
+        })
+        .collect();
+
+    Ok(results)
+}
+
+fn hidden(node: &Node) -> bool {
+    node.ancestors().skip(1).any(|a| HIDDEN.contains(&a.tag_name().name()))
+}
+
+/// The combined transform of `node` and its ancestors
+fn transform(node: &Node) -> Result<Transform, String> {
+    node.ancestors()
+        .filter_map(|n| n.attribute("transform"))
+        .try_fold(Transform::IDENTITY, |inner, text| {
+            Ok(Transform::parse(text)?.then(inner))
+        })
+}
+
+fn read_element(node: &Node) -> Result<KnownShape, String> {
+    let transform = transform(node)?;
+
+    match node.tag_name().name() {
+        "circle" => {
+            let r = length(node, "r")?;
+            read_ellipse(&transform, r, r)
+        }
+        "ellipse" => read_ellipse(&transform, length(node, "rx")?, length(node, "ry")?),
+        "rect" => {
+            let (x, y) = (optional_length(node, "x")?, optional_length(node, "y")?);
+            let (w, h) = (length(node, "width")?, length(node, "height")?);
+
+            let corners = [(x, y), (x + w, y), (x + w, y + h), (x, y + h)];
+            read_polygon(&transform, &corners)
+                .map_err(|_| format!("{} x {} rectangle is not a square", w, h))
+        }
+        _ => {
+            let points = node.attribute("points")
+                .ok_or("missing attribute 'points'")?;
+            let coords = numbers(points)?;
+
+            if coords.len() % 2 != 0 {
+                return Err("odd number of coordinates in 'points'".to_string());
+            }
+
+            let points: Vec<Point> = coords.chunks(2)
+                .map(|xy| (xy[0], xy[1]))
+                .collect();
+
+            read_polygon(&transform, &points)
+        }
+    }
+}
+
+fn read_ellipse(transform: &Transform, rx: f64, ry: f64) -> Result<KnownShape, String> {
+    let (major, minor) = transform.semi_axes(rx, ry);
+
+    if major - minor <= TOLERANCE * major {
+        Ok(Circle::with_radius(major).into())
+    }
+    else {
+        Err(format!("ellipse with radii {} and {} is not a circle", major, minor))
+    }
+}
+
+fn read_polygon(transform: &Transform, points: &[Point]) -> Result<KnownShape, String> {
+    let points: Vec<Point> = points.iter()
+        .map(|&p| transform.apply(p))
+        .collect();
+
+    geometry::recognize(&points)
+}
+
+/// A required length attribute
+fn length(node: &Node, name: &str) -> Result<f64, String> {
+    let value = node.attribute(name)
+        .ok_or(format!("missing attribute '{}'", name))?;
+
+    let number = value.trim().strip_suffix("px").unwrap_or(value.trim());
+    number.parse::<f64>()
+        .map_err(|_| format!("invalid {} '{}'", name, value))
+}
+
+/// A length attribute that defaults to 0
+fn optional_length(node: &Node, name: &str) -> Result<f64, String> {
+    match node.attribute(name) {
+        Some(_) => length(node, name),
+        None => Ok(0.0),
+    }
+}
+
+/// Numbers separated by whitespace and/or commas
+fn numbers(text: &str) -> Result<Vec<f64>, String> {
+    text.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|token| !token.is_empty())
+        .map(|token| token.parse::<f64>().map_err(|_| format!("invalid number '{}'", token)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hamcrest2::prelude::*;
+
+    use crate::shape::Shape;
+
+    fn svg(body: &str) -> String {
+        format!(r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 100 100">{}</svg>"#,
+                body)
+    }
+
+    fn read(body: &str) -> Vec<Result<KnownShape, SvgError>> {
+        read_svg(&svg(body)).unwrap()
+    }
+
+    #[test]
+    fn test_elements() {
+        let shapes = read(r#"
+            <circle cx="10" cy="10" r="5"/>
+            <rect x="1" y="2" width="3px" height="3"/>
+            <polygon points="0,0 3,0 0,4"/>
+            <polygon points="0 0, 4 0, 2 1"/>
+            <ellipse rx="2" ry="2"/>
+            <title>Not a shape</title>
+        "#);
+
+        let names: Vec<&str> = shapes.iter()
+            .map(|s| s.as_ref().unwrap().name())
+            .collect();
+
+        assert_that!(names, equal_to(vec!["Circle", "Square", "Right Triangle",
+                                          "Triangle", "Circle"]));
+        assert_that!(shapes[0].as_ref().unwrap().area(),
+                     close_to(25.0 * std::f64::consts::PI, 1e-9));
+        assert_that!(shapes[1].as_ref().unwrap().area(), close_to(9.0, 1e-9));
+        assert_that!(shapes[2].as_ref().unwrap().perimeter(), close_to(12.0, 1e-9));
+    }
+
+    #[test]
+    fn test_transforms() {
+        let shapes = read(r#"
+            <g transform="scale(2)">
+                <rect width="3" height="3" transform="rotate(30, 1, 1)"/>
+                <g transform="translate(5 5) matrix(0 1 -1 0 0 0)">
+                    <circle r="1.5"/>
+                    <ellipse rx="1" ry="1" transform="scale(1, 2)"/>
+                </g>
+            </g>
+            <rect width="2" height="1" transform="scale(1 2)"/>
+        "#);
+
+        assert_that!(shapes[0].as_ref().unwrap().area(), close_to(36.0, 1e-9));
+        assert_that!(shapes[1].as_ref().unwrap().params(), equal_to(vec![3.0]));
+        assert!(shapes[2].is_err());
+        assert_that!(shapes[3].as_ref().unwrap().area(), close_to(4.0, 1e-9));
+    }
+
+    #[test]
+    fn test_transform_parse() {
+        let t = Transform::parse("translate(10,20) rotate(90)").unwrap();
+        let (x, y) = t.apply((1.0, 0.0));
+
+        assert_that!(x, close_to(10.0, 1e-12));
+        assert_that!(y, close_to(21.0, 1e-12));
+
+        assert_that!(Transform::parse("  ").unwrap(), equal_to(Transform::IDENTITY));
+        assert!(Transform::parse("scale(1, 2, 3)").is_err());
+        assert!(Transform::parse("twist(4)").is_err());
+        assert!(Transform::parse("scale(2").is_err());
+        assert!(Transform::parse(")(").is_err());
+        assert!(Transform::parse("scale(2) )(").is_err());
+    }
+
+    #[test]
+    fn test_errors_carry_ids() {
+        let shapes = read(r#"
+            <rect id="banner" width="4" height="2"/>
+            <ellipse id="egg" rx="2" ry="3"/>
+            <path id="curve" d="M 0 0 L 1 1"/>
+            <polygon points="0 0 1 0 1 1 0.5 2 0 1"/>
+            <circle id="bad" r="big"/>
+            <circle id="twisted" r="1" transform=")("/>
+            <defs><circle id="hidden" r="1"/></defs>
+        "#);
+
+        let errors: Vec<String> = shapes.iter()
+            .map(|s| s.as_ref().unwrap_err().to_string())
+            .collect();
+
+        assert_that!(errors, equal_to(vec![
+            "<rect id=\"banner\">: 4 x 2 rectangle is not a square".to_string(),
+            "<ellipse id=\"egg\">: ellipse with radii 3 and 2 is not a circle".to_string(),
+            "<path id=\"curve\">: <path> is not supported".to_string(),
+            "<polygon>: 5-sided polygon is not a known shape".to_string(),
+            "<circle id=\"bad\">: invalid r 'big'".to_string(),
+            "<circle id=\"twisted\">: invalid transform ')('".to_string(),
+        ]));
+    }
+
+    #[test]
+    fn test_not_xml() {
+        assert!(read_svg("<svg><circle r='1'></svg>").is_err());
+    }
+}
@@ -0,0 +1,396 @@
+//! Columnar (struct-of-arrays) storage for large numbers of shapes.
+//!
+//! A `Vec<KnownShape>` spends 32 bytes on every shape--enough for the
+//! largest variant (`Triangle`) plus the discriminant--even when the shape is
+//! a `Square` with a single `f64`. `ShapeTable` keeps one dense `Vec<f64>` per
+//! kind and per field instead, plus one byte per shape for its kind, e.g., a
+//! `Square` costs 9 bytes.
+//!
+//! The batch kernels ([`ShapeTable::areas`] and [`ShapeTable::perimeters`])
+//! run one loop per kind directly over the column slices, with no per-shape
+//! dispatch and no shape structs, which the compiler can auto-vectorize. The
+//! formulas are written exactly as in the shape types, so the results are
+//! identical. The results are then put back in insertion order.
+//!
+//! This is a library type; the command line program keeps its shapes in a
+//! [`ShapeCollection`](crate::collection::ShapeCollection), which it needs
+//! for display anyway, so the program's own numbers in
+//! `stats-time/rust-mem-stats.txt` are unchanged. The table's numbers, from
+//! `examples/table_stats.rs`, are in `stats-time/rust-table-stats.txt`.
+
+use crate::circle::Circle;
+use crate::equilateral_triangle::EquilateralTriangle;
+use crate::known_shape::KnownShape;
+use crate::right_triangle::RightTriangle;
+use crate::square::Square;
+use crate::triangle::Triangle;
+
+// Kind of each shape, as given by `KnownShape::kind_index`
+const TRIANGLE: u8 = 0;
+const RIGHT_TRIANGLE: u8 = 1;
+const EQUILATERAL_TRIANGLE: u8 = 2;
+const SQUARE: u8 = 3;
+const CIRCLE: u8 = 4;
+const NUM_KINDS: usize = 5;
+
+/// One dense column per field
+#[derive(Clone, Debug, PartialEq)]
+struct Columns<const N: usize> {
+    fields: [Vec<f64>; N],
+}
+
+impl<const N: usize> Columns<N> {
+    fn new() -> Self {
+        Columns { fields: std::array::from_fn(|_| Vec::new()) }
+    }
+
+    fn push(&mut self, row: [f64; N]) {
+        for (column, value) in self.fields.iter_mut().zip(row) {
+            column.push(value);
+        }
+    }
+
+    fn shrink_to_fit(&mut self) {
+        for column in self.fields.iter_mut() {
+            column.shrink_to_fit();
+        }
+    }
+}
+
+/// Shapes stored column by column
+///
+/// # Example
+///
+/// ```
+/// use std::io::BufReader;
+///
+/// use shapes::factory::ShapeReader;
+/// use shapes::table::ShapeTable;
+///
+/// let input = "Square; 2\nCircle; 1\nSquare; 3\n";
+/// let table: ShapeTable = ShapeReader::new(BufReader::new(input.as_bytes()))
+///     .collect();
+///
+/// assert_eq!(table.len(), 3);
+/// assert_eq!(table.areas()[0], 4.0);
+/// assert_eq!(table.areas()[2], 9.0);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShapeTable {
+    /// Kind of each shape, in insertion order
+    kinds: Vec<u8>,
+
+    triangles: Columns<3>,
+    right_triangles: Columns<2>,
+    equilateral_triangles: Columns<1>,
+    squares: Columns<1>,
+    circles: Columns<1>,
+}
+
+impl Default for ShapeTable {
+    fn default() -> Self {
+        ShapeTable::new()
+    }
+}
+
+impl ShapeTable {
+    /// Create an empty table
+    pub fn new() -> Self {
+        ShapeTable {
+            kinds: Vec::new(),
+            triangles: Columns::new(),
+            right_triangles: Columns::new(),
+            equilateral_triangles: Columns::new(),
+            squares: Columns::new(),
+            circles: Columns::new(),
+        }
+    }
+
+    /// Add a copy of `shape` after the shapes already in the table
+    pub fn push(&mut self, shape: &KnownShape) {
+        match shape {
+            KnownShape::Triangle(t) => {
+                self.triangles.push([t.side_a, t.side_b, t.side_c])
+            }
+            KnownShape::RightTriangle(t) => {
+                self.right_triangles.push([t.base, t.height])
+            }
+            KnownShape::EquilateralTriangle(t) => {
+                self.equilateral_triangles.push([t.side])
+            }
+            KnownShape::Square(s) => self.squares.push([s.side]),
+            KnownShape::Circle(c) => self.circles.push([c.radius]),
+        }
+
+        self.kinds.push(shape.kind_index() as u8);
+    }
+
+    /// Total number of shapes
+    pub fn len(&self) -> usize {
+        self.kinds.len()
+    }
+
+    /// Check whether the table holds no shapes
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Release any unused capacity
+    pub fn shrink_to_fit(&mut self) {
+        self.kinds.shrink_to_fit();
+        self.triangles.shrink_to_fit();
+        self.right_triangles.shrink_to_fit();
+        self.equilateral_triangles.shrink_to_fit();
+        self.squares.shrink_to_fit();
+        self.circles.shrink_to_fit();
+    }
+
+    /// Compute the area of every shape, in insertion order
+    pub fn areas(&self) -> Vec<f64> {
+        let [a, b, c] = &self.triangles.fields;
+        let [base, height] = &self.right_triangles.fields;
+        let [equilateral_side] = &self.equilateral_triangles.fields;
+        let [square_side] = &self.squares.fields;
+        let [radius] = &self.circles.fields;
+
+        self.in_order([
+            // Heron's formula
+            map3(a, b, c, |a, b, c| {
+                let s = (a + b + c) / 2.0;
+                (s * (s - a) * (s - b) * (s - c)).sqrt()
+            }),
+            map2(base, height, |b, h| 0.5 * b * h),
+            map1(equilateral_side, |s| 3_f64.sqrt() / 4_f64 * s.powi(2)),
+            map1(square_side, |s| s.powi(2)),
+            map1(radius, |r| std::f64::consts::PI * r * r),
+        ])
+    }
+
+    /// Compute the perimeter of every shape, in insertion order
+    pub fn perimeters(&self) -> Vec<f64> {
+        let [a, b, c] = &self.triangles.fields;
+        let [base, height] = &self.right_triangles.fields;
+        let [equilateral_side] = &self.equilateral_triangles.fields;
+        let [square_side] = &self.squares.fields;
+        let [radius] = &self.circles.fields;
+
+        self.in_order([
+            map3(a, b, c, |a, b, c| a + b + c),
+            map2(base, height, |b, h| b + h + (b.powi(2) + h.powi(2)).sqrt()),
+            map1(equilateral_side, |s| 3.0 * s),
+            map1(square_side, |s| 4.0 * s),
+            map1(radius, |r| 2.0 * std::f64::consts::PI * r),
+        ])
+    }
+
+    /// Rebuild each shape, in insertion order
+    pub fn iter(&self) -> impl Iterator<Item = KnownShape> + '_ {
+        let mut triangles = self.triangles();
+        let mut right_triangles = self.right_triangles();
+        let mut equilateral_triangles = self.equilateral_triangles();
+        let mut squares = self.squares();
+        let mut circles = self.circles();
+
+        // Each kind has exactly as many rows as entries in `kinds`
+        self.kinds.iter().map(move |&kind| match kind {
+            TRIANGLE => triangles.next().unwrap().into(),
+            RIGHT_TRIANGLE => right_triangles.next().unwrap().into(),
+            EQUILATERAL_TRIANGLE => equilateral_triangles.next().unwrap().into(),
+            SQUARE => squares.next().unwrap().into(),
+            CIRCLE => circles.next().unwrap().into(),
+            _ => unreachable!("unknown kind {}", kind),
+        })
+    }
+
+    /// Convert back to individual shapes, in insertion order
+    pub fn to_shapes(&self) -> Vec<KnownShape> {
+        self.iter().collect()
+    }
+
+    /// Interleave per-kind results (each in insertion order) back into
+    /// insertion order
+    fn in_order(&self, by_kind: [Vec<f64>; NUM_KINDS]) -> Vec<f64> {
+        // A table of one kind is already in order
+        if let Some(only) = by_kind.iter().position(|values| values.len() == self.len()) {
+            return by_kind.into_iter().nth(only).unwrap_or_default();
+        }
+
+        let mut next = [0; NUM_KINDS];
+
+        self.kinds.iter()
+            .map(|&kind| {
+                let kind = kind as usize;
+                next[kind] += 1;
+                by_kind[kind][next[kind] - 1]
+            })
+            .collect()
+    }
+
+    fn triangles(&self) -> impl Iterator<Item = Triangle> + '_ {
+        let [a, b, c] = &self.triangles.fields;
+        a.iter().zip(b).zip(c)
+            .map(|((&a, &b), &c)| Triangle::with_sides(a, b, c))
+    }
+
+    fn right_triangles(&self) -> impl Iterator<Item = RightTriangle> + '_ {
+        let [base, height] = &self.right_triangles.fields;
+        base.iter().zip(height)
+            .map(|(&b, &h)| RightTriangle::with_base_height(b, h))
+    }
+
+    fn equilateral_triangles(&self) -> impl Iterator<Item = EquilateralTriangle> + '_ {
+        let [side] = &self.equilateral_triangles.fields;
+        side.iter().map(|&s| EquilateralTriangle::with_side(s))
+    }
+
+    fn squares(&self) -> impl Iterator<Item = Square> + '_ {
+        let [side] = &self.squares.fields;
+        side.iter().map(|&s| Square::with_side(s))
+    }
+
+    fn circles(&self) -> impl Iterator<Item = Circle> + '_ {
+        let [radius] = &self.circles.fields;
+        radius.iter().map(|&r| Circle::with_radius(r))
+    }
+}
+
+/// Apply `f` to each value of one column
+fn map1<F>(x: &[f64], f: F) -> Vec<f64>
+    where F: Fn(f64) -> f64 {
+
+    x.iter().map(|&x| f(x)).collect()
+}
+
+/// Apply `f` to each row of two columns of the same length
+fn map2<F>(x: &[f64], y: &[f64], f: F) -> Vec<f64>
+    where F: Fn(f64, f64) -> f64 {
+
+    x.iter().zip(y).map(|(&x, &y)| f(x, y)).collect()
+}
+
+/// Apply `f` to each row of three columns of the same length
+fn map3<F>(x: &[f64], y: &[f64], z: &[f64], f: F) -> Vec<f64>
+    where F: Fn(f64, f64, f64) -> f64 {
+
+    x.iter().zip(y).zip(z).map(|((&x, &y), &z)| f(x, y, z)).collect()
+}
+
+impl From<&[KnownShape]> for ShapeTable {
+    fn from(shapes: &[KnownShape]) -> Self {
+        shapes.iter().collect()
+    }
+}
+
+impl FromIterator<KnownShape> for ShapeTable {
+    fn from_iter<I: IntoIterator<Item = KnownShape>>(iter: I) -> Self {
+        let mut table = ShapeTable::new();
+        table.extend(iter);
+        table
+    }
+}
+
+impl<'a> FromIterator<&'a KnownShape> for ShapeTable {
+    fn from_iter<I: IntoIterator<Item = &'a KnownShape>>(iter: I) -> Self {
+        let mut table = ShapeTable::new();
+
+        for shape in iter {
+            table.push(shape);
+        }
+
+        table
+    }
+}
+
+impl Extend<KnownShape> for ShapeTable {
+    fn extend<I: IntoIterator<Item = KnownShape>>(&mut self, iter: I) {
+        for shape in iter {
+            self.push(&shape);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hamcrest2::prelude::*;
+
+    use crate::shape::Shape;
+    use crate::testing::some_shapes;
+
+    fn to_strings<'a, I>(shapes: I) -> Vec<String>
+        where I: IntoIterator<Item = &'a KnownShape> {
+
+        shapes.into_iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_kind_constants() {
+        let kinds: Vec<u8> = some_shapes().iter()
+            .map(|s| s.kind_index() as u8)
+            .collect();
+
+        assert_that!(kinds, equal_to(vec![SQUARE, CIRCLE, RIGHT_TRIANGLE, EQUILATERAL_TRIANGLE,
+                                          CIRCLE, TRIANGLE, SQUARE]));
+        assert_that!(KnownShape::KNOWN_NAMES.len(), is(equal_to(NUM_KINDS)));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let shapes = some_shapes();
+        let table = ShapeTable::from(shapes.as_slice());
+
+        assert_that!(table.len(), is(equal_to(7)));
+        assert!(!table.is_empty());
+
+        // Mixed kinds come back in their original order
+        assert_that!(to_strings(&table.to_shapes()), equal_to(to_strings(&shapes)));
+
+        let mut doubled = shapes.clone();
+        doubled.extend(shapes.iter().rev().cloned());
+        let table: ShapeTable = doubled.iter().collect();
+
+        assert_that!(to_strings(&table.to_shapes()), equal_to(to_strings(&doubled)));
+    }
+
+    #[test]
+    fn test_kernels_match_shape() {
+        let shapes = some_shapes();
+        let table: ShapeTable = shapes.iter().collect();
+
+        let areas: Vec<f64> = shapes.iter().map(|s| s.area()).collect();
+        let perimeters: Vec<f64> = shapes.iter().map(|s| s.perimeter()).collect();
+
+        assert_that!(table.areas(), equal_to(areas));
+        assert_that!(table.perimeters(), equal_to(perimeters));
+        // A table of a single kind
+        let circles: Vec<KnownShape> = shapes.into_iter()
+            .filter(|s| s.name() == "Circle")
+            .collect();
+        let table: ShapeTable = circles.iter().collect();
+
+        let pi = std::f64::consts::PI;
+
+        assert_that!(table.areas(), equal_to(vec![pi, 4.0 * pi]));
+        assert_that!(table.perimeters().len(), is(equal_to(2)));
+    }
+
+    #[test]
+    fn test_empty() {
+        let mut table = ShapeTable::default();
+        table.shrink_to_fit();
+
+        assert!(table.is_empty());
+        assert!(table.areas().is_empty());
+        assert_that!(table.iter().count(), is(equal_to(0)));
+    }
+
+    #[test]
+    fn test_extend() {
+        let mut table = ShapeTable::new();
+        table.extend(some_shapes());
+        table.extend(some_shapes());
+
+        assert_that!(table.len(), is(equal_to(14)));
+        assert_that!(table.squares.fields[0].len(), is(equal_to(4)));
+    }
+}
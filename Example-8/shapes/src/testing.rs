@@ -0,0 +1,31 @@
+//! Shapes shared by the unit tests.
+
+use crate::circle::Circle;
+use crate::equilateral_triangle::EquilateralTriangle;
+use crate::known_shape::KnownShape;
+use crate::right_triangle::RightTriangle;
+use crate::square::Square;
+use crate::triangle::Triangle;
+
+/// Every kind of shape, with two squares and two circles (in that order):
+///
+/// | Shape                    | Area    | Perimeter |
+/// |--------------------------|---------|-----------|
+/// | Square 2                 | 4       | 8         |
+/// | Circle 1                 | 3.1416  | 6.2832    |
+/// | Right Triangle 3 4       | 6       | 12        |
+/// | Equilateral Triangle 2   | 1.7321  | 6         |
+/// | Circle 2                 | 12.5664 | 12.5664   |
+/// | Triangle 4 5 6           | 9.9216  | 15        |
+/// | Square 4                 | 16      | 16        |
+pub fn some_shapes() -> Vec<KnownShape> {
+    vec![
+        Square::with_side(2.0).into(),
+        Circle::with_radius(1.0).into(),
+        RightTriangle::with_base_height(3.0, 4.0).into(),
+        EquilateralTriangle::with_side(2.0).into(),
+        Circle::with_radius(2.0).into(),
+        Triangle::with_sides(4.0, 5.0, 6.0).into(),
+        Square::with_side(4.0).into(),
+    ]
+}
@@ -1,4 +1,6 @@
 use crate::shape::Shape;
+use crate::formatter::ShapeFormatter;
+use crate::named_params::{FromNamedParams, NamedParams};
 
 use std::fmt;
 
@@ -42,6 +44,23 @@ impl Shape for Triangle {
 
         (s * (s - self.side_a) * (s - self.side_b) * (s - self.side_c)).sqrt()
     }
+
+    fn fields(&self) -> Vec<(&'static str, f64)> {
+        vec![
+            ("Side A", self.side_a),
+            ("Side B", self.side_b),
+            ("Side C", self.side_c),
+        ]
+    }
+}
+
+impl FromNamedParams for Triangle {
+    /// Accept `side_a`, `side_b` and `side_c` (or `a`, `b` and `c`)
+    fn from_named(params: &NamedParams) -> Option<Self> {
+        params.exactly(["side_a", "side_b", "side_c"])
+            .or_else(|| params.exactly(["a", "b", "c"]))
+            .map(|[a, b, c]| Triangle::with_sides(a, b, c))
+    }
 }
 
 impl Default for Triangle {
@@ -52,12 +71,7 @@ impl Default for Triangle {
 
 impl fmt::Display for Triangle {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(f, "{:12}:{:>24}", "Name", self.name())?;
-        writeln!(f, "{:12}:{:>24.4}", "Side A", self.side_a)?;
-        writeln!(f, "{:12}:{:>24.4}", "Side B", self.side_b)?;
-        writeln!(f, "{:12}:{:>24.4}", "Side C", self.side_c)?;
-        writeln!(f, "{:12}:{:>24.4}", "Perimeter", self.perimeter())?;
-        writeln!(f, "{:12}:{:>24.4}", "Area", self.area())
+        ShapeFormatter::default().write(f, self)
     }
 }
 
@@ -65,7 +79,6 @@ impl fmt::Display for Triangle {
 mod tests {
     use super::*;
     use std::f64;
-    use std::ptr;
     use hamcrest2::prelude::*;
 
     #[test]
@@ -143,5 +156,15 @@ mod tests {
         assert!(fancy_str.ends_with("\n"));
     }
 
-
+    #[test]
+    fn test_from_named() {
+        let params = NamedParams::parse("side_c=5 side_a=3 side_b=4").unwrap();
+        let fancy = Triangle::from_named(&params).unwrap();
+        assert_that!(fancy.side_a, close_to(3.0, 1e-8));
+        assert_that!(fancy.side_b, close_to(4.0, 1e-8));
+        assert_that!(fancy.side_c, close_to(5.0, 1e-8));
+
+        let params = NamedParams::parse("a=3 b=4").unwrap();
+        assert!(Triangle::from_named(&params).is_none());
+    }
 }
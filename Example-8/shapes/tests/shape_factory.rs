@@ -1,12 +1,14 @@
 #[cfg(test)]
-#[macro_use]
 extern crate hamcrest2;
 extern crate shapes;
 
 use hamcrest2::prelude::*;
 
 use shapes::factory;
+use shapes::shape::Shape;
 use shapes::known_shape::KnownShape;
+use shapes::locale::Locale;
+use shapes::sort::{self, SortSpec};
 use shapes::square::Square;
 use shapes::circle::Circle;
 use shapes::triangle::Triangle;
@@ -69,6 +71,25 @@ fn test_create_with() {
     assert_that!(a_shape.to_string(), equal_to(ref_shape.to_string()));
 }
 
+#[test]
+fn test_create_with_wrong_dims() {
+    assert!(factory::create_with("Circle", &[]).is_none());
+    assert!(factory::create_with("Triangle", &[3.0, 4.0]).is_none());
+    assert!(factory::create_with("Right Triangle", &[3.0]).is_none());
+    assert!(factory::create_with("Square", &[1.0, 2.0]).is_none());
+
+    // Lines with too few (or too many) dimensions are skipped like unknown
+    // names
+    let raw_str = "Circle;\n\
+                   Triangle; 3 4\n\
+                   Square; 2\n\
+                   Equilateral Triangle; 1 2\n";
+    let some_shapes = factory::read_shapes_with(BufReader::new(raw_str.as_bytes()));
+
+    assert_that!(some_shapes.len(), is(equal_to(1)));
+    assert_that!(some_shapes[0].name(), equal_to("Square"));
+}
+
 #[test]
 fn test_read_shapes() {
 
@@ -145,3 +166,354 @@ fn test_known_shapes_display() {
     let a_shape = KnownShape::Circle(Circle::new());
     assert_that!(circle.to_string(), equal_to(a_shape.to_string()));
 }
+
+#[test]
+fn test_known_names() {
+    assert_that!(KnownShape::KNOWN_NAMES.len(),
+                 is(equal_to(factory::number_known())));
+
+    for name in KnownShape::KNOWN_NAMES {
+        assert!(factory::is_known(name));
+
+        let a_shape = factory::create(name).unwrap();
+        assert_that!(a_shape.name(), equal_to(*name));
+    }
+
+    assert!(KnownShape::from_name("1337 Haxor").is_none());
+}
+
+#[test]
+fn test_from_dims() {
+    let tri = Triangle::from(&[3.0, 4.0, 5.0][..]);
+    let ref_shape = Triangle::with_sides(3.0, 4.0, 5.0);
+    assert_that!(tri.to_string(), equal_to(ref_shape.to_string()));
+
+    let a_shape: KnownShape = Circle::from(&[2.0][..]).into();
+    assert_that!(a_shape.to_string(),
+                 equal_to(Circle::with_radius(2.0).to_string()));
+}
+
+#[test]
+fn test_read_shapes_with_locale() {
+    let raw_str = r#"
+        Dreieck; 3 4 5
+        Rechtwinkliges Dreieck; 3 4
+        Quadrat; 5
+        Circle; 5
+        Cuadrado; 5"#;
+
+    let str_reader = StringReader::new(raw_str);
+    let str_reader = BufReader::new(str_reader);
+
+    let german = Locale::by_code("de").unwrap();
+    let some_shapes = factory::read_shapes_with_locale(str_reader, &german);
+
+    assert_that!(some_shapes.len(), is(equal_to(4)));
+    assert_that!(some_shapes[0].name(), equal_to("Triangle"));
+    assert_that!(some_shapes[1].name(), equal_to("Right Triangle"));
+    assert_that!(some_shapes[2].name(), equal_to("Square"));
+    assert_that!(some_shapes[3].name(), equal_to("Circle"));
+}
+
+#[test]
+fn test_read_shapes_with_named() {
+    let raw_str = r#"
+        Right Triangle; base=4 height=5
+        Right Triangle; 4 5
+        Circle; diameter=10
+        Square; area=16
+        Square; volume=16
+        Equilateral Triangle; side=3 area=4"#;
+
+    let str_reader = StringReader::new(raw_str);
+    let str_reader = BufReader::new(str_reader);
+
+    let some_shapes = factory::read_shapes_with(str_reader);
+    assert_that!(some_shapes.len(), is(equal_to(4)));
+
+    assert_that!(some_shapes[0].to_string(),
+                 equal_to(some_shapes[1].to_string()));
+    assert_that!(some_shapes[2].to_string(),
+                 equal_to(Circle::with_radius(5.0).to_string()));
+    assert_that!(some_shapes[3].to_string(),
+                 equal_to(Square::with_side(4.0).to_string()));
+}
+
+#[test]
+fn test_shape_reader_top_k() {
+    let raw_str = r#"
+        Square; 2
+        Circle; 1
+        1337 Haxor; 1
+        Square; 3
+        Circle; 2"#;
+
+    let reader = factory::ShapeReader::new(BufReader::new(
+        StringReader::new(raw_str)));
+    let spec = SortSpec::parse("name desc, area desc").unwrap();
+    let top = sort::top_k(reader, 3, &spec);
+
+    assert_that!(top.len(), is(equal_to(3)));
+    assert_that!(top[0].to_string(),
+                 equal_to(Square::with_side(3.0).to_string()));
+    assert_that!(top[1].to_string(),
+                 equal_to(Square::with_side(2.0).to_string()));
+    assert_that!(top[2].to_string(),
+                 equal_to(Circle::with_radius(2.0).to_string()));
+
+    let all: Vec<KnownShape> = factory::ShapeReader::new(BufReader::new(
+        StringReader::new(raw_str))).collect();
+    let read = factory::read_shapes_with(BufReader::new(
+        StringReader::new(raw_str)));
+    assert_that!(format!("{:?}", all), equal_to(format!("{:?}", read)));
+}
+
+/// Deterministic pseudo-random shapes with dimensions spanning many orders
+/// of magnitude
+fn generated_shapes(count: usize) -> Vec<KnownShape> {
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = move || {
+        state = state.wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        state >> 11
+    };
+
+    (0..count)
+        .map(|_| {
+            let kind = next() as usize % KnownShape::KNOWN_NAMES.len();
+            let name = KnownShape::KNOWN_NAMES[kind];
+            let dims: Vec<f64> = (0..KnownShape::PARAM_COUNTS[kind])
+                .map(|_| {
+                    let mantissa = next() as f64 / (1u64 << 53) as f64;
+                    let exponent = (next() % 13) as i32 - 6;
+                    mantissa * 10f64.powi(exponent)
+                })
+                .collect();
+
+            factory::create_with(name, &dims).unwrap()
+        })
+        .collect()
+}
+
+fn write_read(shapes: &[KnownShape], precision: Option<usize>) -> Vec<KnownShape> {
+    let mut out = Vec::new();
+    factory::write_shapes_with(&mut out, shapes, precision).unwrap();
+
+    factory::read_shapes_with(BufReader::new(out.as_slice()))
+}
+
+#[test]
+fn test_write_shapes_with() {
+    let shapes = vec![
+        KnownShape::from(RightTriangle::with_base_height(3.0, 4.25)),
+        KnownShape::from(Circle::with_radius(1.0 / 3.0)),
+    ];
+
+    let mut out = Vec::new();
+    factory::write_shapes_with(&mut out, &shapes, Some(2)).unwrap();
+    assert_that!(String::from_utf8(out).unwrap(),
+                 equal_to("Right Triangle; 3.00 4.25\nCircle; 0.33\n".to_string()));
+
+    let mut out = Vec::new();
+    factory::write_shapes_with(&mut out, &shapes, None).unwrap();
+    assert_that!(String::from_utf8(out).unwrap(),
+                 equal_to("Right Triangle; 3 4.25\nCircle; 0.3333333333333333\n".to_string()));
+}
+
+#[test]
+fn test_write_read_round_trip() {
+    let shapes = generated_shapes(1000);
+    let first = write_read(&shapes, None);
+    let second = write_read(&first, None);
+
+    for read in [&first, &second] {
+        assert_that!(read.len(), is(equal_to(shapes.len())));
+
+        for (actual, expected) in read.iter().zip(shapes.iter()) {
+            assert_that!(actual.name(), equal_to(expected.name()));
+
+            let bits = |shape: &KnownShape| -> Vec<u64> {
+                shape.params().iter().map(|dim| dim.to_bits()).collect()
+            };
+            assert_that!(bits(actual), equal_to(bits(expected)));
+        }
+    }
+}
+
+#[test]
+fn test_comments_and_quoted_names() {
+    let raw_str = r#"#!shapes v2
+        # A comment line
+        "Right Triangle"; 3 4   # trailing comment
+        "Square" ; 2
+        Circle; 1 # radius
+        "Square; 5
+        "Unknown Shape"; 1
+        Square; 3; 4"#;
+
+    let shapes = factory::read_shapes_with(BufReader::new(StringReader::new(raw_str)));
+    let names: Vec<&str> = shapes.iter().map(|s| s.name()).collect();
+
+    assert_that!(names, equal_to(vec!["Right Triangle", "Square", "Circle"]));
+    assert_that!(shapes[0].area(), close_to(6.0, 1e-9));
+    assert_that!(shapes[2].area(), close_to(std::f64::consts::PI, 1e-9));
+}
+
+#[test]
+fn test_old_files_unchanged() {
+    let expected: Vec<KnownShape> = vec![
+        Triangle::with_sides(4.0, 4.0, 4.0).into(),
+        RightTriangle::with_base_height(4.0, 5.0).into(),
+        EquilateralTriangle::with_side(3.0).into(),
+        Square::with_side(9.0).into(),
+        Circle::with_radius(5.0).into(),
+    ];
+
+    let old = std::fs::read_to_string("inputShapes.txt").unwrap();
+    let streamed = factory::read_shapes_with(BufReader::new(old.as_bytes()));
+    let from_file = factory::read_shapes_file("inputShapes.txt", &Locale::english()).unwrap();
+
+    for actual in [streamed, from_file] {
+        assert_that!(format!("{:?}", actual), equal_to(format!("{:?}", expected)));
+    }
+}
+
+#[test]
+fn test_stream_directive_errors() {
+    let read = |raw_str: &str| {
+        let mut reader = factory::ShapeReader::new(BufReader::new(raw_str.as_bytes()));
+        std::iter::from_fn(|| reader.try_next()).collect::<Vec<_>>()
+    };
+
+    // Reading goes on after a line that can not be followed
+    let results = read("Square; 1\ninclude \"other.txt\"\nSquare; 2\n");
+    assert_that!(results.len(), is(equal_to(3)));
+    assert!(results[0].is_ok());
+    assert_that!(results[1].clone().unwrap_err(),
+                 equal_to("line 2: 'include' can only be used in files".to_string()));
+    assert!(results[2].is_ok());
+
+    let results = read("#!shapes v3\nSquare; 1\n");
+    assert_that!(results.len(), is(equal_to(2)));
+    assert_that!(results[0].clone().unwrap_err(),
+                 equal_to("line 1: unsupported format version 3 (newest is 2)".to_string()));
+}
+
+#[test]
+fn test_read_shapes_with_directives() {
+    // Lines that can not be followed are skipped like unknown names
+    let raw_str = "#!shapes v3\n\
+                   Square; 1\n\
+                   include \"other.txt\"\n\
+                   include other.txt\n\
+                   Circle; 1\n";
+
+    let some_shapes = factory::read_shapes_with(BufReader::new(raw_str.as_bytes()));
+    let names: Vec<&str> = some_shapes.iter().map(|s| s.name()).collect();
+    assert_that!(names, equal_to(vec!["Square", "Circle"]));
+
+    let reader = factory::ShapeReader::new(BufReader::new(raw_str.as_bytes()));
+    assert_that!(reader.count(), is(equal_to(2)));
+}
+
+/// A scratch directory holding `files`, removed when dropped
+struct ScratchDir(std::path::PathBuf);
+
+impl ScratchDir {
+    fn new(name: &str, files: &[(&str, &str)]) -> Self {
+        let dir = std::env::temp_dir()
+            .join(format!("shapes-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(dir.join("parts")).unwrap();
+
+        for (file, contents) in files {
+            std::fs::write(dir.join(file), contents).unwrap();
+        }
+
+        ScratchDir(dir)
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn test_includes() {
+    let dir = ScratchDir::new("includes", &[
+        ("main.txt", "#!shapes v2\nSquare; 1\ninclude \"parts/a.txt\" # first part\nCircle; 1\n"),
+        ("parts/a.txt", "Square; 2\ninclude \"b.txt\"\ninclude \"b.txt\"\n"),
+        ("parts/b.txt", "#!shapes v1\nSquare; 3\n"),
+    ]);
+
+    let shapes = factory::read_shapes_file(dir.0.join("main.txt"), &Locale::english())
+        .unwrap();
+    let areas: Vec<f64> = shapes.iter().map(|s| s.area()).collect();
+
+    assert_that!(areas, equal_to(vec![1.0, 4.0, 9.0, 9.0, std::f64::consts::PI]));
+}
+
+#[test]
+fn test_include_errors() {
+    let dir = ScratchDir::new("include-errors", &[
+        ("cycle.txt", "Square; 1\ninclude \"parts/loop.txt\"\n"),
+        ("parts/loop.txt", "include \"../cycle.txt\"\n"),
+        ("missing.txt", "include \"nowhere.txt\"\n"),
+        ("unquoted.txt", "Square; 1\ninclude nowhere.txt\n"),
+        ("future.txt", "#!shapes v3\nSquare; 1\n"),
+        ("header.txt", "#!shapes two\n"),
+        ("later.txt", "Square; 1\ninclude \"nowhere.txt\"\n"),
+    ]);
+
+    let read = |file: &str| {
+        factory::read_shapes_file(dir.0.join(file), &Locale::english()).unwrap_err()
+    };
+
+    let err = read("cycle.txt");
+    assert!(err.starts_with("include cycle: "), "{}", err);
+    assert!(err.ends_with("cycle.txt"), "{}", err);
+    assert!(err.contains("loop.txt"), "{}", err);
+
+    assert!(read("missing.txt").contains("nowhere.txt"));
+    assert!(read("unquoted.txt")
+        .ends_with("unquoted.txt:2: expected a quoted file name after 'include'"));
+    assert!(read("future.txt")
+        .ends_with("future.txt:1: unsupported format version 3 (newest is 2)"));
+    assert!(read("header.txt").ends_with("header.txt:1: invalid header '#!shapes two'"));
+
+    // Included files are only opened when their directive is reached
+    let lines = factory::ShapeLines::open(&dir.0.join("later.txt")).unwrap();
+    let mut reader = factory::ShapeReader::from_lines(lines, Locale::english());
+
+    assert_that!(reader.try_next().unwrap().unwrap().area(), close_to(1.0, 1e-9));
+    assert!(reader.try_next().unwrap().unwrap_err().contains("nowhere.txt"));
+    assert!(reader.try_next().is_none());
+}
+
+#[test]
+fn test_cli_keeps_valid_shapes() {
+    let dir = ScratchDir::new("cli", &[
+        ("good.txt", "Square; 2\nCircle; 1\n"),
+        ("bad.txt", "Square; 3\nTriangle; 3 4\nEquilateral Triangle; 2\n"),
+    ]);
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_shapes"))
+        .arg(dir.0.join("good.txt"))
+        .arg(dir.0.join("bad.txt"))
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(output.status.success(), "{}", stderr);
+
+    // The bad line is reported with its file, and every other shape is kept
+    assert!(stderr.contains("bad.txt:2: Triangle takes 3 dimension(s), found 2"),
+            "{}", stderr);
+    assert!(stdout.contains("Total                   :           4"), "{}", stdout);
+    assert!(stdout.lines().any(|line| line.contains("good.txt") && line.contains(" 2 ")),
+            "{}", stdout);
+    assert!(stdout.lines().any(|line| line.contains("bad.txt") && line.contains(" 2 ")),
+            "{}", stdout);
+}